@@ -1,58 +1,230 @@
-use colored::ColoredString;
+use std::{fs::{self, DirEntry}, path::{Path, PathBuf}, process, time::{SystemTime, UNIX_EPOCH}};
+
+use clap::Subcommand;
+use colored::{ColoredString, Colorize};
 use serde::Deserialize;
 
-use crate::{config_manager::{self, Configuration}, formatter::CrabFetchColor};
+use crate::{config_manager::{self, Configuration}, formatter::{self, CrabFetchColor}, util};
 
 #[derive(Deserialize)]
 pub struct AsciiConfiguration {
     pub display: bool,
     pub side: String,
+    pub override_distro: String,
+    pub random_from_dir: String,
+    pub size: String,
+    pub small_below_columns: u16,
     pub colors: Vec<CrabFetchColor>,
+    pub gradient: bool,
+    pub gradient_horizontal: bool,
     pub margin: u16,
+    pub image_path: String,
+    pub image_width: u16,
+    pub image_height: u16,
+}
+
+#[derive(Subcommand)]
+pub enum AsciiCommand {
+    /// Lists every distro there's ASCII for, both built in and installed to a data dir.
+    List,
+    /// Shows the ASCII that would be used for a distro and where it comes from. Defaults to the
+    /// detected distro.
+    Show {
+        distro: Option<String>,
+    },
+    /// Exports a distro's ASCII to a file, as a starting point for your own.
+    Export {
+        distro: String,
+        path: String,
+    },
+}
+
+// Picks a random file out of the directory, if there is one
+// There's no need for a proper RNG here so this just uses the time & PID
+fn random_ascii_from_dir(dir: &str) -> Option<String> {
+    if dir.is_empty() {
+        return None;
+    }
+
+    let dir: String = shellexpand::tilde(dir).to_string();
+    let mut files: Vec<PathBuf> = fs::read_dir(dir).ok()?
+        .filter_map(|x: Result<DirEntry, _>| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.is_file())
+        .collect();
+    if files.is_empty() {
+        return None;
+    }
+    files.sort();
+
+    let nanos: u32 = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.subsec_nanos()).unwrap_or(0);
+    let index: usize = (nanos ^ process::id()) as usize % files.len();
+    fs::read_to_string(&files[index]).ok()
 }
 
 // Return type is the ascii & the maximum length of it
-pub fn get_ascii(os: &str) -> (String, u16) {
-    // Will first confirm if theres a ascii override file
-    let user_override: Option<String> = config_manager::check_for_ascii_override();
-    if user_override.is_some() {
+pub fn get_ascii(os: &str, config: &Configuration) -> (String, u16) {
+    let os: &str = &os.replace('"', "").to_lowercase();
+    if let Some((_, user_override)) = custom_ascii(os, config) {
+        let user_override: String = color_ascii(&user_override, config);
         let mut length: u16 = 0;
-        user_override.as_ref().unwrap().split('\n').for_each(|x| {
-            let len: usize = x.chars().count();
+        user_override.split('\n').for_each(|x| {
+            let len: usize = strip_ansi_escapes::strip_str(x).chars().count();
             if len > length as usize { length = len as u16 }
         });
-        return (user_override.unwrap(), length)
+        return (user_override, length)
     }
-    let os: &str = &os.replace('"', "").to_lowercase();
 
-    let ascii: (&str, u16) = match os {
-        "arch" => ARCH,
-        "debian" => DEBIAN,
-        "ubuntu" => UBUNTU,
-        "fedora" => FEDORA,
-        "void" => VOID,
-        "endeavouros" => ENDEAVOUR,
-        "linuxmint" => MINT,
-        "elementary" => ELEMENTARY,
-        "zorin" => ZORIN,
-        "manjaro" => MANJARO,
-        "pop" => POPOS,
-        "opensuse-tumbleweed" => OPENSUSE,
-        "opensuse-leap" => OPENSUSE,
-        "bazzite" => BAZZITE,
-        "rocky" => ROCKYLINUX,
-        "kali" => KALI,
-        "almalinux" => ALMA,
-        "android" => ANDROID,
-        "garuda" => GARUDA,
-        _ => ("", 0)
-    };
+    let ascii: (&str, u16) = bundled_ascii(os, use_small_ascii(config)).unwrap_or(("", 0));
 
     // I blame rust not letting me make const strings
     let ascii_string: String = ascii.0.to_string();
     (ascii_string, ascii.1)
 }
 
+// Any ASCII that isn't built in, along with where it came from
+// First checks if theres a ascii directory to pick from or a ascii override file, then for any
+// that's been installed for the distro, e.g by the distro's own package
+fn custom_ascii(os: &str, config: &Configuration) -> Option<(String, String)> {
+    if let Some(r) = random_ascii_from_dir(&config.ascii.random_from_dir) {
+        return Some((format!("a random file from {}", config.ascii.random_from_dir), r));
+    }
+    if let Some(r) = config_manager::check_for_ascii_override() {
+        return Some(("the ascii file in your config directory".to_string(), r));
+    }
+    let path: PathBuf = installed_ascii_path(os, use_small_ascii(config))?;
+    let ascii: String = fs::read_to_string(&path).ok()?;
+    Some((path.display().to_string(), ascii))
+}
+
+fn bundled_ascii(os: &str, small: bool) -> Option<BundledAscii> {
+    let (_, normal, small_ascii) = BUNDLED_ASCII.iter().find(|x| x.0 == os)?;
+    Some(if small { *small_ascii } else { *normal })
+}
+
+// ASCII that's been installed to crabfetch/ascii/<distro> in one of the data dirs, e.g
+// /usr/share/crabfetch/ascii/arch, with <distro>_small being used for the small size if it's there
+fn installed_ascii_path(os: &str, small: bool) -> Option<PathBuf> {
+    if os.is_empty() || os.contains('/') {
+        return None;
+    }
+
+    small.then(|| config_manager::find_file_in_data_dirs(&format!("ascii/{}_small", os)))
+        .flatten()
+        .or_else(|| config_manager::find_file_in_data_dirs(&format!("ascii/{}", os)))
+}
+
+// The `crabfetch ascii` subcommands
+// The detected distro is only used by show, when no distro's given
+pub fn run_command(command: &AsciiCommand, config: &Configuration, detected_distro: Option<String>) -> Result<(), String> {
+    match command {
+        AsciiCommand::List => {
+            println!("Built in:");
+            let mut bundled: Vec<&str> = BUNDLED_ASCII.iter().map(|x| x.0).collect();
+            bundled.sort();
+            for distro in bundled {
+                println!("  {}", distro);
+            }
+
+            let installed: Vec<PathBuf> = config_manager::find_all_in_data_dirs("ascii").iter()
+                .filter_map(|x| fs::read_dir(x).ok())
+                .flat_map(|x| x.filter_map(|y| y.ok()).map(|y| y.path()))
+                .filter(|x| x.is_file())
+                .collect();
+            if !installed.is_empty() {
+                println!();
+                println!("Installed:");
+                for path in installed {
+                    let name: String = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    println!("  {} ({})", name, path.display());
+                }
+            }
+        },
+        AsciiCommand::Show { distro } => {
+            let distro: String = match distro.clone().or(detected_distro) {
+                Some(r) => r.replace('"', "").to_lowercase(),
+                None => return Err("Unable to detect your distro, pass one in to show it's ASCII instead.".to_string()),
+            };
+            if let Some((source, ascii)) = custom_ascii(&distro, config) {
+                println!("Using {} for {}:", source, distro);
+                println!("{}", color_ascii(&ascii, config));
+                return Ok(());
+            }
+            match bundled_ascii(&distro, use_small_ascii(config)) {
+                Some(r) => {
+                    println!("Using the built in ASCII for {}:", distro);
+                    println!("{}", r.0);
+                },
+                None => return Err(format!("No ASCII found for {}. Run `crabfetch ascii list` to see what's available.", distro)),
+            }
+        },
+        AsciiCommand::Export { distro, path } => {
+            let distro: String = distro.replace('"', "").to_lowercase();
+            let small: bool = config.ascii.size == "small";
+            let ascii: String = match installed_ascii_path(&distro, small).and_then(|x| fs::read_to_string(x).ok()) {
+                Some(r) => r,
+                None => match bundled_ascii(&distro, small) {
+                    Some(r) => r.0.to_string(),
+                    None => return Err(format!("No ASCII found for {}. Run `crabfetch ascii list` to see what's available.", distro)),
+                },
+            };
+
+            let path: String = shellexpand::tilde(path).to_string();
+            let path: &Path = Path::new(&path);
+            if path.exists() {
+                return Err(format!("Path already exists: {}", path.display()));
+            }
+            if let Err(e) = fs::write(path, format!("{}\n", ascii.trim_end_matches('\n'))) {
+                return Err(format!("Unable to write to {}: {}", path.display(), e));
+            }
+            println!("Exported the ASCII for {} to {}", distro, path.display());
+            println!("To use it, move it to ascii in your config directory (e.g ~/.config/CrabFetch/ascii)");
+        },
+    }
+
+    Ok(())
+}
+
+// Whether to use the small version of the distro's ASCII, either as it's been asked for or the
+// terminal's too narrow for the normal one
+fn use_small_ascii(config: &Configuration) -> bool {
+    if config.ascii.size == "small" {
+        return true;
+    }
+    if config.ascii.small_below_columns == 0 {
+        return false;
+    }
+
+    match util::terminal_columns() {
+        Some(r) => r < config.ascii.small_below_columns,
+        None => false,
+    }
+}
+
+// Replaces any color placeholders in the user's ASCII, so they can do multi-colored logos
+// Each line is colored by itself, as they get split up and printed alongside the modules, so the
+// last color used is carried on to the next line
+fn color_ascii(ascii: &str, config: &Configuration) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_color: Option<String> = None;
+    for line in ascii.split('\n') {
+        let mut line: String = line.to_string();
+        if let Some(color) = &current_color {
+            line.insert_str(0, color);
+        }
+        if let Some(index) = line.rfind("{color-") {
+            if let Some(end) = line[index..].find('}') {
+                current_color = Some(line[index..index + end + 1].to_string());
+            }
+        }
+        // Double braces in ASCII are part of the art, not escapes
+        let line: String = line.replace("{{", "{{{{").replace("}}", "}}}}");
+        lines.push(formatter::replace_color_placeholders(&line, config));
+    }
+
+    lines.join("\n")
+}
+
 pub fn get_ascii_line(current_line: usize, ascii_split: &[&str], target_length: &u16, config: &Configuration) -> String {
     let percentage: f32 = current_line as f32 / ascii_split.len() as f32;
     let index: u8 = (((config.ascii.colors.len() - 1) as f32) * percentage).round() as u8;
@@ -61,11 +233,20 @@ pub fn get_ascii_line(current_line: usize, ascii_split: &[&str], target_length:
     if ascii_split.len() > current_line {
         line = ascii_split[current_line].to_string();
     }
-    let remainder: u16 = target_length - (line.chars().count() as u16);
+    // Stripped as image logos can have their own colours in
+    let remainder: u16 = target_length - (strip_ansi_escapes::strip_str(&line).chars().count() as u16);
     for _ in 0..remainder {
         line.push(' ');
     }
 
+    // Lines with their own colors in (custom ASCII with color placeholders, or images) are left
+    // alone, as we'd end up coloring the escape codes
+    if current_line < ascii_split.len() && config.ascii.gradient && !line.contains('\x1b') {
+        if let Some(r) = gradient_line(&line, current_line, ascii_split.len(), config) {
+            return r;
+        }
+    }
+
     if current_line < ascii_split.len() {
         let colored: ColoredString = config.ascii.colors.get(index as usize).unwrap().color_string(&line);
         return colored.to_string();
@@ -73,9 +254,58 @@ pub fn get_ascii_line(current_line: usize, ascii_split: &[&str], target_length:
     line
 }
 
+// Smoothly blends between the ASCII colors, either down the whole art or across each line
+fn gradient_line(line: &str, current_line: usize, line_count: usize, config: &Configuration) -> Option<String> {
+    if !config.ascii.gradient_horizontal {
+        let position: f32 = current_line as f32 / (line_count.max(2) - 1) as f32;
+        let (r, g, b) = formatter::gradient_color(&config.ascii.colors, position)?;
+        return Some(line.truecolor(r, g, b).to_string());
+    }
+
+    let length: usize = line.chars().count();
+    let mut colored: String = String::new();
+    for (i, c) in line.chars().enumerate() {
+        // No point coloring the padding
+        if c == ' ' {
+            colored.push(c);
+            continue;
+        }
+        let position: f32 = i as f32 / (length.max(2) - 1) as f32;
+        let (r, g, b) = formatter::gradient_color(&config.ascii.colors, position)?;
+        colored.push_str(&c.to_string().truecolor(r, g, b).to_string());
+    }
+
+    Some(colored)
+}
+
 // Define art down below here
 // All distro ASCII's are generated from here; https://www.text-image.com/convert/ascii.html
 // I suck at ASCII art, and want to use smaller ones than the other fetch defaults.
+// The ASCII itself, and how wide it is
+type BundledAscii = (&'static str, u16);
+// Every distro with built in ASCII, as (distro id, normal, small)
+const BUNDLED_ASCII: &[(&str, BundledAscii, BundledAscii)] = &[
+    ("arch", ARCH, ARCH_SMALL),
+    ("debian", DEBIAN, DEBIAN_SMALL),
+    ("ubuntu", UBUNTU, UBUNTU_SMALL),
+    ("fedora", FEDORA, FEDORA_SMALL),
+    ("void", VOID, VOID_SMALL),
+    ("endeavouros", ENDEAVOUR, ENDEAVOUR_SMALL),
+    ("linuxmint", MINT, MINT_SMALL),
+    ("elementary", ELEMENTARY, ELEMENTARY_SMALL),
+    ("zorin", ZORIN, ZORIN_SMALL),
+    ("manjaro", MANJARO, MANJARO_SMALL),
+    ("pop", POPOS, POPOS_SMALL),
+    ("opensuse-tumbleweed", OPENSUSE, OPENSUSE_SMALL),
+    ("opensuse-leap", OPENSUSE, OPENSUSE_SMALL),
+    ("bazzite", BAZZITE, BAZZITE_SMALL),
+    ("rocky", ROCKYLINUX, ROCKYLINUX_SMALL),
+    ("kali", KALI, KALI_SMALL),
+    ("almalinux", ALMA, ALMA_SMALL),
+    ("android", ANDROID, ANDROID_SMALL),
+    ("garuda", GARUDA, GARUDA_SMALL),
+];
+
 const ARCH: (&str, u16) =  (
 "             ~!
             ^YY^
@@ -399,3 +629,207 @@ const ANDROID: (&str, u16) = (
            ^7!!!!.  .!!!!7:  
            ^7!!!!.  .!!!!7: 
            .~!!!^   .^!!!^. ", 38);
+
+// Small versions of the above, for when there isn't room for them
+// These are the normal ones scaled down to half their size
+
+const ARCH_SMALL: (&str, u16) = (
+"      YY
+     JYYY
+    JYYYYY
+   YYYYYYYY
+  YYYY  YYYY
+^YYYY7  7JYYY^
+7~          ~7", 14);
+
+const DEBIAN_SMALL: (&str, u16) = (
+"   !PP5Y55Y!
+ 7GG5!   ~PGG~
+JG7   !^^. !P^
+PY   Y      P^
+PP   ??7^~7Y!
+^PP    ^~^
+  PY^
+   ~?~~.", 14);
+
+const UBUNTU_SMALL: (&str, u16) = (
+"    !JYYYYYY55Y!
+  JYYYYYYYYYY?YYYJ
+ YYYYYYY:::7!~YYYYY
+YYYYY~~YYYYYY~~YYYYY
+YY7^J 5YYYYYY57?YYYY
+YYYYY 5YYYYYY5 YYYYY
+?YYYYY^JYYYJ~~YYYYY?
+ ?YYYYYYYYYY! ?YYY?
+   ?YYYYYYYY55YY?
+      ~?JYYJ?~", 20);
+
+const FEDORA_SMALL: (&str, u16) = (
+"  JGGGGGGYPJ
+!GBGGGP!PPPGG!
+GGGGGB BGGG5GG
+GGP5~~ ~~PGGGG
+G5GGBB BGGGGGG
+GG555~PGGGGBY
+YGGYYGGGPJ^", 14);
+
+const VOID_SMALL: (&str, u16) = (
+"      :::::::::
+   !  :::..::::::
+  555    .    ::::
+B@@557@@5B@@@@#@BY@@
+ @@#YJ@@5@@G@#@@Y@@B
+  555Y    .    :::
+   Y555Y?!!?YY  .
+     J55555555?", 20);
+
+const ENDEAVOUR_SMALL: (&str, u16) = (
+"       !J
+      55557
+    7555555Y^
+  !J55555555J7
+!755555555555?
+.7JYYYYY555Y?7", 14);
+
+const MINT_SMALL: (&str, u16) = (
+"     ^7??????7^
+   7????????????7
+  ??? ???~~7~~????
+ !??? ?? ?? ?? ???!
+ 7??? ?? ?? ?? ???7
+  ??? ??7??7?? ???
+  ~???7!!!!!!7???~
+    !??????????!
+       :^^^^:", 19);
+
+const ELEMENTARY_SMALL: (&str, u16) = (
+"   JPYPP5PPP5P!
+ 5#G^BYY!7P&^!B#!
+BB ?@G     #G  !&?
+&  @#     5@^  ^B@
+@  @P   ?GB   B&J@
+@# 5@#BPG  ?#BB @&
+ #&GGG&PY5BG7 !B&
+  ?GPP7^..^?PPG!
+     !YPPPPY~", 18);
+
+const ZORIN_SMALL: (&str, u16) = (
+"   !JJJJJJJJJJ!
+    ..........
+ JJJJJJJJ!:   7JJ
+JJJJJJJ?:  7JJJJJJ
+7JJJ7   ?JJJJJJJJ7
+       ::.......
+   7JJJJJJJJJJ7
+    JJJJJJJJJJ", 18);
+
+const MANJARO_SMALL: (&str, u16) = (
+"????????????:J????
+?????JJJJJJ?:J????
+????J7777777:J????
+????J:?J??J?:J????
+????J:??????:J????
+????J:??????:J????
+????J:??????:J????
+????J:??????:J????
+????J ?????? J????", 18);
+
+const POPOS_SMALL: (&str, u16) = (
+"    ~7????77??7~
+  7???!^^!??77???7
+ ???: :?7  ?????7??
+??7??  7?  ??  !?7??
+7777??^  7??? !?7777
+?77777?! ??7?7?7777?
+7?77????7^??^???77?7
+ !???~~~~~~~~~~???!
+   7?7????????7?7
+      ~!7777!~", 20);
+
+const OPENSUSE_SMALL: (&str, u16) = (
+"    5@&#@@@@#&@5
+  &@@Y~:      Y@@&
+^@@@@@@@@@@&GY  ~@@^
+@@@@@@@@@@#@PB@G  @@
+@@@@@@@@@&@@@@@@7 P@
+@@@@@@@#G@PG##@@Y &@
+#@@@@@@@@@@@@@&&!P@#
+ B@@PGB###BGY! !@@B
+   #@@&P7~~7P&@@#
+     ^Y#@@@@#Y^", 20);
+
+const ROCKYLINUX_SMALL: (&str, u16) = (
+"  5@@@@@@@@5
+?@@@@@@@@@@@@?
+@@@@@@@@&#@@@@
+@@@@@@&~ ^ B@@
+&@@@#^^#@@&! !
+ GG !&@@@@@&^
+    P&@@&G!", 14);
+
+const KALI_SMALL: (&str, u16) = (
+" .:^~!!77Y5
+ :::!!~7?JJGJ?!^
+  :^.    ~&?^~?YGP
+         !@7     7~
+          ^YYJJJJY!
+                 77!
+                  !:", 20);
+
+const ALMA_SMALL: (&str, u16) = (
+"   ~@@@##~ G#B@@@
+   #&&&@@J@@@@&@@
+   @@#:: @@  @@@@
+  @@P@BBJGG7GBB@@B^
+@@@@@~ .^Y.Y:  :@@@
+@@@@@5B@&7 P@5Y@@@@@
+  7#&&BY@@J7@@^@@G#P
+      Y@&G@@@B
+        @@@.", 20);
+
+const GARUDA_SMALL: (&str, u16) = (
+"     ~&@&&&&&@#^
+   7@@@Y !#  Y@@@!
+ 5@@@7  P@@#BBB@@@
+P@@~ 5P5555555PYB?
+ ?@@@BBBBBBB@@@!
+   ~@@&&&&&@B", 18);
+
+const BAZZITE_SMALL: (&str, u16) = (
+" %%%%%==%%%%%%%
+ %%%%% %%%%%%%%##
+ %%%%% %%%%%%%####
+ =%%%% %%%%##==####
+ %%%%% %%#####==###
+ %%%%%=#######=####
+ %%%%%=#####==####
+  %####=====#####
+    ############
+       ######", 19);
+
+const ANDROID_SMALL: (&str, u16) = (
+"     ^~!!!!!~:
+   ~7!!7!!!!77~
+   77!!!!!!!!!77
+77!!!!!!!!!!!!!!!7!
+!!77!!!!!!!!!!!77!!
+!!77!!!!!!!!!!!77!!
+7777!!!!!!!!!!!7777
+^~ 777!!!7!!!777 ~^
+   :~!7!!~!!7~~:
+     ^7!! !!7:
+      !!^ ^!!", 19);
+
+
+mod tests {
+    // Test built in ASCII is found by distro id, with no id listed twice
+    #[test]
+    fn bundled_ascii() {
+        let mut ids: Vec<&str> = super::BUNDLED_ASCII.iter().map(|x| x.0).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), super::BUNDLED_ASCII.len());
+        assert!(super::bundled_ascii("arch", true).is_some());
+        assert!(super::bundled_ascii("templeos", false).is_none());
+    }
+}
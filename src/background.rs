@@ -0,0 +1,91 @@
+// Works out whether the terminal has a dark or light background, by asking it with OSC 11
+// This is used to pick between the [dark] and [light] tables in the config, and for {bg_is_dark}
+//
+// A device attributes query (DA1) is sent straight after, as every terminal answers that one. So
+// once it's answered, we know there's no background coming rather than waiting out the timeout
+
+use std::sync::OnceLock;
+
+use crate::util;
+
+// Only hit by terminals that don't even answer DA1, so it can be a bit generous for SSH
+const QUERY_TIMEOUT: u64 = 200;
+
+// Whether the background should be treated as dark, from the background option
+// Anything that can't be found out is assumed to be dark, as that's what most people use
+pub fn is_dark(setting: &str) -> bool {
+    match setting {
+        "dark" => true,
+        "light" => false,
+        _ => query_background().is_none_or(is_dark_color),
+    }
+}
+
+// Only asks the once, no matter how many times it's needed
+fn query_background() -> Option<(u8, u8, u8)> {
+    static BACKGROUND: OnceLock<Option<(u8, u8, u8)>> = OnceLock::new();
+    *BACKGROUND.get_or_init(|| {
+        let response: String = util::query_terminal("\x1b]11;?\x1b\\\x1b[c", QUERY_TIMEOUT, has_da1_answer)?;
+        parse_osc11(&response)
+    })
+}
+
+// DA1 answers look like ESC [ ? <attributes> c
+fn has_da1_answer(response: &[u8]) -> bool {
+    let response: String = String::from_utf8_lossy(response).to_string();
+    match response.find("\x1b[?") {
+        Some(r) => response[r..].contains('c'),
+        None => false,
+    }
+}
+
+// The answer looks like ESC ] 11 ; rgb:RRRR/GGGG/BBBB, ending in either BEL or ST
+// Each part can be 1 to 4 hex digits, which are scaled down into 0-255
+fn parse_osc11(response: &str) -> Option<(u8, u8, u8)> {
+    let start: usize = response.find("]11;rgb:")? + 8;
+    let end: usize = response[start..].find(['\x07', '\x1b']).map_or(response.len(), |x| start + x);
+    let parts: Vec<u8> = response[start..end].split('/')
+        .map(|x| {
+            if x.is_empty() || x.len() > 4 {
+                return None;
+            }
+            let value: u32 = u32::from_str_radix(x, 16).ok()?;
+            let max: u32 = (1 << (x.len() * 4)) - 1;
+            Some((value * 255 / max) as u8)
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    if parts.len() != 3 {
+        return None;
+    }
+
+    Some((parts[0], parts[1], parts[2]))
+}
+
+// Using perceived brightness, so that e.g a saturated blue still counts as dark
+fn is_dark_color((r, g, b): (u8, u8, u8)) -> bool {
+    (0.2126 * r as f32) + (0.7152 * g as f32) + (0.0722 * b as f32) < 128.0
+}
+
+
+mod tests {
+    // Test the terminal's answer is parsed, with any number of digits and either terminator
+    #[test]
+    fn osc11_answer() {
+        assert_eq!(super::parse_osc11("\x1b]11;rgb:ffff/ffff/ffff\x1b\\\x1b[?62;22c"), Some((255, 255, 255)));
+        assert_eq!(super::parse_osc11("\x1b]11;rgb:1e/1e/2e\x07"), Some((30, 30, 46)));
+        assert_eq!(super::parse_osc11("\x1b]11;rgb:8/0/f\x07"), Some((136, 0, 255)));
+        assert_eq!(super::parse_osc11("\x1b[?62;22c"), None);
+        assert_eq!(super::parse_osc11("\x1b]11;rgb:ff/ff\x07"), None);
+        assert!(super::has_da1_answer(b"\x1b]11;rgb:0/0/0\x07\x1b[?1;2c"));
+        assert!(!super::has_da1_answer(b"\x1b]11;rgb:0/0/0\x07"));
+    }
+
+    // Test colors are split into dark and light by how bright they look
+    #[test]
+    fn dark_colors() {
+        assert!(super::is_dark_color((30, 30, 46)));
+        assert!(super::is_dark_color((0, 0, 255)));
+        assert!(!super::is_dark_color((253, 246, 227)));
+        assert!(!super::is_dark_color((0, 255, 0)));
+    }
+}
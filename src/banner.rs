@@ -0,0 +1,152 @@
+// Renders text as a big banner, figlet style
+// The font is a tiny pixel font drawn with half blocks, so each glyph is 5 pixels tall but only takes 3 lines
+// Lowercase gets drawn as uppercase, and anything not in the font becomes a "?"
+
+// Each glyph is 5 rows of pixels, '#' being filled
+const FONT: [(char, [&str; 5]); 51] = [
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#...#", "#...#"]),
+    ('N', ["#..#", "##.#", "#.##", "#..#", "#..#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "##.", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["##.", "..#", ".#.", "#..", "###"]),
+    ('3', ["##.", "..#", ".#.", "..#", "##."]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "##.", "..#", "##."]),
+    ('6', [".##", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", ".#.", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "##."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('_', ["...", "...", "...", "...", "###"]),
+    ('.', [".", ".", ".", ".", "#"]),
+    (',', ["..", "..", "..", ".#", "#."]),
+    (':', [".", "#", ".", "#", "."]),
+    ('!', ["#", "#", "#", ".", "#"]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('(', [".#", "#.", "#.", "#.", ".#"]),
+    (')', ["#.", ".#", ".#", ".#", "#."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    ('=', ["...", "###", "...", "###", "..."]),
+    ('@', [".##.", "#..#", "#.##", "#...", ".###"]),
+    ('\'', ["#", "#", ".", ".", "."]),
+    (' ', ["..", "..", "..", "..", ".."]),
+];
+
+fn find_glyph(c: char) -> &'static [&'static str; 5] {
+    let c: char = c.to_ascii_uppercase();
+    match FONT.iter().find(|x| x.0 == c) {
+        Some(r) => &r.1,
+        None => &FONT.iter().find(|x| x.0 == '?').unwrap().1,
+    }
+}
+
+// Turns one line of a glyph's pixels into half blocks, using the row below it as the bottom half
+fn glyph_line(glyph: &[&str; 5], line: usize) -> String {
+    let top: &str = glyph[line * 2];
+    // The font's an odd number of rows tall, so the last line only has a top half
+    let bottom: Option<&str> = glyph.get(line * 2 + 1).copied();
+
+    top.chars().enumerate().map(|(i, x)| {
+        let top_set: bool = x == '#';
+        let bottom_set: bool = bottom.is_some_and(|y| y.chars().nth(i) == Some('#'));
+        match (top_set, bottom_set) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        }
+    }).collect()
+}
+
+// Renders the text as 3 lines of banner
+// Any color codes already in the text carry over onto the glyphs they're in front of, so color
+// placeholders still work across the whole banner
+pub fn render_banner(text: &str) -> Vec<String> {
+    // Pairs each visible character with the color codes active at that point
+    let mut chars: Vec<(char, String)> = Vec::new();
+    let mut style: String = String::new();
+    let mut iter = text.chars();
+    while let Some(c) = iter.next() {
+        if c != '\x1b' {
+            chars.push((c, style.clone()));
+            continue;
+        }
+
+        // Links can't be carried over onto the glyphs, so they're dropped
+        if iter.clone().next() == Some(']') {
+            while let Some(x) = iter.next() {
+                if x == '\x07' || (x == '\x1b' && iter.next() == Some('\\')) {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let mut code: String = String::from(c);
+        for x in iter.by_ref() {
+            code.push(x);
+            if x == 'm' {
+                break;
+            }
+        }
+        if code == "\x1b[0m" {
+            style.clear();
+        } else {
+            style.push_str(&code);
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in 0..3 {
+        let mut rendered: String = String::new();
+        for (i, (c, style)) in chars.iter().enumerate() {
+            if i != 0 {
+                rendered.push(' ');
+            }
+            let glyph: String = glyph_line(find_glyph(*c), line);
+            if style.is_empty() {
+                rendered.push_str(&glyph);
+            } else {
+                rendered.push_str(&format!("{}{}\x1b[0m", style, glyph));
+            }
+        }
+        lines.push(rendered.trim_end().to_string());
+    }
+
+    lines
+}
+
+mod tests {
+    // Checks glyphs get put together into half blocks, and colors stay with their glyph
+    #[test]
+    fn banner_text() {
+        assert_eq!(super::render_banner("Hi"), vec!["█ █ ▀█▀", "█▀█  █", "▀ ▀ ▀▀▀"]);
+        assert_eq!(super::render_banner("\x1b[31mA\x1b[0m.")[2], "\x1b[31m▀ ▀\x1b[0m ▀");
+        assert_eq!(super::render_banner("\x1b]8;;https://crab.rs\x1b\\Hi\x1b]8;;\x1b\\")[0], "█ █ ▀█▀");
+    }
+}
@@ -1,23 +1,29 @@
-use std::{env, fmt::{Debug, Display}, fs::{self, File}, io::Write, path::{Path, PathBuf}};
+use std::{collections::HashMap, env, fmt::{Debug, Display}, fs::{self, File}, io::Write, path::{Path, PathBuf}, str::FromStr};
 
-use config::{builder::DefaultState, Config, ConfigBuilder};
+use config::{builder::DefaultState, Config, ConfigBuilder, ConfigError, FileFormat, Map, Value, ValueKind};
 use serde::Deserialize;
 
-use crate::{ascii::AsciiConfiguration, battery::BatteryConfiguration, cpu::CPUConfiguration, datetime::DateTimeConfiguration, desktop::DesktopConfiguration, displays::DisplayConfiguration, editor::EditorConfiguration, formatter::CrabFetchColor, gpu::GPUConfiguration, host::HostConfiguration, hostname::HostnameConfiguration, initsys::InitSystemConfiguration, locale::LocaleConfiguration, memory::MemoryConfiguration, modules::localip::LocalIPConfiguration, mounts::MountConfiguration, os::OSConfiguration, packages::PackagesConfiguration, processes::ProcessesConfiguration, shell::ShellConfiguration, swap::SwapConfiguration, terminal::TerminalConfiguration, uptime::UptimeConfiguration, util};
+use crate::{ascii::AsciiConfiguration, background, modules::battery::{BatteryConfiguration, BatteryInfo}, modules::cpu::{CPUConfiguration, CPUInfo}, modules::datetime::DateTimeConfiguration, modules::desktop::{DesktopConfiguration, DesktopInfo}, modules::envvar::{EnvVarConfiguration, EnvVarInfo}, modules::displays::{DisplayConfiguration, DisplayInfo}, modules::editor::{EditorConfiguration, EditorInfo}, formatter::{CrabFetchColor, FormatTemplate}, modules::gpu::{GPUConfiguration, GPUInfo}, modules::host::{HostConfiguration, HostInfo}, modules::hostname::{HostnameConfiguration, HostnameInfo}, modules::initsys::{InitSystemConfiguration, InitSystemInfo}, modules::locale::{LocaleConfiguration, LocaleInfo}, modules::memory::{MemoryConfiguration, MemoryInfo}, module::Module, modules::localip::{LocalIPConfiguration, LocalIPInfo}, modules::publicip::{PublicIPConfiguration, PublicIPInfo}, modules::keys::{KeysConfiguration, KeysInfo}, modules::services::{ServicesConfiguration, ServicesInfo}, modules::audiodevices::{AudioDevicesConfiguration, AudioDeviceInfo}, modules::camera::{CameraConfiguration, CameraInfo}, modules::printers::{PrintersConfiguration, PrinterInfo}, modules::mounts::{MountConfiguration, MountInfo}, modules::os::{OSConfiguration, OSInfo}, modules::packages::{PackagesConfiguration, PackagesInfo}, modules::processes::{ProcessesConfiguration, ProcessesInfo}, modules::shell::{ShellConfiguration, ShellInfo}, modules::swap::{SwapConfiguration, SwapInfo}, modules::terminal::{TerminalConfiguration, TerminalInfo}, modules::uptime::{UptimeConfiguration, UptimeInfo}, util};
 #[cfg(feature = "player")]
-use crate::player::PlayerConfiguration;
+use crate::modules::player::{PlayerConfiguration, PlayerInfo};
 
 
 #[derive(Deserialize)]
 pub struct Configuration {
     pub modules: Vec<String>,
+    pub smart_defaults: bool,
     pub unknown_as_text: bool,
     pub separator: String,
     pub title_color: CrabFetchColor,
     pub title_bold: bool,
     pub title_italic: bool,
+    pub titles: bool,
     pub decimal_places: u32,
+    pub temperature_unit: String,
+    pub background: String,
     pub inline_values: bool,
+    pub align_values: bool,
+    pub auto_segments: bool,
     pub underline_character: char,
     pub color_character: String,
     pub color_margin: u8,
@@ -30,12 +36,37 @@ pub struct Configuration {
     pub progress_progress: String,
     pub progress_empty: String,
     pub progress_target_length: u8,
+    pub progress_color: String,
+    pub progress_empty_color: String,
+    pub progress_show_percent: bool,
+    pub progress_percent_position: String,
     pub percentage_color_thresholds: Vec<String>,
+    pub percentage_sign: bool,
     pub use_ibis: bool,
+    pub unit_space: bool,
     pub use_version_checksums: bool,
     pub suppress_errors: bool,
+    pub permission_hints: bool,
+    pub post_command_timeout: u64,
+    pub after_render_command: String,
+    pub network_modules: bool,
+    pub network_timeout: u64,
+    // Filled in from the options any module can have, keyed by the module's name
+    #[serde(skip)]
+    pub module_extras: HashMap<String, ModuleExtras>,
+    // The user's own named colors from the [colors] table, e.g accent = "#cba6f7"
+    #[serde(skip)]
+    pub colors: HashMap<String, CrabFetchColor>,
+    // Problems found while loading the config that didn't stop it loading, e.g unknown options
+    #[serde(skip)]
+    pub load_warnings: Vec<String>,
+    // Not a real config option; set when something needs every bit of info regardless of the
+    // module formats, e.g JSON output
+    #[serde(skip)]
+    pub detect_all_info: bool,
 
     pub ascii: AsciiConfiguration,
+    pub layout: LayoutConfiguration,
 
     pub hostname: HostnameConfiguration,
     pub cpu: CPUConfiguration,
@@ -59,7 +90,35 @@ pub struct Configuration {
     pub initsys: InitSystemConfiguration,
     pub processes: ProcessesConfiguration,
     pub datetime: DateTimeConfiguration,
-    pub localip: LocalIPConfiguration
+    pub localip: LocalIPConfiguration,
+    pub publicip: PublicIPConfiguration,
+    pub keys: KeysConfiguration,
+    pub services: ServicesConfiguration,
+    pub audiodevices: AudioDevicesConfiguration,
+    pub camera: CameraConfiguration,
+    pub printers: PrintersConfiguration,
+    pub env: EnvVarConfiguration
+}
+
+// Options that can be put in any module's section, which get applied to it's output after it's
+// been styled
+pub struct ModuleExtras {
+    // Needed to split the title's separator back off the value
+    pub separator: String,
+    pub post_command: Option<String>,
+    pub max_length: usize,
+    pub banner: bool,
+    // Falls back to the global prefix/suffix, which only live in here
+    pub prefix: String,
+    pub suffix: String,
+}
+
+#[derive(Deserialize)]
+pub struct LayoutConfiguration {
+    pub min_width_for_side_ascii: u16,
+    pub narrow_ascii: String,
+    pub columns: Vec<Vec<String>>,
+    pub column_gap: u16,
 }
 
 // Config Error 
@@ -86,64 +145,128 @@ impl Debug for ConfigurationError {
     }
 }
 
-pub fn parse(location_override: &Option<String>, module_override: &Option<String>, ignore_file: &bool) -> Result<Configuration, ConfigurationError> {
-    let mut builder: ConfigBuilder<DefaultState> = Config::builder();
-    let mut config_path_str: Option<String> = None;
-    if !ignore_file {
-        if location_override.is_some() {
-            config_path_str = Some(shellexpand::tilde(&location_override.clone().unwrap()).to_string());
-            let config_path_str: String = config_path_str.as_ref().unwrap().to_string();
-            // Config won't be happy unless it ends with .toml
-            if !config_path_str.ends_with(".toml") {
-                return Err(ConfigurationError::new(Some(config_path_str), "Config path MUST end with '.toml'".to_string()));
-            }
+// Parses the default config, for every option to fall back on
+fn default_values() -> Map<String, Value> {
+    let defaults: Config = Config::builder()
+        .add_source(config::File::from_str(DEFAULT_CONFIG_CONTENTS, FileFormat::Toml))
+        .build()
+        .expect("Default config is invalid. This should never happen, please make a bug report!");
 
-            // Verify it exists
-            let path: &Path = Path::new(&config_path_str);
-            if !path.exists() {
-                return Err(ConfigurationError::new(Some(config_path_str), "Unable to find config file.".to_string()));
-            }
-        } else {
-            // Find the config path
-            config_path_str = find_file_in_config_dir("config.toml").map(|x| x.display().to_string());
+    defaults.try_deserialize::<Map<String, Value>>().expect("Default config is invalid. This should never happen, please make a bug report!")
+}
+
+// Loads just the user's config file, without any defaults
+fn user_values(path: &str) -> Result<Map<String, Value>, ConfigError> {
+    Config::builder()
+        .add_source(config::File::with_name(path).required(false))
+        .build()?
+        .try_deserialize::<Map<String, Value>>()
+}
+
+// Flattens the values down into each option's full path, going down into any tables so that a
+// user's table only overrides the keys it has rather than the whole thing
+fn flatten_values(prefix: &str, values: Map<String, Value>, flattened: &mut Vec<(String, Value)>) {
+    for (key, value) in values {
+        let path: String = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        match value.kind {
+            ValueKind::Table(r) => flatten_values(&path, r, flattened),
+            _ => flattened.push((path, value)),
+        };
+    }
+}
+
+fn is_background_variant(path: &str) -> bool {
+    path.starts_with("dark.") || path.starts_with("light.")
+}
+// Puts the options in the [dark] or [light] table on top of the rest, and drops the other one
+fn apply_background_variant(values: Vec<(String, Value)>, dark: bool) -> Vec<(String, Value)> {
+    let prefix: &str = if dark { "dark." } else { "light." };
+    let (variants, mut values): (Vec<_>, Vec<_>) = values.into_iter().partition(|x| is_background_variant(&x.0));
+    for (path, value) in variants {
+        if let Some(path) = path.strip_prefix(prefix) {
+            values.retain(|x| x.0 != path);
+            values.push((path.to_string(), value));
         }
+    }
+
+    values
+}
 
-        if config_path_str.is_some() {
-            builder = builder.add_source(config::File::with_name(config_path_str.as_ref().unwrap()).required(false));
+// Pulls the user's named colors out of the [colors] table, as they aren't options themselves
+// Anything that isn't a color is left out with a warning
+fn take_color_aliases(values: &mut Vec<(String, Value)>, warnings: &mut Vec<String>) -> Vec<(String, String)> {
+    let (aliases, rest): (Vec<_>, Vec<_>) = std::mem::take(values).into_iter().partition(|x| x.0.starts_with("colors."));
+    *values = rest;
+
+    aliases.into_iter().filter_map(|(path, value)| {
+        let name: &str = path.strip_prefix("colors.").unwrap();
+        let color: Option<String> = value.into_string().ok().filter(|x| CrabFetchColor::from_str(x).is_ok());
+        if color.is_none() {
+            warnings.push(format!("'{}' isn't a valid color, it'll be ignored", path));
+            return None;
         }
+        Some((name.to_string(), color.unwrap()))
+    }).collect()
+}
+// Swaps any named colors used in color options for what they're named after
+// Placeholders, e.g {color-accent}, are looked up as they're styled instead, so they work in ASCII files too
+fn apply_color_aliases(values: Vec<(String, Value)>, colors: &[(String, String)]) -> Vec<(String, Value)> {
+    if colors.is_empty() {
+        return values;
+    }
+    let resolve = |x: &str| -> Option<String> {
+        let (prefix, name): (&str, &str) = x.rsplit_once(':').map_or(("", x), |(p, n)| (p, n));
+        let color: &String = &colors.iter().find(|y| y.0 == name)?.1;
+        Some(if prefix.is_empty() { color.to_string() } else { format!("{}:{}", prefix, color) })
+    };
+
+    values.into_iter().map(|(path, value)| {
+        let option: &str = path.rsplit('.').next().unwrap();
+        if option.ends_with("color") {
+            if let Some(r) = value.clone().into_string().ok().and_then(|x| resolve(&x)) {
+                return (path, Value::from(r));
+            }
+        }
+        // The ASCII colors, and the colors in the thresholds (e.g "90:accent")
+        if option.ends_with("colors") || option == "percentage_color_thresholds" {
+            if let Ok(array) = value.clone().into_array() {
+                let array: Vec<Value> = array.into_iter()
+                    .map(|x| match x.clone().into_string().ok().and_then(|y| resolve(&y)) {
+                        Some(r) => Value::from(r),
+                        None => x,
+                    })
+                    .collect();
+                return (path, Value::from(array));
+            }
+        }
+        (path, value)
+    }).collect()
+}
+
+// The options any module's section can have, see ModuleExtras
+const MODULE_EXTRAS: [&str; 6] = ["separator", "post_command", "max_length", "banner", "prefix", "suffix"];
+
+// Anything that's in the default config, or that a module's section can have without it being in
+// there; the module extras, hide_title, an optional format, or an override of a global option
+fn is_known_option(path: &str, defaults: &[(String, Value)]) -> bool {
+    if defaults.iter().any(|x| x.0 == path) {
+        return true;
+    }
+    let option: &str = match path.split_once('.') {
+        Some(r) => r.1,
+        None => return false,
+    };
+
+    MODULE_EXTRAS.contains(&option) || ["hide_title", "format"].contains(&option) || defaults.iter().any(|x| x.0 == option)
+}
+
+// Puts the user's values on top of the defaults
+fn build_config(defaults: &[(String, Value)], values: &[(String, Value)], module_override: &Option<String>) -> Result<Config, ConfigError> {
+    let mut builder: ConfigBuilder<DefaultState> = Config::builder();
+    // These all come from the default config, so there's only the one copy of them to keep up to date
+    for (path, value) in defaults {
+        builder = builder.set_default(path, value.clone())?;
     }
-    // Set the defaults here
-    // General
-    builder = builder.set_default("modules", vec![
-        "hostname".to_string(),
-        "underline:16".to_string(),
-
-        "cpu".to_string(),
-        "gpu".to_string(),
-        "memory".to_string(),
-        "swap".to_string(),
-        "mounts".to_string(),
-        "host".to_string(),
-        "displays".to_string(),
-
-        "os".to_string(),
-        "packages".to_string(),
-        "desktop".to_string(),
-        "terminal".to_string(),
-        "shell".to_string(),
-        "editor".to_string(),
-        "uptime".to_string(),
-        "locale".to_string(),
-        "player".to_string(),
-        "initsys".to_string(),
-        "processes".to_string(),
-        "battery".to_string(),
-        "localip".to_string(),
-
-        "space".to_string(),
-        "colors".to_string(),
-        "bright_colors".to_string(),
-    ]).unwrap();
 
     // Android only module
     #[cfg(feature = "android")]
@@ -169,144 +292,317 @@ pub fn parse(location_override: &Option<String>, module_override: &Option<String
             "space".to_string(),
             "colors".to_string(),
             "bright_colors".to_string(),
-        ]).unwrap();
+        ])?;
     }
-    builder = builder.set_default("unknown_as_text", false).unwrap();
-
-    builder = builder.set_default("separator", " > ").unwrap();
-    builder = builder.set_default("title_color", "bright_magenta").unwrap();
-    builder = builder.set_default("title_bold", true).unwrap();
-    builder = builder.set_default("title_italic", false).unwrap();
-
-    builder = builder.set_default("decimal_places", 2).unwrap();
-    builder = builder.set_default("inline_values", false).unwrap();
-    builder = builder.set_default("underline_character", "―").unwrap();
-    builder = builder.set_default("color_character", "   ").unwrap();
-    builder = builder.set_default("color_margin", 0).unwrap();
-    builder = builder.set_default("color_use_background", true).unwrap();
-
-    builder = builder.set_default("use_os_color", true).unwrap();
-
-    builder = builder.set_default("segment_top", "{color-white}[======------{color-brightmagenta} {name} {color-white}------======]").unwrap();
-    builder = builder.set_default("segment_bottom", "{color-white}[======------{color-brightmagenta} {name_sized_gap} {color-white}------======]").unwrap();
 
-    builder = builder.set_default("progress_left_border", "[").unwrap();
-    builder = builder.set_default("progress_right_border", "]").unwrap();
-    builder = builder.set_default("progress_progress", "=").unwrap();
-    builder = builder.set_default("progress_empty", " ").unwrap();
-    builder = builder.set_default("progress_target_length", 20).unwrap();
-
-    builder = builder.set_default("use_ibis", false).unwrap();
-    builder = builder.set_default("use_version_checksums", false).unwrap();
-    builder = builder.set_default("suppress_errors", true).unwrap();
-
-    builder = builder.set_default("percentage_color_thresholds", vec!["75:brightgreen", "85:brightyellow", "90:brightred"]).unwrap();
-
-    // ASCII
-    builder = builder.set_default("ascii.display", true).unwrap();
-    builder = builder.set_default("ascii.colors", vec!["bright_magenta"]).unwrap();
-    builder = builder.set_default("ascii.margin", 4).unwrap();
-    builder = builder.set_default("ascii.side", "left").unwrap();
-
-    // Modules
-    builder = builder.set_default("hostname.title", "").unwrap();
-    builder = builder.set_default("hostname.format", "{color-title}{username}{color-white}@{color-title}{hostname}").unwrap();
+    for (path, value) in values {
+        builder = builder.set_override(path, value.clone())?;
+    }
 
-    builder = builder.set_default("cpu.title", "CPU").unwrap();
-    builder = builder.set_default("cpu.format", "{name} ({core_count}c {thread_count}t) @ {max_clock_ghz} GHz").unwrap();
-    builder = builder.set_default("cpu.remove_trailing_processor", true).unwrap();
+    // Check for any module overrides
+    if let Some(module_override) = module_override {
+        builder = builder.set_override("modules", module_override.split(',').collect::<Vec<&str>>())?;
+    }
 
-    builder = builder.set_default("gpu.amd_accuracy", true).unwrap();
-    builder = builder.set_default("gpu.ignore_disabled_gpus", true).unwrap();
-    builder = builder.set_default("gpu.title", "GPU").unwrap();
-    builder = builder.set_default("gpu.format", "{vendor} {model} ({vram})").unwrap();
+    builder.build()
+}
 
-    builder = builder.set_default("memory.title", "Memory").unwrap();
-    builder = builder.set_default("memory.format", "{used} / {max} ({percent})").unwrap();
+pub fn parse(location_override: &Option<String>, module_override: &Option<String>, ignore_file: &bool) -> Result<Configuration, ConfigurationError> {
+    let mut values: Vec<(String, Value)> = Vec::new();
+    let mut config_path_str: Option<String> = None;
+    if !ignore_file {
+        if location_override.is_some() {
+            config_path_str = Some(shellexpand::tilde(&location_override.clone().unwrap()).to_string());
+            let config_path_str: String = config_path_str.as_ref().unwrap().to_string();
+            // Config won't be happy unless it ends with .toml
+            if !config_path_str.ends_with(".toml") {
+                return Err(ConfigurationError::new(Some(config_path_str), "Config path MUST end with '.toml'".to_string()));
+            }
 
-    builder = builder.set_default("swap.title", "Swap").unwrap();
-    builder = builder.set_default("swap.format", "{used} / {total} ({percent})").unwrap();
+            // Verify it exists
+            let path: &Path = Path::new(&config_path_str);
+            if !path.exists() {
+                return Err(ConfigurationError::new(Some(config_path_str), "Unable to find config file.".to_string()));
+            }
+        } else {
+            // Find the config path
+            config_path_str = find_file_in_config_dir("config.toml").map(|x| x.display().to_string());
+        }
 
-    builder = builder.set_default("mounts.title", "Disk ({mount})").unwrap();
-    builder = builder.set_default("mounts.format", "{space_used} used of {space_total} ({percent}) [{filesystem}]").unwrap();
-    builder = builder.set_default("mounts.ignore", vec![""]).unwrap();
+        if let Some(config_path_str) = &config_path_str {
+            match user_values(config_path_str) {
+                Ok(r) => flatten_values("", r, &mut values),
+                Err(e) => return Err(ConfigurationError::new(Some(config_path_str.to_string()), e.to_string())),
+            }
+        }
+    }
 
-    builder = builder.set_default("host.title", "Host").unwrap();
-    builder = builder.set_default("host.format", "{host} ({chassis})").unwrap();
-    builder = builder.set_default("host.newline_chassis", false).unwrap();
-    builder = builder.set_default("host.chassis_title", "Chassis").unwrap();
-    builder = builder.set_default("host.chassis_format", "{chassis}").unwrap();
+    // Only ask the terminal if there's a [dark] or [light] table to pick between
+    if values.iter().any(|x| is_background_variant(&x.0)) {
+        let background: String = values.iter().find(|x| x.0 == "background")
+            .and_then(|x| x.1.clone().into_string().ok())
+            .unwrap_or_default();
+        values = apply_background_variant(values, background::is_dark(&background));
+    }
 
-    builder = builder.set_default("displays.title", "Display ({make} {model})").unwrap();
-    builder = builder.set_default("displays.format", "{width}x{height} @ {refresh_rate}Hz ({name})").unwrap();
-    builder = builder.set_default("displays.scale_size", false).unwrap();
+    let mut load_warnings: Vec<String> = Vec::new();
+    let colors: Vec<(String, String)> = take_color_aliases(&mut values, &mut load_warnings);
+    values = apply_color_aliases(values, &colors);
 
-    builder = builder.set_default("os.title", "Operating System").unwrap();
-    builder = builder.set_default("os.format", "{distro} ({kernel})").unwrap();
-    builder = builder.set_default("os.newline_kernel", false).unwrap();
-    builder = builder.set_default("os.kernel_title", "Kernel").unwrap();
-    builder = builder.set_default("os.kernel_format", "Linux {kernel}").unwrap();
+    let mut defaults: Vec<(String, Value)> = Vec::new();
+    flatten_values("", default_values(), &mut defaults);
+    for (path, _) in &values {
+        if !is_known_option(path, &defaults) {
+            load_warnings.push(format!("Unknown option '{}', it'll be ignored", path));
+        }
+    }
 
+    let mut config: Config = match build_config(&defaults, &values, module_override) {
+        Ok(r) => r,
+        Err(e) => return Err(ConfigurationError::new(config_path_str, e.to_string())),
+    };
+    if config.clone().try_deserialize::<Configuration>().is_err() {
+        // Rather than failing the whole config over one bad option, find which ones are at fault
+        // and use their default instead. This keeps older configs working too
+        values.retain(|(path, value)| {
+            let error: Option<String> = match build_config(&defaults, &[(path.to_string(), value.clone())], module_override) {
+                Ok(r) => r.try_deserialize::<Configuration>().err().map(|x| x.to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(error) = &error {
+                load_warnings.push(format!("Invalid value for '{}', using the default instead: {}", path, error));
+            }
+            error.is_none()
+        });
+        config = match build_config(&defaults, &values, module_override) {
+            Ok(r) => r,
+            Err(e) => return Err(ConfigurationError::new(config_path_str, e.to_string())),
+        };
+    }
 
-    builder = builder.set_default("packages.title", "Packages").unwrap();
-    builder = builder.set_default("packages.format", "{count} ({manager})").unwrap();
-    builder = builder.set_default("packages.ignore", Vec::<String>::new()).unwrap();
+    // Done before deserializing as that consumes the config
+    let mut module_extras: HashMap<String, ModuleExtras> = HashMap::new();
+    let global_separator: String = config.get_string("separator").unwrap_or_default();
+    let global_max_length: i64 = config.get_int("max_length").unwrap_or(0);
+    let global_prefix: String = config.get_string("prefix").unwrap_or_default();
+    let global_suffix: String = config.get_string("suffix").unwrap_or_default();
+    if let Ok(modules) = config.get_array("modules") {
+        for module in modules {
+            let name: String = match module.into_string() {
+                Ok(r) => r.split(':').next().unwrap().to_string(),
+                Err(_) => continue,
+            };
+            let extras: ModuleExtras = ModuleExtras {
+                separator: config.get_string(&format!("{}.separator", name)).unwrap_or(global_separator.clone()),
+                post_command: config.get_string(&format!("{}.post_command", name)).ok(),
+                // The player module has it's own max_length, that limits each placeholder instead
+                max_length: if name == "player" {
+                    global_max_length
+                } else {
+                    config.get_int(&format!("{}.max_length", name)).unwrap_or(global_max_length)
+                }.max(0) as usize,
+                banner: config.get_bool(&format!("{}.banner", name)).unwrap_or(false),
+                prefix: config.get_string(&format!("{}.prefix", name)).unwrap_or(global_prefix.clone()),
+                suffix: config.get_string(&format!("{}.suffix", name)).unwrap_or(global_suffix.clone()),
+            };
+            module_extras.insert(name, extras);
+        }
+    }
 
-    builder = builder.set_default("desktop.title", "Desktop").unwrap();
-    builder = builder.set_default("desktop.format", "{desktop} ({display_type})").unwrap();
+    let mut deserialized: Configuration = match config.try_deserialize::<Configuration>() {
+        Ok(r) => r,
+        Err(e) => return Err(ConfigurationError::new(config_path_str, e.to_string())),
+    };
+    deserialized.module_extras = module_extras;
+    deserialized.colors = colors.iter()
+        .filter_map(|(name, color)| Some((name.to_string(), CrabFetchColor::from_str(color).ok()?)))
+        .collect();
+    deserialized.load_warnings = load_warnings;
 
-    builder = builder.set_default("terminal.title", "Terminal").unwrap();
-    builder = builder.set_default("terminal.format", "{name} {version}").unwrap();
+    // Columns replace the modules list, unless they've been overridden
+    if module_override.is_some() {
+        deserialized.layout.columns.clear();
+    }
+    // Only the default list gets touched, if the user's picked their modules they get what they asked for
+    let default_modules: bool = module_override.is_none() && deserialized.layout.columns.is_empty() && !values.iter().any(|x| x.0 == "modules");
+    if deserialized.smart_defaults && default_modules {
+        let desktop: bool = util::file_read(Path::new("/sys/devices/virtual/dmi/id/chassis_type"))
+            .is_ok_and(|x| DESKTOP_CHASSIS_TYPES.contains(&x.trim()));
+        let headless_ssh: bool = env::var("SSH_CONNECTION").is_ok() && env::var("DISPLAY").is_err() && env::var("WAYLAND_DISPLAY").is_err();
+        deserialized.modules = smart_default_modules(&deserialized.modules, desktop, headless_ssh);
+    }
+    if deserialized.auto_segments {
+        deserialized.modules = auto_segment_modules(&deserialized.modules);
+        deserialized.layout.columns = deserialized.layout.columns.iter().map(|x| auto_segment_modules(x)).collect();
+    }
+    if !deserialized.layout.columns.is_empty() {
+        deserialized.modules = deserialized.layout.columns.concat();
+    }
 
-    builder = builder.set_default("shell.title", "Shell").unwrap();
-    builder = builder.set_default("shell.format", "{name} {version}").unwrap();
-    builder = builder.set_default("shell.show_default_shell", "false").unwrap();
+    Ok(deserialized)
+}
 
-    builder = builder.set_default("uptime.title", "Uptime").unwrap();
+// From the SMBIOS spec; desktop, low profile desktop, pizza box, mini tower, tower, all in one,
+// main server chassis, rack mount, sealed-case PC, mini PC and stick PC
+const DESKTOP_CHASSIS_TYPES: [&str; 11] = ["3", "4", "5", "6", "7", "13", "17", "23", "24", "35", "36"];
 
-    builder = builder.set_default("battery.title", "Battery {index}").unwrap();
-    builder = builder.set_default("battery.format", "{percentage}%").unwrap();
+// Drops the modules that won't have anything to show on this machine, for smart_defaults
+fn smart_default_modules(modules: &[String], desktop: bool, headless_ssh: bool) -> Vec<String> {
+    modules.iter().filter(|x| {
+        let name: &str = x.split(':').next().unwrap();
+        !(desktop && name == "battery" || headless_ssh && name == "displays")
+    }).cloned().collect()
+}
 
-    builder = builder.set_default("editor.title", "Editor").unwrap();
-    builder = builder.set_default("editor.format", "{name} {version}").unwrap();
-    builder = builder.set_default("editor.fancy", true).unwrap();
+// Which category each module goes in for auto_segments
+// Anything not in here doesn't get put in a segment
+const HARDWARE_MODULES: [&str; 8] = ["cpu", "gpu", "memory", "swap", "mounts", "host", "displays", "battery"];
+const SOFTWARE_MODULES: [&str; 12] = ["os", "packages", "desktop", "terminal", "shell", "editor", "uptime", "locale", "player", "initsys", "processes", "datetime"];
+
+// Wraps each run of modules in the same category in a segment, for auto_segments
+fn auto_segment_modules(modules: &[String]) -> Vec<String> {
+    let mut segmented: Vec<String> = Vec::new();
+    let mut current: Option<&str> = None;
+    for module in modules {
+        let name: &str = module.split(':').next().unwrap();
+        let category: Option<&str> = if HARDWARE_MODULES.contains(&name) {
+            Some("Hardware")
+        } else if SOFTWARE_MODULES.contains(&name) {
+            Some("Software")
+        } else {
+            None
+        };
 
-    builder = builder.set_default("locale.title", "Locale").unwrap();
-    builder = builder.set_default("locale.format", "{language} ({encoding})").unwrap();
+        if category != current {
+            if current.is_some() {
+                segmented.push("end_segment".to_string());
+            }
+            if let Some(category) = category {
+                segmented.push(format!("segment:{}", category));
+            }
+            current = category;
+        }
+        segmented.push(module.to_string());
+    }
+    if current.is_some() {
+        segmented.push("end_segment".to_string());
+    }
 
-    builder = builder.set_default("player.title", "Player ({player})").unwrap();
-    builder = builder.set_default("player.format", "{track} by {track_artists} ({album}) [{status}]").unwrap();
-    builder = builder.set_default("player.ignore", Vec::<String>::new()).unwrap();
+    segmented
+}
 
-    builder = builder.set_default("initsys.title", "Init System").unwrap();
-    builder = builder.set_default("initsys.format", "{name} {version}").unwrap();
+fn lint_placeholders<M: Module>(module: &str, templates: &[&FormatTemplate], config: &Configuration, warnings: &mut Vec<String>) {
+    for template in templates {
+        for placeholder in M::unknown_placeholders(template, config) {
+            warnings.push(format!("{}: unknown placeholder {{{}}} in \"{}\"", module, placeholder, template));
+        }
+    }
+}
 
-    builder = builder.set_default("processes.title", "Total Processes").unwrap();
+// Checks for settings that are valid on their own but don't make sense together, or that won't do
+// anything. These are only warnings, as CrabFetch will still run fine with them
+pub fn lint(config: &Configuration) -> Vec<String> {
+    let mut warnings: Vec<String> = config.load_warnings.clone();
 
-    builder = builder.set_default("datetime.title", "Date/Time").unwrap();
-    builder = builder.set_default("datetime.format", "%H:%M:%S on %e %B %G").unwrap();
+    if config.use_os_color {
+        if config.title_color != CrabFetchColor::BrightMagenta {
+            warnings.push("title_color is set but use_os_color is on, so it'll be replaced by your distro's color".to_string());
+        }
+        if config.ascii.colors != vec![CrabFetchColor::BrightMagenta] {
+            warnings.push("ascii.colors is set but use_os_color is on, so they'll be replaced by your distro's color".to_string());
+        }
+    }
+    if config.ascii.gradient && config.ascii.colors.len() < 2 && !config.use_os_color {
+        warnings.push("ascii.gradient needs at least 2 colors in ascii.colors to do anything".to_string());
+    }
+    if !["left", "right", "top", "bottom"].contains(&config.ascii.side.as_str()) {
+        warnings.push(format!("ascii.side '{}' isn't one of left, right, top or bottom", config.ascii.side));
+    }
+    if !["normal", "small"].contains(&config.ascii.size.as_str()) {
+        warnings.push(format!("ascii.size '{}' isn't normal or small", config.ascii.size));
+    }
+    if !["top", "hide"].contains(&config.layout.narrow_ascii.as_str()) {
+        warnings.push(format!("layout.narrow_ascii '{}' isn't top or hide", config.layout.narrow_ascii));
+    }
+    if !["auto", "dark", "light"].contains(&config.background.as_str()) {
+        warnings.push(format!("background '{}' isn't auto, dark or light", config.background));
+    }
+    if !["none", "thresholds", "gradient"].contains(&config.progress_color.as_str()) {
+        warnings.push(format!("progress_color '{}' isn't none, thresholds or gradient", config.progress_color));
+    }
+    if !["overlay", "append"].contains(&config.progress_percent_position.as_str()) {
+        warnings.push(format!("progress_percent_position '{}' isn't overlay or append", config.progress_percent_position));
+    }
+    if !config.progress_empty_color.is_empty() && CrabFetchColor::from_str(&config.progress_empty_color).is_err() {
+        warnings.push(format!("progress_empty_color '{}' isn't a valid color", config.progress_empty_color));
+    }
+    if !["celsius", "fahrenheit", "kelvin"].contains(&config.temperature_unit.as_str()) {
+        warnings.push(format!("temperature_unit '{}' isn't celsius, fahrenheit or kelvin", config.temperature_unit));
+    }
+    if !["count_desc", "alphabetical", "config_order"].contains(&config.packages.sort.as_str()) {
+        warnings.push(format!("packages.sort '{}' isn't count_desc, alphabetical or config_order", config.packages.sort));
+    }
+    if !config.ascii.image_path.is_empty() && !config.ascii.random_from_dir.is_empty() {
+        warnings.push("ascii.image_path is set, so ascii.random_from_dir won't be used".to_string());
+    }
 
-    builder = builder.set_default("localip.title", "Local IP ({interface})").unwrap();
-    builder = builder.set_default("localip.format", "{addr}").unwrap();
+    // Typo'd placeholders would otherwise just get left in the output as they are
+    lint_placeholders::<BatteryInfo>("battery", &[&config.battery.title, &config.battery.format], config, &mut warnings);
+    lint_placeholders::<CPUInfo>("cpu", &[&config.cpu.title, &config.cpu.format], config, &mut warnings);
+    lint_placeholders::<DesktopInfo>("desktop", &[&config.desktop.title, &config.desktop.format], config, &mut warnings);
+    lint_placeholders::<DisplayInfo>("displays", &[&config.displays.title, &config.displays.format], config, &mut warnings);
+    lint_placeholders::<EditorInfo>("editor", &[&config.editor.title, &config.editor.format], config, &mut warnings);
+    lint_placeholders::<GPUInfo>("gpu", &[&config.gpu.title, &config.gpu.format], config, &mut warnings);
+    lint_placeholders::<HostInfo>("host", &[&config.host.title, &config.host.format], config, &mut warnings);
+    lint_placeholders::<HostnameInfo>("hostname", &[&config.hostname.title, &config.hostname.format], config, &mut warnings);
+    lint_placeholders::<InitSystemInfo>("initsys", &[&config.initsys.title, &config.initsys.format], config, &mut warnings);
+    lint_placeholders::<KeysInfo>("keys", &[&config.keys.title, &config.keys.format], config, &mut warnings);
+    lint_placeholders::<ServicesInfo>("services", &[&config.services.title, &config.services.format], config, &mut warnings);
+    lint_placeholders::<AudioDeviceInfo>("audiodevices", &[&config.audiodevices.title, &config.audiodevices.format], config, &mut warnings);
+    lint_placeholders::<CameraInfo>("camera", &[&config.camera.title, &config.camera.format], config, &mut warnings);
+    lint_placeholders::<PrinterInfo>("printers", &[&config.printers.title, &config.printers.format], config, &mut warnings);
+    lint_placeholders::<EnvVarInfo>("env", &[&config.env.title, &config.env.format], config, &mut warnings);
+    lint_placeholders::<LocaleInfo>("locale", &[&config.locale.title, &config.locale.format], config, &mut warnings);
+    lint_placeholders::<LocalIPInfo>("localip", &[&config.localip.title, &config.localip.format], config, &mut warnings);
+    lint_placeholders::<MemoryInfo>("memory", &[&config.memory.title, &config.memory.node_title, &config.memory.format], config, &mut warnings);
+    lint_placeholders::<MountInfo>("mounts", &[&config.mounts.title, &config.mounts.format], config, &mut warnings);
+    lint_placeholders::<OSInfo>("os", &[&config.os.title, &config.os.format], config, &mut warnings);
+    lint_placeholders::<PackagesInfo>("packages", &[&config.packages.format], config, &mut warnings);
+    lint_placeholders::<ProcessesInfo>("processes", &[&config.processes.title], config, &mut warnings);
+    if let Some(format) = &config.processes.format {
+        lint_placeholders::<ProcessesInfo>("processes", &[format], config, &mut warnings);
+    }
+    lint_placeholders::<PublicIPInfo>("publicip", &[&config.publicip.title, &config.publicip.format], config, &mut warnings);
+    lint_placeholders::<ShellInfo>("shell", &[&config.shell.title, &config.shell.format], config, &mut warnings);
+    lint_placeholders::<SwapInfo>("swap", &[&config.swap.title, &config.swap.format], config, &mut warnings);
+    lint_placeholders::<TerminalInfo>("terminal", &[&config.terminal.title, &config.terminal.format], config, &mut warnings);
+    lint_placeholders::<UptimeInfo>("uptime", &[&config.uptime.title], config, &mut warnings);
+    if let Some(format) = &config.uptime.format {
+        lint_placeholders::<UptimeInfo>("uptime", &[format], config, &mut warnings);
+    }
+    #[cfg(feature = "player")]
+    lint_placeholders::<PlayerInfo>("player", &[&config.player.title, &config.player.format], config, &mut warnings);
 
-    // Check for any module overrides
-    if module_override.is_some() {
-        let module_override: String = module_override.clone().unwrap();
-        builder = builder.set_override("modules", module_override.split(',').collect::<Vec<&str>>()).unwrap();
+    // Scaling is only known on Wayland, X11 has no idea about it
+    if config.displays.scale_size && env::var("WAYLAND_DISPLAY").is_err() {
+        warnings.push("displays.scale_size only works on Wayland, and you don't seem to be in a Wayland session".to_string());
     }
 
-    // Now stop.
-    let config: Config = match builder.build() {
-        Ok(r) => r,
-        Err(e) => return Err(ConfigurationError::new(config_path_str, e.to_string())),
-    };
+    for threshold in &config.percentage_color_thresholds {
+        let valid: bool = match threshold.split_once(':') {
+            Some((percent, color)) => percent.parse::<u8>().is_ok() && CrabFetchColor::from_str(color).is_ok(),
+            None => false,
+        };
+        if !valid {
+            warnings.push(format!("percentage_color_thresholds entry '{}' isn't a valid \"{{percentage}}:{{color}}\"", threshold));
+        }
+    }
 
-    let deserialized: Configuration = match config.try_deserialize::<Configuration>() {
-        Ok(r) => r,
-        Err(e) => return Err(ConfigurationError::new(config_path_str, e.to_string())),
-    };
+    if config.auto_segments && config.modules.iter().any(|x| x.starts_with("segment:")) {
+        warnings.push("auto_segments is on alongside your own segments, so they'll end up mixed together".to_string());
+    }
 
-    Ok(deserialized)
+    warnings
 }
 
 fn find_file_in_config_dir(path: &str) -> Option<PathBuf> {
@@ -330,25 +626,65 @@ fn find_file_in_config_dir(path: &str) -> Option<PathBuf> {
     util::find_first_pathbuf_exists(paths)
 }
 
+// Where packages can put things for CrabFetch to use, e.g ASCII art for a distro
+// Tries $XDG_DATA_HOME/crabfetch, then each of $XDG_DATA_DIRS, which is /usr/local/share and
+// /usr/share if it's not set
+pub fn find_file_in_data_dirs(path: &str) -> Option<PathBuf> {
+    let paths: Vec<PathBuf> = data_dirs(env::var("XDG_DATA_HOME").ok(), env::var("HOME").ok(), env::var("XDG_DATA_DIRS").ok())
+        .into_iter()
+        .map(|x| x.join("crabfetch").join(path))
+        .collect();
+
+    util::find_first_pathbuf_exists(paths)
+}
+// Every data dir's copy of the path, for when they all matter and not just the first
+pub fn find_all_in_data_dirs(path: &str) -> Vec<PathBuf> {
+    data_dirs(env::var("XDG_DATA_HOME").ok(), env::var("HOME").ok(), env::var("XDG_DATA_DIRS").ok())
+        .into_iter()
+        .map(|x| x.join("crabfetch").join(path))
+        .filter(|x| x.exists())
+        .collect()
+}
+// Empty values are treated as unset, as the XDG spec says to
+fn data_dirs(data_home: Option<String>, home: Option<String>, data_dirs: Option<String>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    match data_home.filter(|x| !x.is_empty()) {
+        Some(r) => dirs.push(PathBuf::from(r)),
+        None => if let Some(home) = home {
+            dirs.push(PathBuf::from(home).join(".local/share"));
+        },
+    }
+    let data_dirs: String = data_dirs.filter(|x| !x.is_empty()).unwrap_or("/usr/local/share:/usr/share".to_string());
+    dirs.extend(data_dirs.split(':').filter(|x| !x.is_empty()).map(PathBuf::from));
+
+    dirs
+}
+
 pub fn check_for_ascii_override() -> Option<String> {
-    let path: PathBuf = match find_file_in_config_dir("ascii") {
-        Some(r) => r,
-        None => return None
-    };
+    let path: PathBuf = find_file_in_config_dir("ascii")?;
     if !path.exists() {
         return None;
     }
 
-    match util::file_read(&path) {
-        Ok(r) => Some(r),
-        Err(_) => None,
+    util::file_read(&path).ok()
+}
+
+// Where the user's own version checksums are kept, see versions::update_checksums
+// If there isn't one yet, this is where it'll be made
+pub fn checksums_path() -> Option<PathBuf> {
+    if let Some(r) = find_file_in_config_dir("checksums") {
+        return Some(r);
+    }
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(r) => Some(PathBuf::from(r).join("CrabFetch/checksums")),
+        Err(_) => env::var("HOME").ok().map(|x| PathBuf::from(x).join(".config/CrabFetch/checksums")),
     }
 }
 
 pub fn generate_config_file(location_override: Option<String>) {
     let path: String;
-    if location_override.is_some() {
-        path = shellexpand::tilde(&location_override.unwrap()).to_string();
+    if let Some(location_override) = location_override {
+        path = shellexpand::tilde(&location_override).to_string();
         // Config won't be happy unless it ends with .toml
         if !path.ends_with(".toml") {
             // Simply crash, to avoid confusing the user as to why the default config is being used
@@ -396,6 +732,84 @@ pub fn generate_config_file(location_override: Option<String>) {
 }
 
 mod tests {
+    // Checks conflicting options get warned about, and the default config doesn't have any
+    #[test]
+    fn lint_config() {
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        config.displays.scale_size = false;
+        assert!(crate::config_manager::lint(&config).is_empty());
+
+        config.title_color = crate::formatter::CrabFetchColor::Red;
+        config.percentage_color_thresholds.push("50:notacolor".to_string());
+        config.percentage_color_thresholds.push("brightred".to_string());
+        assert_eq!(crate::config_manager::lint(&config).len(), 3);
+    }
+
+    // Checks smart_defaults only drops what doesn't fit the machine
+    #[test]
+    fn smart_defaults() {
+        let modules: Vec<String> = vec!["cpu".to_string(), "displays".to_string(), "battery".to_string()];
+        assert_eq!(super::smart_default_modules(&modules, false, false), modules);
+        assert_eq!(super::smart_default_modules(&modules, true, false), vec!["cpu", "displays"]);
+        assert_eq!(super::smart_default_modules(&modules, true, true), vec!["cpu"]);
+    }
+
+    // Checks a config with bad or unknown options still loads, with the rest of it intact
+    #[test]
+    fn partial_config() {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("crabfetch-partial-{}.toml", std::process::id()));
+        std::fs::write(&path, "titles = \"maybe\"\nunknown = 1\n[cpu]\ntitle = \"Processor\"\ndecimal_places = 1\nprefix = \">\"\n").unwrap();
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&Some(path.display().to_string()), &None, &false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(config.titles);
+        assert_eq!(config.cpu.decimal_places, Some(1));
+        assert_eq!(config.cpu.title.to_string(), "Processor");
+        assert_eq!(config.load_warnings.len(), 2);
+    }
+
+    // Checks only the matching background's table gets applied, on top of everything else
+    #[test]
+    fn background_variants() {
+        let values: Vec<(String, config::Value)> = vec![
+            ("title_color".to_string(), config::Value::from("white")),
+            ("light.title_color".to_string(), config::Value::from("black")),
+            ("dark.cpu.title".to_string(), config::Value::from("CPU")),
+        ];
+        let light: Vec<(String, config::Value)> = super::apply_background_variant(values.clone(), false);
+        assert_eq!(light.len(), 1);
+        assert_eq!(light[0].0, "title_color");
+        assert_eq!(light[0].1.clone().into_string().unwrap(), "black");
+
+        let dark: Vec<String> = super::apply_background_variant(values, true).into_iter().map(|x| x.0).collect();
+        assert_eq!(dark, vec!["title_color", "cpu.title"]);
+    }
+
+    // Checks modules can pick their own units, leaving the rest on the global one
+    #[test]
+    fn module_use_ibis() {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("crabfetch-ibis-{}.toml", std::process::id()));
+        std::fs::write(&path, "use_ibis = false\n[memory]\nuse_ibis = true\n").unwrap();
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&Some(path.display().to_string()), &None, &false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.memory.use_ibis, Some(true));
+        assert_eq!(config.mounts.use_ibis, None);
+        assert!(config.load_warnings.is_empty());
+    }
+
+    // Checks segments get wrapped around each category, and left off anything without one
+    #[test]
+    fn auto_segments() {
+        let modules: Vec<String> = ["hostname", "cpu", "gpu", "os", "shell", "space", "colors"].iter().map(|x| x.to_string()).collect();
+        assert_eq!(crate::config_manager::auto_segment_modules(&modules), vec![
+            "hostname",
+            "segment:Hardware", "cpu", "gpu", "end_segment",
+            "segment:Software", "os", "shell", "end_segment",
+            "space", "colors"
+        ]);
+    }
+
     // Test configs get created correctly, in the correct place and that the TOML is valid
     #[test]
     fn create_config() {
@@ -414,409 +828,52 @@ mod tests {
         assert!(removed.is_ok()); // Asserting this cus if the file fails to remove it's likely cus it never existed
     }
     
-    // Tests that the default-config.toml file is the same as the DEFAULT_CONFIG_CONTENTS string in
-    // here 
-    // In case anyone's wondering why they're separated; it's so that package maintainers or people
-    // who want a copy of the default config without re-genning it can have it without digging in
-    // CrabFetch's source code
-    // This test's just to make sure I keep it up to date and don't forget to update one or the
-    // other
+    // Tests that the defaults all come from default-config.toml, and that it covers every option
+    // The file's shipped as-is so that package maintainers or people who want a copy of the default
+    // config without re-genning it can have it without digging in CrabFetch's source code
     #[test]
-    fn config_is_consistent() {
-        use std::{path::{PathBuf, Path}, fs::File, io::Read};
-        let mut cargo_loc = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        cargo_loc.push("default-config.toml");
-        assert!(Path::new(&cargo_loc).exists());
-
-        let mut file: File = File::open(cargo_loc).unwrap();
-        let mut file_contents: String = String::new();
-        let _ = file.read_to_string(&mut file_contents);
-        // File saving will sometimes add new lines in different places than the rust ver, so I
-        // don't bother checking it as it just causes problems
-        file_contents = file_contents.replace('\n', "");
-        let comparing: &str = &crate::config_manager::DEFAULT_CONFIG_CONTENTS.replace('\n', "");
-
-        assert_eq!(file_contents, comparing);
+    fn defaults_from_default_config() {
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        assert_eq!(config.separator, " > ");
+        assert_eq!(config.cpu.format.source(), "{name} {arch} ({core_count}c {thread_count}t) @ {max_clock_ghz} GHz");
+        assert_eq!(config.ascii.margin, 4);
+        assert!(config.mounts.ignore.is_empty());
     }
-}
-
-// The default config, stored so that it can be written
-const DEFAULT_CONFIG_CONTENTS: &str = r#"# For more in-depth configuration documentation, please view https://github.com/LivacoNew/CrabFetch/wiki
-
-
-# The modules to display and in what order.
-# Again for a full list of modules, go to the documentation above.
-modules = [
-    "hostname",
-    "underline:16",
-
-    "cpu",
-    "gpu",
-    "memory",
-    "swap",
-    "mounts",
-    "host",
-    "displays",
-
-    "os",
-    "packages",
-    "desktop",
-    "terminal",
-    "shell",
-    "editor",
-    "uptime",
-    "locale",
-    "player",
-    "initsys",
-    "processes",
-    "battery",
-    "localip",
-
-    "space",
-    "colors",
-    "bright_colors"
-]
-
-# Whether to treat unknown modules as a raw text output, allowing you to use custom strings n stuff.
-# Yes, these support color placeholders.
-unknown_as_text = false
-
-# The default separator between a modules title and it's value
-separator = " > "
-# The default color of a modules title
-# Can be; black, red, green, yellow, blue, magenta, cyan, white
-# All of these can be prefixed with "bright_" to be lighter versions, e.g bright_red
-# REQUIRES use_os_color TO BE OFF
-title_color = "bright_magenta"
-# Whether to bold/italic the title by default too
-title_bold = true
-title_italic = false
-
-# The default decimal places to provide in a module
-decimal_places = 2
-
-# Whether to have all module values as inline, e.g; https://i.imgur.com/UNyq2zj.png
-# To add padding use the "separator" and add some spaces
-inline_values = false
-
-# The character to use in the underline module
-underline_character = '―'
-
-# The character for each color in the colors module
-color_character = "   "
-# Margin between each character
-color_margin = 0
-# And if to set the color to the background instead of on the character
-color_use_background = true
-
-# Whether to use the distro's preferred color for the title and ASCII displays
-# Disable to use custom default title colors, or custom ASCII colors
-use_os_color = true
-
-# Format of segments
-# Segments can be defined in the modules array
-segment_top = "{color-white}[======------{color-brightmagenta} {name} {color-white}------======]"
-segment_bottom = "{color-white}[======------{color-brightmagenta} {name_sized_gap} {color-white}------======]"
-
-# Formatting characters used in progress bars 
-progress_left_border = '['
-progress_right_border = ']'
-progress_progress = '='
-progress_empty = ' '
-# The target length of the progress bar
-progress_target_length = 20
-
-# Whether to use 'ibibytes opposed to 'gabytes 
-# E.g use Gibibytes (GiB) opposed to Gigabytes (GB)
-use_ibis = false
-
-# Whether to use known checksums to attempt to find the version of some stuff e.g terminal/shell/editor
-# Disabled by default as it was seen as "too cheaty"
-# If your benchmarking, disable it as well. If your a end user, you likely won't care if it's on or not.
-use_version_checksums = false
-
-# Whether to supress any errors that come or not
-suppress_errors = true
-
-# Percentage coloring thresholds 
-# Empty this section to make it not color 
-# Values are in the format of "{percentage}:{color}"
-percentage_color_thresholds = [
-    "75:brightgreen",
-    "85:brightyellow",
-    "90:brightred"
-]
-
-
-[ascii]
-# If to display the ASCII distro art or not
-display = true
-
-# The colors to render the ASCII in
-# This array can be as long as the actual ASCII. Each entry represents the color at a certain %
-# E.g ["red", "green"] would render the top half as red and the bottom half as green.
-# ["yellow", "blue", "magenta"] would render 33.33% as yellow, then blue, than magenta.
-#
-# REQUIRES use_os_color TO BE OFF
-colors = ["bright_magenta"]
-
-# The amount of space to put between the ASCII and the info
-margin = 4
-
-# Which side of the modules to display the ASCII on, left, right, top or bottom
-side = "left"
-
-
-
-
-# Below here is the actual modules
-# Refer to the wiki for any module-specific parameters or hidden parameters
-# Also remember that you can override some stuff on these, e.g the title formatting. Again check the wiki.
-
-[hostname]
-# Placeholders;
-# {hostname} -> The hostname
-# {username} -> The username of the current user
-title = ""
-format = "{color-title}{username}{color-white}@{color-title}{hostname}"
-
-
-[cpu]
-# Placeholders;
-# {name} -> The name of the cpu.
-# {core_count} -> The number of cores.
-# {thread_count} -> The number of threads.
-# {current_clock_mhz} -> The current clock speed, in MHz.
-# {current_clock_ghz} -> The current clock speed, in GHz.
-# {max_clock_mhz} -> The maximum clock speed, in MHz.
-# {max_clock_ghz} -> The maximum clock speed, in GHz.
-# {arch} -> The architecture of your CPU.
-title = "CPU"
-format = "{name} {arch} ({core_count}c {thread_count}t) @ {max_clock_ghz} GHz"
-
-# Whether to attempt to remove any trailing "x-Core Processor" left in the branding name by the manufacturer
-# May not be perfect, disable and report an issue if output looks odd.
-remove_trailing_processor = true
-
-
-[gpu]
-# Whether to try to search a separate AMD specific file to try to improve accuracy on AMD GPU's 
-amd_accuracy = true
-
-# Ignore any GPU's that are marked as "disabled" by Linux
-ignore_disabled_gpus = true
-
-
-# Placeholders;
-# - {index} -> The index of the GPU, only useful if you have more than one GPU.
-# - {vendor} -> The vendor of the GPU, e.g AMD
-# - {model} -> The model of the GPU, e.g Radeon RX 7800XT
-# - {vram} -> The total memory of the GPU.
-title = "GPU"
-format = "{vendor} {model} ({vram})"
-
-
-[memory]
-# Placeholders;
-# {used} -> The currently in-use memory.
-# {max} -> The maximum total memory.
-# {bar} -> A progress bar representing the total space available/taken.
-# {percent} -> Percentage of memory used
-title = "Memory"
-format = "{used} / {max} ({percent})"
-
-
-[swap]
-# Placeholders;
-# {used} -> The currently used swap.
-# {max} -> The maximum total swap.
-# {bar} -> A progress bar representing the total space available/taken.
-# {percent} -> Percentage of swap used
-title = "Swap"
-format = "{used} / {total} ({percent})"
-
-
-[mounts]
-# This module is a multi-line module, each mount has it's own line in the output. 
-# Placeholders;
-# {device} -> Device, e.g /dev/sda
-# {mount} -> The mount point, e.g /home
-# {space_used} -> The space used.
-# {space_avail} -> The space available.
-# {space_total} -> The total space.
-# {filesystem} -> The filesystem running on that mount.
-# {bar} -> A progress bar representing the total space available/taken.
-# {percent} -> The percentage of the disk used.
-title = "Disk ({mount})"
-format = "{space_used} used of {space_total} ({percent}) [{filesystem}]"
-
-# A ignore list for any point points OR filesystems to ignore
-# The entries only need to start with these to be ignored
-# It's also worth noting that CrabFetch automatically ignores any non-physical device mount
-ignore = []
-
-
-[host]
-# Placeholders;
-# {host} -> The name of the host, either a motherboard name or a laptop model
-# {chassis} -> The chassis type, e.g Desktop or Laptop or whatever
-title = "Host"
-format = "{host} ({chassis})"
-
-# Whether to output the chassis on it's own line to remain consistent with other fetch scripts.
-newline_chassis = false
-# The title/format of the chassis if we are outputting on it's own line
-chassis_title = "Chassis"
-chassis_format = "{chassis}"
-
-
-[displays]
-# This module is a multi-line module, each display will have it's own line in the output.
-# Placeholders;
-# {make} -> The monitor's make
-# {model} -> The monitor's model
-# {name} -> The monitor DRM name, e.g DP-2
-# {width} -> The monitor's width
-# {height} -> The monitor's height
-# {refresh_rate} -> The monitor's refresh rate. This won't work in x11!
-title = "Display ({make} {model})"
-format = "{width}x{height} @ {refresh_rate}Hz ({name})"
-
-# Whether to scale the width/height according to the screen's scale. Only availabe on Wayland.
-# **This will output wrong with fractional scaling**, as the library we use to interact with Wayland doesn't support fractional scaling yet.
-scale_size = false
-
-
-[os]
-# Placeholders;
-# {distro} -> The distro name
-# {kernel} -> The kernel version
-title = "Operating System"
-format = "{distro} ({kernel})"
-
-# Display the kernel version on a newline and if so, what format to use 
-newline_kernel = false
-kernel_title = "Kernel"
-kernel_format = "Linux {kernel}"
-
-
-[packages]
-# This format is for each entry, with all entries being combined into a single string separated by a comma. Placeholders;
-# {manager} -> The name of the manager
-# {count} -> The amount of packages that manager reports
-title = "Packages"
-format = "{count} ({manager})"
-
-# List of package managers to ignore, for whatever reason you choose to
-ignore = []
-
-
-[desktop]
-# Placeholders;
-# {desktop} -> The name of the desktop
-# {display_type} -> The type of display server, aka x11 or wayland.
-title = "Desktop"
-format = "{desktop} ({display_type})"
-
-
-[terminal]
-# Placeholders;
-# {name} -> The name of the terminal, e.g kitty
-# {path} -> The path of the terminal, e.g /usr/bin/kitty
-# {version} -> The version of the terminal
-title = "Terminal"
-format = "{name} {version}"
-
-
-[shell]
-# Placeholders;
-# {name} -> The name of the shell, e.g zsh
-# {path} -> The path of the shell, e.g /usr/bin/zsh
-# {version} -> The version of the shell.
-title = "Shell"
-format = "{name} {version}"
-
-# Whether to show your default shell, instead of your current shell.
-show_default_shell = false
-
-
-[uptime]
-title = "Uptime"
-
-
-[editor]
-# Placeholders;
-# {name} -> The name of the editor
-# {path} -> The path the editor is at
-# {version} -> The version of the editor.
-title = "Editor"
-format = "{name} {version}"
-
-# Whether to turn the name into a "fancy" variant. E.g "nvim" gets turned into "NeoVim"
-fancy = true
-
-
-[locale]
-# Placeholders;
-# {language} - The selected language
-# {encoding} - The encoding selected, most likely UTF-8
-title = "Locale"
-format = "{language} ({encoding})"
-
-
-[player]
-# This is a multi-line module, each player detected will have it's own line in the output
-# Placeholders;
-# {player} -> The player currently playing
-# {track} - The name of the track
-# {album} - The name of the album
-# {track_artists} - The names of all track artists
-# {album_artists} - The names of all album artists
-# {status} - The status of the player, AKA if it's playing or not.
-title = "Player ({player})"
-format = "{track} by {track_artists} ({album}) [{status}]"
-
-# Any music players to ignore
-# These must be valid MPRIS player strings. You can find them by running something like `playerctl --list-all`
-ignore = []
-
-
-[battery]
-# Placeholders;
-# {index} -> The batterys index
-# {percentage} -> The battery percentage
-# {bar} -> A progeress bar representing how full the battery is
-title = "Battery {index}"
-format = "{percentage}%"
-
-
-[initsys]
-# Placeholders;
-# {name} -> The name of the init system
-# {path} -> The path to the init system binary
-# {version} -> The version of the init system
-title = "Init System"
-format = "{name} {version}"
-
-
-[processes]
-title = "Total Processes"
-
-
-[datetime]
-title = "Date Time"
-# Available placeholders; https://docs.rs/chrono/latest/chrono/format/strftime/index.html#specifiers
-# CrabFetch wiki page coming soon for it instead (tm)
-format = "%H:%M:%S on %e %B %G"
-
-[localip]
-# This is a multi-line module, each IP/interface detected will have it's own line in the output
-# Placeholders;
-# {interface} -> The name of the interface, along with if it's IPV4 or IPV6
-# {addr} -> The IP address
-title = "Local IP ({interface})"
-format = "{addr}"
 
+    // Tests named colors get used in color options, and bad ones are warned about
+    #[test]
+    fn color_aliases() {
+        let mut values: Vec<(String, config::Value)> = vec![
+            ("title_color".to_string(), config::Value::from("accent")),
+            ("percentage_color_thresholds".to_string(), config::Value::from(vec!["90:accent", "100:red"])),
+            ("ascii.colors".to_string(), config::Value::from(vec!["accent", "red"])),
+            ("colors.accent".to_string(), config::Value::from("#cba6f7")),
+            ("colors.bad".to_string(), config::Value::from("notacolor")),
+        ];
+        let mut warnings: Vec<String> = Vec::new();
+        let colors: Vec<(String, String)> = super::take_color_aliases(&mut values, &mut warnings);
+        assert_eq!(colors, vec![("accent".to_string(), "#cba6f7".to_string())]);
+        assert_eq!(warnings, vec!["'colors.bad' isn't a valid color, it'll be ignored"]);
+
+        let values: Vec<(String, config::Value)> = super::apply_color_aliases(values, &colors);
+        let strings = |x: &config::Value| -> Vec<String> { x.clone().into_array().unwrap().into_iter().map(|y| y.into_string().unwrap()).collect() };
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[0].1.clone().into_string().unwrap(), "#cba6f7");
+        assert_eq!(strings(&values[1].1), vec!["90:#cba6f7", "100:red"]);
+        assert_eq!(strings(&values[2].1), vec!["#cba6f7", "red"]);
+    }
 
+    // Tests the XDG data dirs are searched in the right order, falling back when they're unset or empty
+    #[test]
+    fn data_dirs() {
+        use std::path::PathBuf;
+        let dirs = |a: Option<&str>, b: Option<&str>, c: Option<&str>| crate::config_manager::data_dirs(a.map(String::from), b.map(String::from), c.map(String::from));
+        assert_eq!(dirs(None, Some("/home/crab"), None), vec![PathBuf::from("/home/crab/.local/share"), PathBuf::from("/usr/local/share"), PathBuf::from("/usr/share")]);
+        assert_eq!(dirs(Some("/data"), Some("/home/crab"), Some("/opt/share::/usr/share")), vec![PathBuf::from("/data"), PathBuf::from("/opt/share"), PathBuf::from("/usr/share")]);
+        assert_eq!(dirs(Some(""), None, Some("")), vec![PathBuf::from("/usr/local/share"), PathBuf::from("/usr/share")]);
+    }
+}
 
-# You've reached the end! Congrats, have a muffin :)"#;
+// The default config, stored so that it can be written
+// This is also where every option's default value comes from
+const DEFAULT_CONFIG_CONTENTS: &str = include_str!("../default-config.toml");
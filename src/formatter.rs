@@ -1,15 +1,14 @@
 // Works in conjunction with ColoredString crate, for now...
-use std::str::FromStr;
+use std::{env, fmt::Display, path::Path, str::FromStr, sync::OnceLock};
 
 use colored::{ColoredString, Colorize};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
-use crate::config_manager::Configuration;
+use crate::{background, config_manager::Configuration, modules::os, syscalls::SyscallCache};
 
 // This is a hack to get the color deserializaton working
 // Essentially it uses my own enum, and to print it you need to call color_string
-#[derive(Deserialize, Debug, Clone, PartialEq)]
-#[serde(rename_all = "snake_case")]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CrabFetchColor {
     Black,
     Red,
@@ -27,13 +26,32 @@ pub enum CrabFetchColor {
     BrightMagenta,
     BrightCyan,
     BrightWhite,
-    Clear
+    Clear,
+    // Any color as a hex code, e.g "#e95420". Needs a terminal with truecolor support
+    Rgb(u8, u8, u8),
+    // A color from the terminal's 256 color palette, e.g "color256-208"
+    // For terminals where truecolor is unreliable, but the palette is fine
+    Indexed(u8)
+}
+impl<'de> Deserialize<'de> for CrabFetchColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let color: String = String::deserialize(deserializer)?;
+        CrabFetchColor::from_str(&color).map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", color)))
+    }
 }
 impl FromStr for CrabFetchColor {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
+        if let Some((r, g, b)) = parse_hex_color(s) {
+            return Ok(CrabFetchColor::Rgb(r, g, b));
+        }
+        if let Some(index) = s.strip_prefix("color256-") {
+            return index.parse::<u8>().map(CrabFetchColor::Indexed).map_err(|_| ());
+        }
+
+        // The config uses bright_red, placeholders use brightred
+        match s.to_lowercase().replace('_', "").as_str() {
             "black" => Ok(CrabFetchColor::Black),
             "red" => Ok(CrabFetchColor::Red),
             "green" => Ok(CrabFetchColor::Green),
@@ -75,45 +93,675 @@ impl CrabFetchColor {
             CrabFetchColor::BrightCyan => string.bright_cyan(),
             CrabFetchColor::BrightWhite => string.bright_white(),
             CrabFetchColor::Clear => string.clear(),
+            CrabFetchColor::Rgb(r, g, b) => string.truecolor(*r, *g, *b),
+            // Colored doesn't do the 256 color palette, so this one's done by hand
+            CrabFetchColor::Indexed(i) => if colored::control::SHOULD_COLORIZE.should_colorize() {
+                ColoredString::from(format!("\x1b[38;5;{}m{}\x1b[0m", i, string).as_str())
+            } else {
+                string.normal()
+            },
+        }
+    }
+
+    // Roughly what the terminal shows each color as, using xterm's defaults
+    // Used when we need to do something with the actual values, e.g gradients
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            CrabFetchColor::Black => Some((0, 0, 0)),
+            CrabFetchColor::Red => Some((205, 0, 0)),
+            CrabFetchColor::Green => Some((0, 205, 0)),
+            CrabFetchColor::Yellow => Some((205, 205, 0)),
+            CrabFetchColor::Blue => Some((0, 0, 238)),
+            CrabFetchColor::Magenta => Some((205, 0, 205)),
+            CrabFetchColor::Cyan => Some((0, 205, 205)),
+            CrabFetchColor::White => Some((229, 229, 229)),
+            CrabFetchColor::BrightBlack => Some((127, 127, 127)),
+            CrabFetchColor::BrightRed => Some((255, 0, 0)),
+            CrabFetchColor::BrightGreen => Some((0, 255, 0)),
+            CrabFetchColor::BrightYellow => Some((255, 255, 0)),
+            CrabFetchColor::BrightBlue => Some((92, 92, 255)),
+            CrabFetchColor::BrightMagenta => Some((255, 0, 255)),
+            CrabFetchColor::BrightCyan => Some((0, 255, 255)),
+            CrabFetchColor::BrightWhite => Some((255, 255, 255)),
+            CrabFetchColor::Clear => None,
+            CrabFetchColor::Rgb(r, g, b) => Some((*r, *g, *b)),
+            CrabFetchColor::Indexed(i) => Some(indexed_rgb(*i)),
+        }
+    }
+}
+
+// Works out the RGB of a 256 color palette entry, using xterm's defaults
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    const BASIC: [CrabFetchColor; 16] = [
+        CrabFetchColor::Black, CrabFetchColor::Red, CrabFetchColor::Green, CrabFetchColor::Yellow,
+        CrabFetchColor::Blue, CrabFetchColor::Magenta, CrabFetchColor::Cyan, CrabFetchColor::White,
+        CrabFetchColor::BrightBlack, CrabFetchColor::BrightRed, CrabFetchColor::BrightGreen, CrabFetchColor::BrightYellow,
+        CrabFetchColor::BrightBlue, CrabFetchColor::BrightMagenta, CrabFetchColor::BrightCyan, CrabFetchColor::BrightWhite,
+    ];
+    match index {
+        0..=15 => BASIC[index as usize].rgb().unwrap(),
+        // 6x6x6 color cube
+        16..=231 => {
+            let level = |x: u8| -> u8 { if x == 0 { 0 } else { 55 + x * 40 } };
+            let cube: u8 = index - 16;
+            (level(cube / 36), level((cube / 6) % 6), level(cube % 6))
+        },
+        // Grayscale ramp
+        _ => {
+            let gray: u8 = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        },
+    }
+}
+
+// Picks the color `position` (0-1) of the way through `colors`, blending between the two either side
+// Returns None if any of the colors can't be blended, e.g clear
+pub fn gradient_color(colors: &[CrabFetchColor], position: f32) -> Option<(u8, u8, u8)> {
+    if colors.is_empty() {
+        return None;
+    }
+    let position: f32 = position.clamp(0.0, 1.0) * (colors.len() - 1) as f32;
+    let index: usize = position.floor() as usize;
+    let start: (u8, u8, u8) = colors[index].rgb()?;
+    let end: (u8, u8, u8) = colors.get(index + 1).unwrap_or(&colors[index]).rgb()?;
+    let amount: f32 = position - index as f32;
+
+    let blend = |a: u8, b: u8| -> u8 { (a as f32 + (b as f32 - a as f32) * amount).round() as u8 };
+    Some((blend(start.0, end.0), blend(start.1, end.1), blend(start.2, end.2)))
+}
+
+// A title/format string from the config, split up into it's text and placeholders when the config
+// is loaded. This saves every module re-scanning the whole string for each placeholder it has,
+// every time it's styled (which adds up on multi-line modules like mounts)
+// Placeholders can have a decimal places suffix, e.g {percent:1}, which overrides decimal_places
+// They can also have a case suffix to change the text, e.g {vendor:upper}, {distro:lower} or {name:title}
+// Anything between {?<placeholder>} and {/?} is only shown if that placeholder has a value
+// They can also have a fallback for when they don't, e.g {version|dev-build} or {version|fallback:dev-build}
+// This is kept as written, so that it can be put back the same if the placeholder isn't known
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormatToken {
+    Text(String),
+    Placeholder(String, Option<u32>, Option<TextCase>, Option<String>),
+    Conditional(String, Vec<FormatToken>),
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextCase {
+    Upper,
+    Lower,
+    Title,
+}
+impl TextCase {
+    fn parse(name: &str) -> Option<TextCase> {
+        match name {
+            "upper" => Some(TextCase::Upper),
+            "lower" => Some(TextCase::Lower),
+            "title" => Some(TextCase::Title),
+            _ => None,
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            TextCase::Upper => "upper",
+            TextCase::Lower => "lower",
+            TextCase::Title => "title",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            TextCase::Upper => text.to_uppercase(),
+            TextCase::Lower => text.to_lowercase(),
+            // Each word gets a capital, and the rest lowercase, e.g "NVIDIA GeForce" becomes "Nvidia Geforce"
+            TextCase::Title => {
+                let mut result: String = String::with_capacity(text.len());
+                let mut word_start: bool = true;
+                for c in text.chars() {
+                    if word_start {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.extend(c.to_lowercase());
+                    }
+                    word_start = c.is_whitespace() || c == '-' || c == '_';
+                }
+                result
+            },
+        }
+    }
+}
+#[derive(Clone, Debug)]
+pub struct FormatTemplate {
+    source: String,
+    tokens: Vec<FormatToken>,
+}
+impl FormatTemplate {
+    pub fn parse(source: &str) -> FormatTemplate {
+        let mut tokens: Vec<FormatToken> = Vec::new();
+        // Conditionals that haven't been closed yet, along with the tokens from before they started
+        let mut open: Vec<(String, Vec<FormatToken>)> = Vec::new();
+        let mut text: String = String::new();
+        let mut remaining: &str = source;
+        while let Some(start) = remaining.find('{') {
+            text.push_str(&remaining[..start]);
+            remaining = &remaining[start..];
+
+            // Escaped braces are left escaped for the color placeholders to undo, otherwise they'd
+            // see it as one of theirs
+            if remaining.starts_with("{{") {
+                text.push_str("{{");
+                remaining = &remaining[2..];
+                continue;
+            }
+
+            // Anything that isn't a clean {placeholder} is left as text
+            let end: usize = match remaining.find('}') {
+                Some(r) => r,
+                None => break,
+            };
+            let name: &str = &remaining[1..end];
+            if name.is_empty() || name.contains('{') {
+                text.push('{');
+                remaining = &remaining[1..];
+                continue;
+            }
+
+            if !text.is_empty() {
+                tokens.push(FormatToken::Text(text));
+                text = String::new();
+            }
+            remaining = &remaining[end + 1..];
+            if let Some(condition) = name.strip_prefix('?').filter(|x| !x.is_empty()) {
+                open.push((condition.to_string(), std::mem::take(&mut tokens)));
+                continue;
+            }
+            if name == "/?" {
+                if let Some((condition, outer)) = open.pop() {
+                    let inner: Vec<FormatToken> = std::mem::replace(&mut tokens, outer);
+                    tokens.push(FormatToken::Conditional(condition, inner));
+                    continue;
+                }
+            }
+
+            let (name, fallback): (&str, Option<String>) = match name.split_once('|') {
+                Some((n, f)) if is_placeholder_name(n) => (n, Some(f.to_string())),
+                _ => (name, None),
+            };
+            let (name, places, case): (&str, Option<u32>, Option<TextCase>) = parse_suffixes(name);
+            tokens.push(FormatToken::Placeholder(name.to_string(), places, case, fallback));
+        }
+        text.push_str(remaining);
+        if !text.is_empty() {
+            tokens.push(FormatToken::Text(text));
+        }
+        // Anything left open just runs to the end
+        while let Some((condition, outer)) = open.pop() {
+            let inner: Vec<FormatToken> = std::mem::replace(&mut tokens, outer);
+            tokens.push(FormatToken::Conditional(condition, inner));
+        }
+
+        FormatTemplate {
+            source: source.to_string(),
+            tokens
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // Fills in each placeholder from `lookup`, then the global placeholders
+    // Anything neither has is left as-is, e.g color placeholders which are done after
+    pub fn render<F: Fn(&str) -> Option<String>>(&self, lookup: F) -> String {
+        self.render_with_places(0, |x, _| lookup(x))
+    }
+    // Same as render, but with set values for each placeholder, e.g for a module's unknown output
+    pub fn render_values(&self, values: &[(&str, &str)]) -> String {
+        self.render(|x| values.iter().find(|y| y.0 == x).map(|y| y.1.to_string()))
+    }
+    // Same as render, but also hands `lookup` the decimal places to use for that placeholder
+    // This is the placeholder's own suffix if it has one, otherwise `default_places`
+    pub fn render_with_places<F: Fn(&str, u32) -> Option<String>>(&self, default_places: u32, lookup: F) -> String {
+        let mut result: String = String::with_capacity(self.source.len());
+        render_tokens(&self.tokens, default_places, &lookup, &mut result);
+
+        result
+    }
+
+    // Every placeholder used, including the ones conditionals check
+    pub fn placeholder_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        collect_names(&self.tokens, &mut names);
+
+        names
+    }
+
+    // The source with any decimal places suffixes and conditionals removed, for gen_info_flags to check against
+    // A conditional's placeholder is kept, as it still needs detecting to know whether to show it
+    pub fn without_places(source: &str) -> String {
+        let mut result: String = String::new();
+        flatten_tokens(&FormatTemplate::parse(source).tokens, &mut result);
+
+        result
+    }
+}
+fn render_tokens<F: Fn(&str, u32) -> Option<String>>(tokens: &[FormatToken], default_places: u32, lookup: &F, result: &mut String) {
+    for token in tokens {
+        match token {
+            FormatToken::Text(text) => result.push_str(text),
+            FormatToken::Placeholder(name, places, case, fallback) => match lookup(name, places.unwrap_or(default_places)).or_else(|| global_placeholder(name)) {
+                Some(r) => match (fallback, case) {
+                    (Some(fallback), _) if is_missing(&r) => result.push_str(fallback.strip_prefix("fallback:").unwrap_or(fallback)),
+                    (_, Some(case)) => result.push_str(&case.apply(&r)),
+                    _ => result.push_str(&r),
+                },
+                None => {
+                    result.push('{');
+                    result.push_str(name);
+                    if let Some(places) = places {
+                        result.push(':');
+                        result.push_str(&places.to_string());
+                    }
+                    if let Some(case) = case {
+                        result.push(':');
+                        result.push_str(case.name());
+                    }
+                    if let Some(fallback) = fallback {
+                        result.push('|');
+                        result.push_str(fallback);
+                    }
+                    result.push('}');
+                },
+            },
+            FormatToken::Conditional(name, inner) => {
+                if lookup(name, default_places).or_else(|| global_placeholder(name)).is_some_and(|x| !is_missing(&x)) {
+                    render_tokens(inner, default_places, lookup, result);
+                }
+            },
+        }
+    }
+}
+fn collect_names(tokens: &[FormatToken], names: &mut Vec<String>) {
+    for token in tokens {
+        let name: &String = match token {
+            FormatToken::Text(_) => continue,
+            FormatToken::Placeholder(name, _, _, _) => name,
+            FormatToken::Conditional(name, inner) => {
+                collect_names(inner, names);
+                name
+            },
+        };
+        if !names.contains(name) {
+            names.push(name.to_string());
+        }
+    }
+}
+fn flatten_tokens(tokens: &[FormatToken], result: &mut String) {
+    for token in tokens {
+        match token {
+            FormatToken::Text(text) => result.push_str(text),
+            FormatToken::Placeholder(name, _, _, _) => result.push_str(&format!("{{{}}}", name)),
+            FormatToken::Conditional(name, inner) => {
+                result.push_str(&format!("{{{}}}", name));
+                flatten_tokens(inner, result);
+            },
+        }
+    }
+}
+// Splits off the decimal places and case suffixes, which can be in either order, e.g {clock:1:upper}
+// If any of them aren't one of those it's not ours, e.g {link:<url>}, so it's all kept as the name
+fn parse_suffixes(name: &str) -> (&str, Option<u32>, Option<TextCase>) {
+    let mut split = name.split(':');
+    let base: &str = split.next().unwrap_or_default();
+    if base.is_empty() || base.len() == name.len() {
+        return (name, None, None);
+    }
+
+    let mut places: Option<u32> = None;
+    let mut case: Option<TextCase> = None;
+    for suffix in split {
+        if let Ok(r) = suffix.parse::<u32>() {
+            places = Some(r);
+        } else if let Some(r) = TextCase::parse(suffix) {
+            case = Some(r);
+        } else {
+            return (name, None, None);
+        }
+    }
+
+    (base, places, case)
+}
+// Whether it's a placeholder, with optional decimal places or case suffixes, rather than something like a
+// {link:<url>} that might have a | in it
+fn is_placeholder_name(name: &str) -> bool {
+    let name: &str = name.split_once(':').map_or(name, |x| x.0);
+    !name.is_empty() && name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+}
+// Whether a placeholder's value is what modules put when they couldn't find it
+fn is_missing(value: &str) -> bool {
+    let value: &str = value.trim();
+    value.is_empty() || value == "Unknown" || value == "N/A"
+}
+impl Display for FormatTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+impl<'de> Deserialize<'de> for FormatTemplate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source: String = String::deserialize(deserializer)?;
+        Ok(FormatTemplate::parse(&source))
+    }
+}
+
+// Placeholders that work anywhere, no matter the module, along with the text modules and segments
+// A module's own placeholder of the same name wins, e.g the OS module's {kernel}
+const GLOBAL_PLACEHOLDERS: [&str; 5] = ["user", "home", "kernel", "distro_id", "term"];
+static GLOBAL_VALUES: OnceLock<Vec<String>> = OnceLock::new();
+
+pub fn is_global_placeholder(name: &str) -> bool {
+    GLOBAL_PLACEHOLDERS.contains(&name)
+}
+
+// These are only worked out the first time one's used, and then kept for the rest of the run
+fn global_placeholder(name: &str) -> Option<String> {
+    let index: usize = GLOBAL_PLACEHOLDERS.iter().position(|x| *x == name)?;
+    let values: &Vec<String> = GLOBAL_VALUES.get_or_init(|| {
+        let mut syscall_cache: SyscallCache = SyscallCache::new();
+        let user: String = env::var("USER").unwrap_or_else(|_| syscall_cache.get_passwd_cached().name);
+        let home: String = env::var("HOME").unwrap_or_else(|_| syscall_cache.get_passwd_cached().dir);
+        let kernel: String = syscall_cache.get_uname_cached().release;
+        let distro_id: String = os::parse_os_release(Path::new("/etc/os-release")).map(|x| x.1).unwrap_or("Unknown".to_string());
+        // Just what the terminal tells us, the terminal module's far more accurate but has to go
+        // through the process tree for it
+        let term: String = env::var("TERM_PROGRAM").or_else(|_| env::var("TERM")).unwrap_or("Unknown".to_string());
+
+        vec![user, home, kernel, distro_id.trim_matches('"').to_string(), term]
+    });
+
+    values.get(index).cloned()
+}
+
+// The styles {style-*} placeholders can use
+const STYLES: [&str; 5] = ["bold", "italic", "underline", "strike", "dim"];
+
+// The placeholders text modules have, e.g unknown_as_text or the banner module
+// {bg_is_dark} is only worked out if it's used, as it means asking the terminal
+pub fn replace_text_placeholders(str: &str, config: &Configuration) -> String {
+    let str: String = if str.contains("{bg_is_dark}") {
+        str.replace("{bg_is_dark}", &background::is_dark(&config.background).to_string())
+    } else {
+        str.to_string()
+    };
+    let str: String = match str.contains('{') {
+        true => FormatTemplate::parse(&str).render(|_| None),
+        false => str,
+    };
+
+    replace_env_placeholders(&str)
+}
+
+// Replaces {env:<VAR>} with the value of that environment variable
+// Anything that isn't set is left empty
+fn replace_env_placeholders(str: &str) -> String {
+    let mut result: String = String::with_capacity(str.len());
+    let mut remaining: &str = str;
+    while let Some(start) = remaining.find("{env:") {
+        result.push_str(&remaining[..start]);
+        remaining = &remaining[start..];
+        let name: Option<&str> = remaining.find('}')
+            .map(|x| &remaining[5..x])
+            .filter(|x| !x.is_empty() && !x.contains('{'));
+        match name {
+            Some(r) => {
+                result.push_str(&env::var(r).unwrap_or_default());
+                remaining = &remaining[r.len() + 6..];
+            },
+            None => {
+                result.push('{');
+                remaining = &remaining[1..];
+            },
         }
     }
+    result.push_str(remaining);
+
+    result
 }
 
+// Whether it's one of the placeholders replace_color_placeholders takes care of, rather than a module's
+pub fn is_style_placeholder(name: &str) -> bool {
+    ["reset", "/gradient", "/link"].contains(&name)
+        || ["color-", "color256-", "gradient-", "link:"].iter().any(|x| name.starts_with(x))
+        || name.strip_prefix("style-").is_some_and(|x| STYLES.contains(&x))
+}
+
+// Replaces the color and style placeholders, e.g {color-red}, {color256-208}, {style-bold} and {reset}
+// Also undoes escaped braces, so {{ and }} become { and }
+// A color lasts until the next color, while styles stack up until a {reset}
+// {gradient-<from>-<to>} blends between two colors across the text, up until a {/gradient}
+// {link:<url>} makes the text up until a {/link} a clickable link, in terminals that support OSC 8
 pub fn replace_color_placeholders(str: &str, config: &Configuration) -> String { 
-    let mut new_string = String::new();
-    let split: Vec<&str> = str.split("{color-").collect();
-    if split.len() <= 1 {
+    if !str.contains('{') && !str.contains("}}") {
         return str.to_string();
     }
-    for s in split {
-        let len: usize = match s.find('}') {
+
+    style_placeholders(str, config, None, Vec::new())
+}
+
+// The same as replace_color_placeholders, but starting off with a color and styles, e.g for a title
+pub fn style_placeholders(str: &str, config: &Configuration, color: Option<CrabFetchColor>, styles: Vec<&'static str>) -> String {
+    let mut styler: Styler = Styler {
+        output: String::new(),
+        text: String::new(),
+        color,
+        styles,
+        gradient: None,
+    };
+    let mut remaining: &str = str;
+    while let Some(start) = remaining.find('{') {
+        styler.text.push_str(&remaining[..start].replace("}}", "}"));
+        remaining = &remaining[start..];
+        if remaining.starts_with("{{") {
+            styler.text.push('{');
+            remaining = &remaining[2..];
+            continue;
+        }
+        let end: usize = match remaining.find('}') {
             Some(r) => r,
-            None => {
-                new_string.push_str(s);
-                continue;
-            },
+            None => break,
         };
-        let color_str: String = s[..len].to_string();
-        let color: CrabFetchColor = match CrabFetchColor::from_str(&color_str) {
-            Ok(r) => r,
-            Err(_) => if color_str == "title" {
-                config.title_color.clone()
-            } else {continue},
+        let placeholder: &str = &remaining[1..end];
+
+        let handled: bool = if placeholder == "reset" {
+            styler.end_gradient();
+            styler.color = None;
+            styler.styles.clear();
+            true
+        } else if placeholder == "/gradient" {
+            styler.end_gradient();
+            true
+        } else if let Some(url) = placeholder.strip_prefix("link:").filter(|x| !x.is_empty()) {
+            styler.flush();
+            styler.link(url);
+            true
+        } else if placeholder == "/link" {
+            styler.flush();
+            styler.link("");
+            true
+        } else if let Some(style) = placeholder.strip_prefix("style-").and_then(|x| STYLES.iter().find(|y| **y == x)) {
+            styler.flush();
+            styler.styles.push(style);
+            true
+        } else if let Some(colors) = placeholder.strip_prefix("gradient-").and_then(|x| parse_gradient(x, config)) {
+            styler.end_gradient();
+            styler.gradient = Some((colors, Vec::new()));
+            true
+        } else {
+            let new_color: Option<CrabFetchColor> = if let Some(r) = placeholder.strip_prefix("color-") {
+                placeholder_color(r, config)
+            } else if placeholder.starts_with("color256-") {
+                CrabFetchColor::from_str(placeholder).ok()
+            } else {
+                None
+            };
+            match new_color {
+                Some(r) => {
+                    styler.end_gradient();
+                    styler.color = Some(r);
+                    true
+                },
+                None => false,
+            }
         };
-        new_string.push_str(&color.color_string(&s[len + 1..]).to_string());
+
+        if handled {
+            remaining = &remaining[end + 1..];
+        } else {
+            // Not one of ours, leave it as it is
+            styler.text.push('{');
+            remaining = &remaining[1..];
+        }
     }
+    styler.text.push_str(&remaining.replace("}}", "}"));
+    styler.end_gradient();
 
-    new_string
+    styler.output
 }
 
-pub fn process_percentage_placeholder(text: &str, percentage: f32, config: &Configuration) -> String {
+// Each bit of text, and the styles it had
+type StyledParts = Vec<(String, Vec<&'static str>)>;
+// Keeps track of what's currently applied while going through the placeholders
+struct Styler {
+    output: String,
+    // Text that's waiting to be styled with the current color and styles
+    text: String,
+    color: Option<CrabFetchColor>,
+    styles: Vec<&'static str>,
+    // The colors to blend between, and the text inside of it so far
+    gradient: Option<([CrabFetchColor; 2], StyledParts)>,
+}
+impl Styler {
+    fn flush(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text: String = std::mem::take(&mut self.text);
+        match &mut self.gradient {
+            Some((_, parts)) => parts.push((text, self.styles.clone())),
+            None => self.output.push_str(&style_text(&text, &self.color, &self.styles)),
+        }
+    }
+
+    // An empty url ends the link
+    // Left out when colors are off, as then we're either piped somewhere or the terminal can't handle it
+    fn link(&mut self, url: &str) {
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            return;
+        }
+        self.end_gradient();
+        self.output.push_str(&format!("\x1b]8;;{}\x1b\\", url));
+    }
+
+    // The whole gradient's only known once it's over, as each character's color depends on how long it is
+    fn end_gradient(&mut self) {
+        self.flush();
+        let (colors, parts) = match self.gradient.take() {
+            Some(r) => r,
+            None => return,
+        };
+
+        let length: usize = parts.iter().map(|x| x.0.chars().count()).sum();
+        let mut index: usize = 0;
+        for (text, styles) in parts {
+            for c in text.chars() {
+                let position: f32 = if length > 1 { index as f32 / (length - 1) as f32 } else { 0.0 };
+                let color: Option<CrabFetchColor> = gradient_color(&colors, position).map(|(r, g, b)| CrabFetchColor::Rgb(r, g, b));
+                self.output.push_str(&style_text(&c.to_string(), &color, &styles));
+                index += 1;
+            }
+        }
+    }
+}
+
+// A color from a placeholder, including "title" for the title color and the user's own named colors
+fn placeholder_color(name: &str, config: &Configuration) -> Option<CrabFetchColor> {
+    if name == "title" {
+        return Some(config.title_color.clone());
+    }
+    if let Some(color) = config.colors.get(name) {
+        return Some(color.clone());
+    }
+    CrabFetchColor::from_str(name).ok()
+}
+
+// Colors can have dashes in them too (e.g color256-208), so every split is tried until both sides work
+fn parse_gradient(colors: &str, config: &Configuration) -> Option<[CrabFetchColor; 2]> {
+    colors.match_indices('-').find_map(|(i, _)| {
+        let from: CrabFetchColor = placeholder_color(&colors[..i], config)?;
+        let to: CrabFetchColor = placeholder_color(&colors[i + 1..], config)?;
+        Some([from, to])
+    })
+}
+
+fn style_text(text: &str, color: &Option<CrabFetchColor>, styles: &[&str]) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut styled: ColoredString = match color {
+        Some(r) => r.color_string(text),
+        None => text.normal(),
+    };
+    for style in styles {
+        styled = match *style {
+            "bold" => styled.bold(),
+            "italic" => styled.italic(),
+            "underline" => styled.underline(),
+            "strike" => styled.strikethrough(),
+            "dim" => styled.dimmed(),
+            _ => styled,
+        };
+    }
+
+    styled.to_string()
+}
+
+// Parses a "#rrggbb" or "#rgb" color into it's RGB values
+pub fn parse_hex_color(color: &str) -> Option<(u8, u8, u8)> {
+    let hex: &str = color.strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    match hex.len() {
+        6 => {
+            let r: u8 = u8::from_str_radix(&hex[0..2], 16).ok()?;
+            let g: u8 = u8::from_str_radix(&hex[2..4], 16).ok()?;
+            let b: u8 = u8::from_str_radix(&hex[4..6], 16).ok()?;
+            Some((r, g, b))
+        },
+        // Shorthand, each digit's doubled up e.g #f80 is #ff8800
+        3 => {
+            let r: u8 = u8::from_str_radix(&hex[0..1], 16).ok()?;
+            let g: u8 = u8::from_str_radix(&hex[1..2], 16).ok()?;
+            let b: u8 = u8::from_str_radix(&hex[2..3], 16).ok()?;
+            Some((r * 17, g * 17, b * 17))
+        },
+        _ => None,
+    }
+}
+
+// Formats a percentage for a {percent} placeholder, colored according to percentage_color_thresholds
+pub fn format_percentage(percentage: f32, config: &Configuration) -> String {
     let mut percent_str: String = percentage.to_string();
-    percent_str.push('%');
+    if config.percentage_sign {
+        percent_str.push('%');
+    }
     if config.percentage_color_thresholds.is_empty() {
-        return text.replace("{percent}", &percent_str).to_string();
+        return percent_str;
     }
 
-
+    percentage_color(percentage, config).color_string(&percent_str).to_string()
+}
+// The color from percentage_color_thresholds that the percentage falls under
+fn percentage_color(percentage: f32, config: &Configuration) -> CrabFetchColor {
     // This is done in a bit of a shit way but idc to improve it, im writing this after being awake
     // for 30 hours so I'm happy to push some shit to the repo for later me to deal with lol
     let mut color_assigned: bool = false;
@@ -139,11 +787,10 @@ pub fn process_percentage_placeholder(text: &str, percentage: f32, config: &Conf
         cur_color = min_color;
     }
 
-    percent_str = cur_color.color_string(&percent_str.to_string()).to_string();
-    text.replace("{percent}", &percent_str).to_string()
+    cur_color
 }
 
-pub fn auto_format_bytes(kilobytes: u64, ibis: bool, dec_places: u32) -> String {
+pub fn auto_format_bytes(kilobytes: u64, ibis: bool, dec_places: u32, unit_space: bool) -> String {
     let mut result: f64 = kilobytes as f64;
     let mut steps: u8 = 0; // 0 - Kilo, 1 - Mega, 2 - Giga, 3 - Tera 
     let divider = if ibis {1024} else {1000};
@@ -164,12 +811,15 @@ pub fn auto_format_bytes(kilobytes: u64, ibis: bool, dec_places: u32) -> String
 
     let dec_places: usize = dec_places as usize;
     let mut res: String = format!("{:.dec_places$}", result).to_string();
+    if unit_space {
+        res.push(' ');
+    }
     res.push_str(match steps {
-        0 => if ibis {" KiB"} else {" KB"},
-        1 => if ibis {" MiB"} else {" MB"},
-        2 => if ibis {" GiB"} else {" GB"},
-        3 => if ibis {" TiB"} else {" TB"},
-        _ => " ?"
+        0 => if ibis {"KiB"} else {"KB"},
+        1 => if ibis {"MiB"} else {"MB"},
+        2 => if ibis {"GiB"} else {"GB"},
+        3 => if ibis {"TiB"} else {"TB"},
+        _ => "?"
     });
 
     res
@@ -182,71 +832,450 @@ pub fn round(number: f64, places: u32) -> f64 {
     (number * power).round() / power
 }
 
+// Converts a temperature into temperature_unit, with the unit on the end
+// Anything producing a temperature should go through this, so they all follow the setting
+pub fn format_temperature(celsius: f32, dec_places: u32, config: &Configuration) -> String {
+    let (value, unit): (f32, &str) = match config.temperature_unit.as_str() {
+        "fahrenheit" => (celsius * 9.0 / 5.0 + 32.0, "°F"),
+        "kelvin" => (celsius + 273.15, "K"),
+        _ => (celsius, "°C"),
+    };
+
+    format!("{}{}", round(value as f64, dec_places), unit)
+}
+
 // Bar processing 
 // Modifies the bar string in place
 pub fn make_bar(bar: &mut String, left_border: &str, right_border: &str, progress_char: &str, empty_char: &str, target_percentage: f32, length: u8) {
+    make_colored_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length, "", |_, _| None);
+}
+// The same as make_bar, but colored according to progress_color and progress_empty_color
+#[allow(clippy::too_many_arguments)]
+pub fn make_styled_bar(bar: &mut String, left_border: &str, right_border: &str, progress_char: &str, empty_char: &str, target_percentage: f32, length: u8, config: &Configuration) {
+    make_labelled_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length, "", config);
+}
+// The same as make_styled_bar, but with the percentage either written over the middle of the bar or
+// put after it, depending on progress_percent_position
+#[allow(clippy::too_many_arguments)]
+pub fn make_percent_bar(bar: &mut String, left_border: &str, right_border: &str, progress_char: &str, empty_char: &str, target_percentage: f32, length: u8, dec_places: u32, config: &Configuration) {
+    let percentage: f32 = round(target_percentage as f64, dec_places) as f32;
+    if config.progress_percent_position == "append" {
+        make_styled_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length, config);
+        bar.push(' ');
+        bar.push_str(&format_percentage(percentage, config));
+        return;
+    }
+
+    let label: String = format!("{}{}", percentage, if config.percentage_sign { "%" } else { "" });
+    make_labelled_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length, &label, config);
+}
+#[allow(clippy::too_many_arguments)]
+fn make_labelled_bar(bar: &mut String, left_border: &str, right_border: &str, progress_char: &str, empty_char: &str, target_percentage: f32, length: u8, label: &str, config: &Configuration) {
+    let empty_color: Option<CrabFetchColor> = CrabFetchColor::from_str(&config.progress_empty_color).ok();
+    let fill_color: Option<CrabFetchColor> = match config.progress_color.as_str() {
+        "thresholds" if !config.percentage_color_thresholds.is_empty() => Some(percentage_color(target_percentage, config)),
+        _ => None,
+    };
+    // Blended across the whole bar, so the color of each part shows how far along it is
+    let gradient: Vec<CrabFetchColor> = if config.progress_color == "gradient" {
+        config.percentage_color_thresholds.iter()
+            .filter_map(|x| CrabFetchColor::from_str(x.split_once(':')?.1).ok())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if empty_color.is_none() && fill_color.is_none() && gradient.is_empty() && label.is_empty() {
+        return make_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length);
+    }
+
+    make_colored_bar(bar, left_border, right_border, progress_char, empty_char, target_percentage, length, label, |filled, position| {
+        if !filled {
+            return empty_color.clone();
+        }
+        if !gradient.is_empty() {
+            return gradient_color(&gradient, position).map(|(r, g, b)| CrabFetchColor::Rgb(r, g, b));
+        }
+        fill_color.clone()
+    });
+}
+// `color` is given whether that part's filled, and how far along the bar it is from 0 to 1
+// `label` is written over the middle of the bar, if it fits
+#[allow(clippy::too_many_arguments)]
+fn make_colored_bar<F: Fn(bool, f32) -> Option<CrabFetchColor>>(bar: &mut String, left_border: &str, right_border: &str, progress_char: &str, empty_char: &str, target_percentage: f32, length: u8, label: &str, color: F) {
     if length < (left_border.chars().count() as u8 + right_border.chars().count() as u8) {
         return; // Will crash otherwise, user's own fault
     }
     bar.push_str(left_border);
     let bar_length: u8 = length - 2;
+    let label: Vec<char> = label.chars().collect();
+    let label_start: usize = if label.len() <= bar_length as usize { (bar_length as usize - label.len()) / 2 } else { usize::MAX };
     for x in 0..(bar_length) {
-        if target_percentage as u8 > ((x as f32 / bar_length as f32) * 100.0) as u8 {
-            bar.push_str(progress_char);
-        } else {
-            bar.push_str(empty_char);
+        let filled: bool = target_percentage as u8 > ((x as f32 / bar_length as f32) * 100.0) as u8;
+        let label_char: Option<String> = (x as usize).checked_sub(label_start).and_then(|i| label.get(i)).map(|c| c.to_string());
+        let character: &str = match &label_char {
+            Some(r) => r,
+            None => if filled { progress_char } else { empty_char },
+        };
+        let position: f32 = if bar_length > 1 { x as f32 / (bar_length - 1) as f32 } else { 0.0 };
+        match color(filled, position) {
+            Some(r) => bar.push_str(&r.color_string(character).to_string()),
+            None => bar.push_str(character),
         }
     }
     bar.push_str(right_border);
 }
 
+// How many terminal columns a character takes up
+// Not a full implementation of the unicode width rules, but covers CJK, emoji and combining
+// characters which is what we actually run into with song titles etc
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0000..=0x001F | 0x007F..=0x009F => 0,
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+// Cuts a string down to `max_width` columns, replacing the end with an ellipsis if it's too long
+// Color codes don't count towards the width, and the colors get reset after the cut so they don't
+// bleed into whatever comes next
+pub fn truncate(text: &str, max_width: usize) -> String {
+    let width: usize = strip_ansi_escapes::strip_str(text).chars().map(char_width).sum();
+    if width <= max_width {
+        return text.to_string();
+    }
+
+    let mut result: String = String::new();
+    let mut cur_width: usize = 0;
+    let mut colored: bool = false;
+    let mut linked: bool = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&']') {
+            // OSC, e.g a link, which runs until ESC \ rather than a letter
+            let mut code: String = String::from(c);
+            while let Some(x) = chars.next() {
+                code.push(x);
+                if x == '\x07' || (x == '\x1b' && chars.peek() == Some(&'\\')) {
+                    code.extend(chars.next());
+                    break;
+                }
+            }
+            linked = !code.starts_with("\x1b]8;;\x1b");
+            result.push_str(&code);
+            continue;
+        }
+        if c == '\x1b' {
+            // Copy the whole escape code over as-is
+            result.push(c);
+            for x in chars.by_ref() {
+                result.push(x);
+                if x.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            colored = true;
+            continue;
+        }
+
+        let c_width: usize = char_width(c);
+        // -1 to leave room for the ellipsis
+        if cur_width + c_width > max_width.saturating_sub(1) {
+            break;
+        }
+        cur_width += c_width;
+        result.push(c);
+    }
+    if max_width > 0 {
+        result.push('…');
+    }
+    if colored {
+        result.push_str("\x1b[0m");
+    }
+    if linked {
+        result.push_str("\x1b]8;;\x1b\\");
+    }
+
+    result
+}
+
 
 mod tests {
+    // Test format strings get split into the right tokens, and render back correctly
+    #[test]
+    fn parse_format_template() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{used} / {max} ({percent})");
+        assert_eq!(template.tokens, vec![
+            super::FormatToken::Placeholder("used".to_string(), None, None, None),
+            super::FormatToken::Text(" / ".to_string()),
+            super::FormatToken::Placeholder("max".to_string(), None, None, None),
+            super::FormatToken::Text(" (".to_string()),
+            super::FormatToken::Placeholder("percent".to_string(), None, None, None),
+            super::FormatToken::Text(")".to_string()),
+        ]);
+
+        // Unknown placeholders are left alone for later (e.g colors), as is anything malformed
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{color-red}{name} {} {unclosed");
+        let rendered: String = template.render(|x| match x {
+            "name" => Some("CrabFetch".to_string()),
+            _ => None
+        });
+        assert_eq!(rendered, "{color-red}CrabFetch {} {unclosed");
+
+        // Escaped braces stay escaped until the color placeholders are done
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{{name}} {{{name}}}");
+        assert_eq!(template.render(|_| Some("x".to_string())), "{{name}} {{x}}");
+        assert_eq!(super::FormatTemplate::parse("").render(|_| None), "");
+    }
+
+    // Test decimal places suffixes override the default, and are kept on unknown placeholders
+    #[test]
+    fn format_template_places() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{clock} {clock:1} {clock:0} {other:3} {a:b} {:2}");
+        let rendered: String = template.render_with_places(2, |x, places| match x {
+            "clock" => Some(format!("{:.1$}", 4.5678, places as usize)),
+            _ => None
+        });
+        assert_eq!(rendered, "4.57 4.6 5 {other:3} {a:b} {:2}");
+        assert_eq!(super::FormatTemplate::without_places("{percent:1} / {max}"), "{percent} / {max}");
+    }
+
+    // Test conditional segments are left out when their placeholder has nothing to show
+    #[test]
+    fn format_template_conditionals() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{name}{?version} v{version}{/?}{?via} ({via}){/?}");
+        let render = |version: &str| template.render(|x| match x {
+            "name" => Some("kitty".to_string()),
+            "version" => Some(version.to_string()),
+            "via" => Some(String::new()),
+            _ => None
+        });
+        assert_eq!(render("0.35.2"), "kitty v0.35.2");
+        assert_eq!(render("Unknown"), "kitty");
+        assert_eq!(render(""), "kitty");
+
+        // Nested, unclosed, and stray closes
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{?a}a{?b}b{/?}{/?}{/?}{?b}b");
+        assert_eq!(template.render(|x| if x == "a" || x == "b" { Some(x.to_string()) } else { None }), "ab{/?}b");
+        assert_eq!(template.render(|x| if x == "a" { Some(x.to_string()) } else { None }), "a{/?}");
+        assert_eq!(super::FormatTemplate::without_places("{?version} v{version:1}{/?}"), "{version} v{version}");
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{color-red}{name}{?version} v{version}{/?}");
+        assert_eq!(template.render_values(&[("name", "Unknown"), ("version", "Unknown")]), "{color-red}Unknown");
+    }
+
+    // Test fallbacks are only used when the placeholder has nothing to show
+    #[test]
+    fn format_template_fallbacks() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{name|fallback:none} {version|dev-build} {percent:1|fallback:?} {version|}");
+        let rendered: String = template.render_with_places(2, |x, places| match x {
+            "name" => Some("kitty".to_string()),
+            "version" => Some("Unknown".to_string()),
+            "percent" => Some(format!("{:.1$}", 4.5678, places as usize)),
+            _ => None
+        });
+        assert_eq!(rendered, "kitty dev-build 4.6 ");
+
+        // Left alone if it's not a placeholder the module knows, and links can still have a | in them
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{other|fallback:x} {other:1|x} {link:https://crab.rs/?a|b}");
+        assert_eq!(template.render(|_| None), "{other|fallback:x} {other:1|x} {link:https://crab.rs/?a|b}");
+        assert_eq!(super::FormatTemplate::without_places("{version:1|fallback:x}"), "{version}");
+    }
+
+    // Test case suffixes change the value, but not fallbacks or unknown placeholders
+    #[test]
+    fn format_template_cases() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{vendor:upper} {vendor:lower} {name:title} {clock:1:upper} {version:upper|dev-build} {other:title} {a:shout}");
+        let rendered: String = template.render_with_places(2, |x, places| match x {
+            "vendor" => Some("Nvidia".to_string()),
+            "name" => Some("NVIDIA GeForce rtx-3070".to_string()),
+            "clock" => Some(format!("{:.1$}ghz", 4.5678, places as usize)),
+            "version" => Some("Unknown".to_string()),
+            _ => None
+        });
+        assert_eq!(rendered, "NVIDIA nvidia Nvidia Geforce Rtx-3070 4.6GHZ dev-build {other:title} {a:shout}");
+        assert_eq!(super::FormatTemplate::without_places("{distro:lower} {clock:upper:1}"), "{distro} {clock}");
+    }
+
     // Test bytes format correctly
     #[test]
     fn format_bytes() {
-        assert_eq!(crate::formatter::auto_format_bytes(15, false, 0), "15 KB");
-        assert_eq!(crate::formatter::auto_format_bytes(15, false, 1), "15.0 KB");
-        assert_eq!(crate::formatter::auto_format_bytes(15, false, 2), "15.00 KB");
-        assert_eq!(crate::formatter::auto_format_bytes(15, false, 3), "15.000 KB");
-
-        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 0), "2 MB");
-        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 1), "1.5 MB");
-        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 2), "1.53 MB");
-        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 3), "1.526 MB");
-
-        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 0), "2 GB");
-        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 1), "1.6 GB");
-        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 2), "1.56 GB");
-        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 3), "1.563 GB");
-
-        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 0), "16 TB");
-        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 1), "15.6 TB");
-        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 2), "15.63 TB");
-        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 3), "15.626 TB");
+        assert_eq!(crate::formatter::auto_format_bytes(15, false, 0, true), "15 KB");
+        assert_eq!(crate::formatter::auto_format_bytes(15, false, 1, true), "15.0 KB");
+        assert_eq!(crate::formatter::auto_format_bytes(15, false, 2, true), "15.00 KB");
+        assert_eq!(crate::formatter::auto_format_bytes(15, false, 3, true), "15.000 KB");
+
+        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 0, true), "2 MB");
+        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 1, true), "1.5 MB");
+        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 2, true), "1.53 MB");
+        assert_eq!(crate::formatter::auto_format_bytes(1526, false, 3, true), "1.526 MB");
+
+        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 0, true), "2 GB");
+        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 1, true), "1.6 GB");
+        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 2, true), "1.56 GB");
+        assert_eq!(crate::formatter::auto_format_bytes(1562600, false, 3, true), "1.563 GB");
+
+        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 0, true), "16 TB");
+        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 1, true), "15.6 TB");
+        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 2, true), "15.63 TB");
+        assert_eq!(crate::formatter::auto_format_bytes(15626234632, false, 3, true), "15.626 TB");
     }
     // Test 'ibibytes format correctly
     #[test]
     fn format_ibibytes() {
-        assert_eq!(super::auto_format_bytes(15, true, 0), "15 KiB");
-        assert_eq!(super::auto_format_bytes(15, true, 1), "14.6 KiB");
-        assert_eq!(super::auto_format_bytes(15, true, 2), "14.65 KiB");
-        assert_eq!(super::auto_format_bytes(15, true, 3), "14.648 KiB");
-
-        assert_eq!(super::auto_format_bytes(1526, true, 0), "1 MiB");
-        assert_eq!(super::auto_format_bytes(1526, true, 1), "1.5 MiB");
-        assert_eq!(super::auto_format_bytes(1526, true, 2), "1.46 MiB");
-        assert_eq!(super::auto_format_bytes(1526, true, 3), "1.455 MiB");
-
-        assert_eq!(super::auto_format_bytes(1562600, true, 0), "1 GiB");
-        assert_eq!(super::auto_format_bytes(1562600, true, 1), "1.5 GiB");
-        assert_eq!(super::auto_format_bytes(1562600, true, 2), "1.46 GiB");
-        assert_eq!(super::auto_format_bytes(1562600, true, 3), "1.455 GiB");
-
-        assert_eq!(super::auto_format_bytes(15626234632, true, 0), "14 TiB");
-        assert_eq!(super::auto_format_bytes(15626234632, true, 1), "14.2 TiB");
-        assert_eq!(super::auto_format_bytes(15626234632, true, 2), "14.21 TiB");
-        assert_eq!(super::auto_format_bytes(15626234632, true, 3), "14.212 TiB");
+        assert_eq!(super::auto_format_bytes(15, true, 0, true), "15 KiB");
+        assert_eq!(super::auto_format_bytes(15, true, 1, true), "14.6 KiB");
+        assert_eq!(super::auto_format_bytes(15, true, 2, true), "14.65 KiB");
+        assert_eq!(super::auto_format_bytes(15, true, 3, true), "14.648 KiB");
+
+        assert_eq!(super::auto_format_bytes(1526, true, 0, true), "1 MiB");
+        assert_eq!(super::auto_format_bytes(1526, true, 1, true), "1.5 MiB");
+        assert_eq!(super::auto_format_bytes(1526, true, 2, true), "1.46 MiB");
+        assert_eq!(super::auto_format_bytes(1526, true, 3, true), "1.455 MiB");
+
+        assert_eq!(super::auto_format_bytes(1562600, true, 0, true), "1 GiB");
+        assert_eq!(super::auto_format_bytes(1562600, true, 1, true), "1.5 GiB");
+        assert_eq!(super::auto_format_bytes(1562600, true, 2, true), "1.46 GiB");
+        assert_eq!(super::auto_format_bytes(1562600, true, 3, true), "1.455 GiB");
+
+        assert_eq!(super::auto_format_bytes(15626234632, true, 0, true), "14 TiB");
+        assert_eq!(super::auto_format_bytes(15626234632, true, 1, true), "14.2 TiB");
+        assert_eq!(super::auto_format_bytes(15626234632, true, 2, true), "14.21 TiB");
+        assert_eq!(super::auto_format_bytes(15626234632, true, 3, true), "14.212 TiB");
+    }
+    // Test the space before the unit can be left out
+    #[test]
+    fn format_bytes_no_space() {
+        assert_eq!(super::auto_format_bytes(15, false, 0, false), "15KB");
+        assert_eq!(super::auto_format_bytes(1526, false, 1, false), "1.5MB");
+        assert_eq!(super::auto_format_bytes(1562600, true, 2, false), "1.46GiB");
+    }
+    // Test hex colors parse, and bad ones don't
+    #[test]
+    fn hex_colors() {
+        assert_eq!(super::parse_hex_color("#e95420"), Some((233, 84, 32)));
+        assert_eq!(super::parse_hex_color("#FFFFFF"), Some((255, 255, 255)));
+        assert_eq!(super::parse_hex_color("e95420"), None);
+        assert_eq!(super::parse_hex_color("#e9542"), None);
+        assert_eq!(super::parse_hex_color("#gg0000"), None);
+        assert_eq!(super::parse_hex_color("#f80"), Some((255, 136, 0)));
+        assert_eq!(super::parse_hex_color("#f8"), None);
+
+        use std::str::FromStr;
+        assert_eq!(super::CrabFetchColor::from_str("#ff7f50"), Ok(super::CrabFetchColor::Rgb(255, 127, 80)));
+        assert_eq!(super::CrabFetchColor::from_str("bright_red"), Ok(super::CrabFetchColor::BrightRed));
+    }
+
+    // Checks 256 color palette entries parse and map onto xterm's palette
+    #[test]
+    fn indexed_colors() {
+        use std::str::FromStr;
+        assert_eq!(super::CrabFetchColor::from_str("color256-208"), Ok(super::CrabFetchColor::Indexed(208)));
+        assert_eq!(super::CrabFetchColor::from_str("color256-256"), Err(()));
+        assert_eq!(super::CrabFetchColor::Indexed(208).rgb(), Some((255, 135, 0)));
+        assert_eq!(super::CrabFetchColor::Indexed(9).rgb(), Some((255, 0, 0)));
+        assert_eq!(super::CrabFetchColor::Indexed(244).rgb(), Some((128, 128, 128)));
+    }
+
+    // Checks styles stack up across color changes until a reset, and unknown placeholders are left alone
+    #[test]
+    fn style_placeholders() {
+        colored::control::set_override(true);
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        let replaced: String = super::replace_color_placeholders("a{style-bold}b{color-red}c{reset}d{style-nope}{name}", &config);
+        assert_eq!(replaced, "a\x1b[1mb\x1b[0m\x1b[1;31mc\x1b[0md{style-nope}{name}");
+        assert_eq!(super::replace_color_placeholders("{{color-red}} }}", &config), "{color-red} }");
+    }
+
+    // Checks named colors can be used in placeholders, alongside the built in ones
+    #[test]
+    fn placeholder_color() {
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        config.colors.insert("accent".to_string(), super::CrabFetchColor::Rgb(203, 166, 247));
+        assert_eq!(super::placeholder_color("accent", &config), Some(super::CrabFetchColor::Rgb(203, 166, 247)));
+        assert_eq!(super::placeholder_color("red", &config), Some(super::CrabFetchColor::Red));
+        assert_eq!(super::placeholder_color("nope", &config), None);
+    }
+
+    // Checks placeholders are picked out for linting, leaving out the ones colors take care of
+    #[test]
+    fn placeholder_names() {
+        let template: super::FormatTemplate = super::FormatTemplate::parse("{color-red}{name}{?version} {version:1}{/?} {{escaped}} {style-bold}{style-nope}");
+        let names: Vec<String> = template.placeholder_names().into_iter().filter(|x| !super::is_style_placeholder(x)).collect();
+        assert_eq!(names, vec!["name", "version", "style-nope"]);
+    }
+
+    // Checks gradients blend across the text inside them, including across other placeholders
+    #[test]
+    fn gradient_placeholders() {
+        colored::control::set_override(true);
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        let replaced: String = super::replace_color_placeholders("{gradient-#ff0000-color256-21}a{style-bold}b{/gradient}c{gradient-nope-red}", &config);
+        assert_eq!(replaced, "\x1b[38;2;255;0;0ma\x1b[0m\x1b[1;38;2;0;0;255mb\x1b[0m\x1b[1mc{gradient-nope-red}\x1b[0m");
+    }
+
+    // Checks links get wrapped in OSC 8, keeping the colors inside of them
+    #[test]
+    fn link_placeholders() {
+        colored::control::set_override(true);
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        let replaced: String = super::replace_color_placeholders("{link:https://crab.rs}{color-red}Crab{/link} {link:}", &config);
+        assert_eq!(replaced, "\x1b]8;;https://crab.rs\x1b\\\x1b[31mCrab\x1b[0m\x1b]8;;\x1b\\\x1b[31m {link:}\x1b[0m");
+    }
+    // Test environment variables get filled in, with unset ones left empty
+    #[test]
+    fn env_placeholders() {
+        std::env::set_var("CRABFETCH_TEST_ENV", "crab");
+        assert_eq!(super::replace_env_placeholders("a {env:CRABFETCH_TEST_ENV} b"), "a crab b");
+        assert_eq!(super::replace_env_placeholders("{env:CRABFETCH_TEST_UNSET}|{env:}|{env:X"), "|{env:}|{env:X");
+    }
+
+    // Test global placeholders fill in anything the module doesn't have, but don't override the module's own
+    #[test]
+    fn global_placeholders() {
+        use crate::formatter::FormatTemplate;
+        let home: String = std::env::var("HOME").unwrap_or_default();
+        assert_eq!(FormatTemplate::parse("{home}").render(|_| None), home);
+        assert_eq!(FormatTemplate::parse("{kernel}").render(|x| (x == "kernel").then(|| "mine".to_string())), "mine");
+        assert_eq!(FormatTemplate::parse("{?user}{user:upper}{/?}").render(|_| None), FormatTemplate::parse("{user}").render(|_| None).to_uppercase());
+        assert_eq!(FormatTemplate::parse("{color-red}{nope}").render(|_| None), "{color-red}{nope}");
+    }
+
+    // Test temperatures get converted into the right unit
+    #[test]
+    fn temperatures() {
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        assert_eq!(super::format_temperature(45.25, 1, &config), "45.3°C");
+        config.temperature_unit = "fahrenheit".to_string();
+        assert_eq!(super::format_temperature(45.0, 0, &config), "113°F");
+        config.temperature_unit = "kelvin".to_string();
+        assert_eq!(super::format_temperature(45.0, 2, &config), "318.15K");
+    }
+    // Test gradients blend between the right colors
+    #[test]
+    fn gradient_colors() {
+        let colors: Vec<super::CrabFetchColor> = vec![super::CrabFetchColor::Black, super::CrabFetchColor::BrightWhite, super::CrabFetchColor::BrightRed];
+        assert_eq!(super::gradient_color(&colors, 0.0), Some((0, 0, 0)));
+        assert_eq!(super::gradient_color(&colors, 0.25), Some((128, 128, 128)));
+        assert_eq!(super::gradient_color(&colors, 0.5), Some((255, 255, 255)));
+        assert_eq!(super::gradient_color(&colors, 1.0), Some((255, 0, 0)));
+        assert_eq!(super::gradient_color(&[super::CrabFetchColor::Clear], 0.5), None);
+    }
+    // Test strings get cut down to the right terminal width
+    #[test]
+    fn truncate_strings() {
+        assert_eq!(super::truncate("Short", 10), "Short");
+        assert_eq!(super::truncate("Exactly 10", 10), "Exactly 10");
+        assert_eq!(super::truncate("A bit too long", 10), "A bit too…");
+        // Wide characters take up 2 columns, so don't get split in half
+        assert_eq!(super::truncate("残酷な天使のテーゼ", 8), "残酷な…");
+        assert_eq!(super::truncate("Anything", 0), "");
+        assert_eq!(super::truncate("\x1b[31mRed\x1b[0m and more", 6), "\x1b[31mRed\x1b[0m a…\x1b[0m");
+        assert_eq!(super::truncate("\x1b[31mShort\x1b[0m", 5), "\x1b[31mShort\x1b[0m");
+        assert_eq!(super::truncate("\x1b]8;;https://crab.rs\x1b\\Link\x1b]8;;\x1b\\ text", 6), "\x1b]8;;https://crab.rs\x1b\\Link\x1b]8;;\x1b\\ …");
+        assert_eq!(super::truncate("\x1b]8;;https://crab.rs\x1b\\Long link", 6), "\x1b]8;;https://crab.rs\x1b\\Long …\x1b]8;;\x1b\\");
     }
     // Test progress bars get created correctly
     #[test]
@@ -298,6 +1327,50 @@ mod tests {
         super::make_bar(&mut bar_str, "[", "]", "#", " ", 39.0, 1);
         assert_eq!(bar_str, "");
     }
+
+    // Test bars are colored by the thresholds or a gradient, and their empty part separately
+    #[test]
+    fn styled_bars() {
+        colored::control::set_override(true);
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        config.percentage_color_thresholds = vec!["50:green".to_string(), "100:red".to_string()];
+
+        let mut bar: String = String::new();
+        super::make_styled_bar(&mut bar, "[", "]", "=", "-", 75.0, 6, &config);
+        assert_eq!(bar, "[===-]");
+
+        config.progress_color = "thresholds".to_string();
+        config.progress_empty_color = "black".to_string();
+        let mut bar: String = String::new();
+        super::make_styled_bar(&mut bar, "[", "]", "=", "-", 75.0, 6, &config);
+        assert_eq!(bar, "[\u{1b}[32m=\u{1b}[0m\u{1b}[32m=\u{1b}[0m\u{1b}[32m=\u{1b}[0m\u{1b}[30m-\u{1b}[0m]");
+
+        config.progress_color = "gradient".to_string();
+        config.progress_empty_color = String::new();
+        config.percentage_color_thresholds = vec!["50:#000000".to_string(), "100:#ffffff".to_string()];
+        let mut bar: String = String::new();
+        super::make_styled_bar(&mut bar, "[", "]", "=", "-", 50.0, 5, &config);
+        assert_eq!(bar, "[\u{1b}[38;2;0;0;0m=\u{1b}[0m\u{1b}[38;2;128;128;128m=\u{1b}[0m-]");
+    }
+
+    // Test the percentage goes over the middle of the bar, or after it, and is left out if it won't fit
+    #[test]
+    fn percent_bars() {
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        config.percentage_color_thresholds.clear();
+
+        let mut bar: String = String::new();
+        super::make_percent_bar(&mut bar, "[", "]", "=", " ", 42.123, 12, 0, &config);
+        assert_eq!(bar, "[===42%    ]");
+        let mut bar: String = String::new();
+        super::make_percent_bar(&mut bar, "[", "]", "=", " ", 42.123, 4, 0, &config);
+        assert_eq!(bar, "[= ]");
+
+        config.progress_percent_position = "append".to_string();
+        let mut bar: String = String::new();
+        super::make_percent_bar(&mut bar, "[", "]", "=", " ", 42.123, 6, 1, &config);
+        assert_eq!(bar, "[==  ] 42.1%");
+    }
 }
 
 // A list of OS ID's + Their recommended colors for use_os_color 
@@ -0,0 +1,271 @@
+// Image logos, drawn in place of the ASCII art on terminals that can show images
+use std::{env, fs, mem};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{config_manager::Configuration, png, util};
+
+// Kitty wants the base64 sent in chunks no bigger than this
+const KITTY_CHUNK_SIZE: usize = 4096;
+// How long to wait for the terminal to answer us, in ms
+const TERMINAL_QUERY_TIMEOUT: u64 = 100;
+
+pub enum ImageProtocol {
+    Kitty,
+    Sixel,
+    ITerm,
+    // Not a protocol as such, just coloured text, for terminals that can't do any of the above
+    HalfBlock,
+}
+
+pub struct ImageLogo {
+    protocol: ImageProtocol,
+    data: Vec<u8>,
+    // Only decoded for the protocols that need it, kitty and iTerm2 take the PNG as-is
+    image: Option<png::Image>,
+    // Both in terminal cells, not pixels
+    pub width: u16,
+    pub height: u16,
+}
+impl ImageLogo {
+    // The lines of text that go in place of the ASCII
+    // For the real image protocols this is just blank space, as the image is drawn separately
+    pub fn text_lines(&self) -> Vec<String> {
+        match (&self.protocol, &self.image) {
+            (ImageProtocol::HalfBlock, Some(image)) => encode_half_blocks(image),
+            _ => vec![String::new(); self.height as usize],
+        }
+    }
+
+    // Returns the escape codes to draw the image with it's top left corner at `column` on the
+    // current line. The cursor is left at the start of that same line afterwards, so the
+    // ASCII/module output can carry on over the top of it as usual
+    pub fn draw(&self, column: u16) -> String {
+        if let ImageProtocol::HalfBlock = self.protocol {
+            // Drawn as part of the text instead
+            return String::new();
+        }
+
+        // Print enough newlines to scroll the terminal if we need to, otherwise the image would
+        // run off the bottom, then go back up to where we started
+        let mut out: String = "\n".repeat(self.height as usize);
+        out.push_str(&format!("\x1b[{}A\x1b[{}G", self.height, column + 1));
+        // Some protocols move the cursor after drawing, so save/restore it around them
+        out.push_str("\x1b7");
+        match self.protocol {
+            ImageProtocol::Kitty => self.draw_kitty(&mut out),
+            ImageProtocol::Sixel => self.draw_sixel(&mut out),
+            ImageProtocol::ITerm => self.draw_iterm(&mut out),
+            ImageProtocol::HalfBlock => {},
+        }
+        out.push_str("\x1b8\r");
+
+        out
+    }
+
+    fn draw_kitty(&self, out: &mut String) {
+        // https://sw.kovidgoyal.net/kitty/graphics-protocol/
+        // f=100 lets kitty decode the PNG itself, C=1 stops it moving the cursor, q=2 stops it
+        // replying to us
+        let encoded: String = STANDARD.encode(&self.data);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more: u8 = if i + 1 < chunks.len() {1} else {0};
+            out.push_str("\x1b_G");
+            if i == 0 {
+                out.push_str(&format!("a=T,f=100,c={},r={},C=1,q=2,", self.width, self.height));
+            }
+            out.push_str(&format!("m={};", more));
+            out.push_str(&String::from_utf8_lossy(chunk));
+            out.push_str("\x1b\\");
+        }
+    }
+
+    fn draw_iterm(&self, out: &mut String) {
+        // https://iterm2.com/documentation-images.html
+        // Unlike kitty this is sent all in one go
+        out.push_str(&format!("\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0;doNotMoveCursor=1:",
+            self.data.len(), self.width, self.height));
+        out.push_str(&STANDARD.encode(&self.data));
+        out.push('\x07');
+    }
+
+    fn draw_sixel(&self, out: &mut String) {
+        let image: &png::Image = match &self.image {
+            Some(r) => r,
+            None => return,
+        };
+        out.push_str(&encode_sixel(image));
+    }
+}
+
+// https://vt100.net/docs/vt3xx-gp/chapter14.html
+// Colors are cut down to a 6x6x6 cube, which is plenty for a logo and means we don't need to
+// bother working out a palette for the image
+fn encode_sixel(image: &png::Image) -> String {
+    // P2 of 1 leaves any pixels we don't draw transparent
+    let mut out: String = format!("\x1bP0;1;0q\"1;1;{};{}", image.width, image.height);
+
+    let color_index = |pixel: [u8; 4]| -> Option<usize> {
+        if pixel[3] < 128 {
+            return None;
+        }
+        let level = |x: u8| -> usize { (x as usize * 5 + 127) / 255 };
+        Some(level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]))
+    };
+
+    // Palette
+    let mut used: [bool; 216] = [false; 216];
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if let Some(i) = color_index(image.pixel(x, y)) {
+                used[i] = true;
+            }
+        }
+    }
+    for (i, _) in used.iter().enumerate().filter(|x| *x.1) {
+        // Sixel's RGB is in percent
+        out.push_str(&format!("#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20));
+    }
+
+    // Each "sixel" is a column of 6 pixels, so the image is done in bands of 6 rows, each color
+    // drawn over the band in turn
+    for band in (0..image.height).step_by(6) {
+        let mut columns: Vec<Vec<u8>> = vec![Vec::new(); 216];
+        for row in 0..6.min(image.height - band) {
+            for x in 0..image.width {
+                if let Some(i) = color_index(image.pixel(x, band + row)) {
+                    if columns[i].is_empty() {
+                        columns[i] = vec![0; image.width as usize];
+                    }
+                    columns[i][x as usize] |= 1 << row;
+                }
+            }
+        }
+
+        for (i, column) in columns.iter().enumerate().filter(|x| !x.1.is_empty()) {
+            out.push_str(&format!("#{}", i));
+            // Run length encoded, as most of the image will be the same color in a row
+            let mut x: usize = 0;
+            while x < column.len() {
+                let mut run: usize = 1;
+                while x + run < column.len() && column[x + run] == column[x] {
+                    run += 1;
+                }
+                let c: char = (column[x] + 63) as char;
+                if run > 3 {
+                    out.push_str(&format!("!{}{}", run, c));
+                } else {
+                    out.push_str(&c.to_string().repeat(run));
+                }
+                x += run;
+            }
+            out.push('$'); // back to the start of the band for the next color
+        }
+        out.push('-'); // next band
+    }
+    out.push_str("\x1b\\");
+
+    out
+}
+
+// Each cell is 2 pixels, the top one as the foreground of a ▀ and the bottom one as it's background
+// Transparent pixels are left as the terminal's background
+fn encode_half_blocks(image: &png::Image) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for y in (0..image.height).step_by(2) {
+        let mut line: String = String::new();
+        for x in 0..image.width {
+            let top: [u8; 4] = image.pixel(x, y);
+            let bottom: [u8; 4] = if y + 1 < image.height {image.pixel(x, y + 1)} else {[0; 4]};
+            match (top[3] >= 128, bottom[3] >= 128) {
+                (true, true) => line.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀", top[0], top[1], top[2], bottom[0], bottom[1], bottom[2])),
+                (true, false) => line.push_str(&format!("\x1b[49m\x1b[38;2;{};{};{}m▀", top[0], top[1], top[2])),
+                (false, true) => line.push_str(&format!("\x1b[49m\x1b[38;2;{};{};{}m▄", bottom[0], bottom[1], bottom[2])),
+                (false, false) => line.push_str("\x1b[0m "),
+            }
+        }
+        line.push_str("\x1b[0m");
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Prefers going off what the terminal tells us about itself, as it's instant. Anything we can't
+// tell from that is asked directly, and if all else fails it's drawn with half blocks
+pub fn detect_protocol() -> ImageProtocol {
+    let term: String = env::var("TERM").unwrap_or_default();
+    let term_program: String = env::var("TERM_PROGRAM").unwrap_or_default();
+
+    if env::var("KITTY_WINDOW_ID").is_ok() || term == "xterm-kitty" || term == "xterm-ghostty" || term_program == "ghostty" {
+        return ImageProtocol::Kitty;
+    }
+    // WezTerm does kitty's protocol too, but its support for iTerm2's is more complete
+    // LC_TERMINAL is what iTerm2 passes through over SSH, as TERM_PROGRAM doesn't make it
+    if term_program == "iTerm.app" || term_program == "WezTerm" || env::var("LC_TERMINAL").is_ok_and(|x| x == "iTerm2") {
+        return ImageProtocol::ITerm;
+    }
+    if term.starts_with("foot") || term.starts_with("mlterm") {
+        return ImageProtocol::Sixel;
+    }
+
+    // Primary device attributes, a 4 in the reply means the terminal can do sixel
+    // e.g xterm replies with something like \x1b[?63;1;2;4;6;9;15;22c
+    if let Some(reply) = util::query_terminal("\x1b[c", TERMINAL_QUERY_TIMEOUT, |x| x.ends_with(b"c")) {
+        if reply.trim_start_matches("\x1b[?").trim_end_matches('c').split(';').any(|x| x == "4") {
+            return ImageProtocol::Sixel;
+        }
+    }
+
+    ImageProtocol::HalfBlock
+}
+
+// The size of a terminal cell in pixels, to know how big to make the image
+fn cell_size() -> (u32, u32) {
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+    let success: bool = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if !success || size.ws_col == 0 || size.ws_row == 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 {
+        // Not every terminal fills in the pixel size, so just go with something sensible
+        return (10, 20);
+    }
+
+    (size.ws_xpixel as u32 / size.ws_col as u32, size.ws_ypixel as u32 / size.ws_row as u32)
+}
+
+pub fn get_image(config: &Configuration, protocol: ImageProtocol) -> Result<ImageLogo, String> {
+    let path: String = shellexpand::tilde(&config.ascii.image_path).to_string();
+    let data: Vec<u8> = match fs::read(&path) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Unable to read image {}: {}", path, e)),
+    };
+
+    let image: Option<png::Image> = match protocol {
+        ImageProtocol::Kitty | ImageProtocol::ITerm => {
+            // Both take the file as-is, but only PNG's are guaranteed to work everywhere
+            if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+                return Err(format!("Image {} isn't a PNG", path));
+            }
+            None
+        },
+        ImageProtocol::Sixel | ImageProtocol::HalfBlock => {
+            let image: png::Image = match png::decode(&data) {
+                Ok(r) => r,
+                Err(e) => return Err(format!("Unable to decode image {}: {}", path, e)),
+            };
+            let (cell_width, cell_height): (u32, u32) = match protocol {
+                ImageProtocol::HalfBlock => (1, 2),
+                _ => cell_size(),
+            };
+            Some(image.resize(config.ascii.image_width as u32 * cell_width, config.ascii.image_height as u32 * cell_height))
+        },
+    };
+
+    Ok(ImageLogo {
+        protocol,
+        data,
+        image,
+        width: config.ascii.image_width,
+        height: config.ascii.image_height,
+    })
+}
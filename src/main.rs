@@ -1,42 +1,28 @@
+use std::io::{stdout, IsTerminal};
 use std::time::Duration;
 use std::{cmp::max, env, process::exit, time::Instant};
 
 use formatter::CrabFetchColor;
-use module::{Module, ModuleError};
-use modules::battery::{self, BatteryInfo};
-use modules::cpu::{self, CPUInfo};
-use clap::{ArgAction, Parser};
+use module::{ModuleError, ModuleWarning, PermissionHint};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use modules::datetime::{self, DateTimeInfo};
-use modules::desktop::{self, DesktopInfo};
-use modules::displays::{self, DisplayInfo};
-use modules::editor::{self, EditorInfo};
-use modules::host::{self, HostInfo};
-use modules::initsys::{self, InitSystemInfo};
-use modules::locale::{self, LocaleInfo};
-use modules::memory::{self, MemoryInfo};
-use modules::gpu::{self, GPUInfo};
-use modules::mounts::{self, MountInfo};
-#[cfg(feature = "player")]
-use modules::player::{self, PlayerInfo};
-use modules::os::{self, OSInfo};
-use modules::packages::{self, PackagesInfo};
-use modules::processes::{self, ProcessesInfo};
-use modules::shell::{self, ShellInfo};
-use modules::swap::{self, SwapInfo};
-use modules::terminal::{self, TerminalInfo};
-use modules::uptime::{self, UptimeInfo};
-use modules::hostname::{self, HostnameInfo};
-use config_manager::Configuration;
+use modules::os::OSInfo;
+use config_manager::{Configuration, ModuleExtras};
 use package_managers::ManagerInfo;
 use syscalls::SyscallCache;
 
 use crate::ascii::get_ascii_line;
-use crate::modules::localip::{self, LocalIPInfo};
+use crate::image::ImageLogo;
+use crate::registry::{ModuleContext, ModuleRegistry};
 
 mod modules;
+mod registry;
 mod config_manager;
+mod background;
 mod ascii;
+mod banner;
+mod image;
+mod png;
 mod formatter;
 mod proccess_info;
 mod versions;
@@ -44,6 +30,42 @@ mod package_managers;
 mod module;
 mod util;
 mod syscalls;
+mod soc;
+mod network;
+
+// Exit codes, so scripts can tell what went wrong without scraping our output
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_UNSUPPORTED_OS: i32 = 1;
+const EXIT_CONFIG_ERROR: i32 = 2;
+const EXIT_MODULE_FAILURE: i32 = 3;
+const EXIT_COMMAND_FAILURE: i32 = 4;
+
+#[derive(Clone, PartialEq, ValueEnum)]
+enum OutputType {
+    /// The usual formatted output
+    Text,
+    /// The raw detected module values, as JSON
+    Json,
+}
+
+#[derive(Clone, PartialEq, ValueEnum)]
+enum ColorMode {
+    /// Colors when outputting to a terminal, unless NO_COLOR is set
+    Auto,
+    /// Always output colors
+    Always,
+    /// Never output colors
+    Never,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Browse and export the ASCII art CrabFetch knows about.
+    Ascii {
+        #[command(subcommand)]
+        command: ascii::AsciiCommand,
+    },
+}
 
 #[derive(Parser)]
 #[command(about, long_about = None)]
@@ -60,8 +82,19 @@ pub struct Args {
     /// Generates a default config file
     generate_config_file: bool,
 
-    #[arg(short, long)]
-    /// Overrides the distro ASCII to another distro.
+    #[arg(long)]
+    /// Checks your config file for errors, and for any options that conflict with each other or
+    /// won't do anything, then exits.
+    check_config: bool,
+
+    #[arg(long, num_args(0..), value_name("PATH[=VERSION]"))]
+    /// Adds the checksums of your installed terminals, shells and editor to your own checksum
+    /// database in the config dir, for use with use_version_checksums. Extra binaries can be
+    /// passed in too, optionally with their version if it can't be detected.
+    update_checksums: Option<Vec<String>>,
+
+    #[arg(short, long, visible_aliases(["distro", "logo"]))]
+    /// Overrides the distro ASCII (and OS color) to another distro.
     distro_override: Option<String>,
 
     #[arg(short, long, require_equals(true), default_missing_value("false"), default_value("false"), action=ArgAction::Set)]
@@ -73,10 +106,38 @@ pub struct Args {
     /// modules. E.g cpu,gpu,underline:16,title
     module_override: Option<String>,
 
-    #[arg(long)]
+    #[arg(long, visible_alias = "align-values")]
     /// Enables the inline values option.
     inline_values: bool,
 
+    #[arg(short, long)]
+    /// Only outputs the module data; no ASCII art, and no error messages. Combine with --strict to
+    /// still be able to tell if something failed.
+    quiet: bool,
+
+    #[arg(long)]
+    /// Shows any warnings modules ran into while detecting, even if errors are suppressed.
+    debug: bool,
+
+    #[arg(long)]
+    /// Exits with a non-zero exit code if any module fails to detect it's information.
+    strict: bool,
+
+    #[arg(long, value_enum, default_value_t = OutputType::Text)]
+    /// What format to output in. JSON skips any styling and outputs the raw values each module
+    /// detected, for use in scripts or status bars.
+    output: OutputType,
+
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+    /// When to output colors. Auto turns them off when stdout isn't a terminal (e.g when piping
+    /// into another program or a file), or when the NO_COLOR environment variable is set.
+    color: ColorMode,
+
+    #[arg(long)]
+    /// Forces colors and ASCII art to be output, even when stdout isn't a terminal. The same as
+    /// --color=always.
+    force_color: bool,
+
     #[arg(long)]
     /// Runs CrabFetch in a "benchmark" mode, showing the total times it takes between each stage
     /// and module detection times.
@@ -89,7 +150,10 @@ pub struct Args {
 
     #[arg(long, short)]
     /// Displays the version of CrabFetch, as well as the current features enabled in this build.
-    version: bool
+    version: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 // This is done here simply to make the main function not as indented of a mess, it's abstracted into here
@@ -118,103 +182,129 @@ fn print_bench_time(benchmarking: bool, benchmark_warn: Option<u128>, name: &str
     println!("[Benchmark] {}: {}", name, t_output);
 }
 
-// Macro for calling most module types
-#[macro_export]
-macro_rules! run_generic_module {
-    ($mod: ident, $type: ident, $run: ident, $known: expr, $config: expr, $err: expr, $out: expr, $($rargs:tt)*) => {
-        if $known.is_none() {
-            $known = Some($mod::$run($($rargs)*));
-        }
-        match $known.as_ref().unwrap() {
-            Ok(x) => $out.push(x.style(&$config)),
-            Err(e) => {
-                if $err {
-                    $out.push((String::new(), e.to_string()));
-                } else {
-                    $out.push($type::unknown_output(&$config));
-                }
-            },
-        }; 
-    };
+// Warnings go to stderr after everything else, so they never end up mixed into the output itself
+fn print_warnings(warnings: &[ModuleWarning], show: bool) {
+    if !show {
+        return;
+    }
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
 }
-#[macro_export]
-macro_rules! run_multiline_module {
-    ($mod: ident, $type: ident, $run: ident, $known: expr, $config: expr, $err: expr, $out: expr, $($rargs:tt)*) => {
-        if $known.is_none() {
-            $known = Some($mod::$run($($rargs)*));
-        }
-        match $known.as_ref().unwrap() {
-            Ok(x) => {
-                for y in x {
-                    $out.push(y.style(&$config));
-                }
-            },
-            Err(e) => {
-                if $err {
-                    $out.push((String::new(), e.to_string()));
-                } else {
-                    $out.push($type::unknown_output(&$config));
-                }
-            },
-        }; 
-    };
+
+// Anything that couldn't be read due to permissions, with what'd let us read it
+// Like warnings, this goes to stderr after everything else
+fn print_permission_hints(show: bool) {
+    let hints: Vec<PermissionHint> = module::take_permission_hints();
+    if !show || hints.is_empty() {
+        return;
+    }
+    eprintln!("Some info couldn't be read without more permissions;");
+    for hint in hints {
+        eprintln!("{}", hint);
+    }
+    eprintln!("(Set permission_hints = false in your config to hide this)");
 }
 
+// Applies the options any module can have to the lines it's just output
+fn apply_module_extras(output: &mut Vec<(String, String)>, start: usize, module_name: &str, extras: &ModuleExtras, config: &Configuration, warnings: &mut Vec<ModuleWarning>) {
+    if let Some(command) = &extras.post_command {
+        for line in output.iter_mut().skip(start) {
+            // Only the value itself gets piped, not the separator in front of it
+            let prefix: &str = if !line.0.is_empty() && line.1.starts_with(extras.separator.as_str()) { &extras.separator } else { "" };
+            match util::run_post_command(command, &line.1[prefix.len()..], &[], config.post_command_timeout) {
+                Ok(r) => line.1 = format!("{}{}", prefix, r),
+                Err(e) => warnings.push(ModuleWarning::new(module_name, e)),
+            }
+        }
+    }
+
+    if extras.max_length != 0 {
+        for line in output.iter_mut().skip(start) {
+            let prefix: &str = if !line.0.is_empty() && line.1.starts_with(extras.separator.as_str()) { &extras.separator } else { "" };
+            line.1 = format!("{}{}", prefix, formatter::truncate(&line.1[prefix.len()..], extras.max_length));
+        }
+    }
+
+    if extras.banner {
+        // Banners don't get a title, just the value blown up over a few lines
+        let lines: Vec<(String, String)> = output.drain(start..).collect();
+        for line in lines {
+            let value: &str = if !line.0.is_empty() { line.1.strip_prefix(extras.separator.as_str()).unwrap_or(&line.1) } else { &line.1 };
+            for banner_line in banner::render_banner(value) {
+                output.push((String::new(), banner_line));
+            }
+        }
+    }
 
-// Stores all the module's outputs as we know them
-// This is to prevent us doing additional work when we don't need to, when modules need shared data
-struct ModuleOutputs {
-    hostname: Option<Result<HostnameInfo, ModuleError>>,
-    cpu: Option<Result<CPUInfo, ModuleError>>,
-    gpu: Option<Result<Vec<GPUInfo>, ModuleError>>,
-    memory: Option<Result<MemoryInfo, ModuleError>>,
-    swap: Option<Result<SwapInfo, ModuleError>>,
-    mounts: Option<Result<Vec<MountInfo>, ModuleError>>,
-    host: Option<Result<HostInfo, ModuleError>>,
-    displays: Option<Result<Vec<DisplayInfo>, ModuleError>>,
-    packages: Option<PackagesInfo>,
-    desktop: Option<Result<DesktopInfo, ModuleError>>,
-    terminal: Option<Result<TerminalInfo, ModuleError>>,
-    shell: Option<Result<ShellInfo, ModuleError>>,
-    battery: Option<Result<Vec<BatteryInfo>, ModuleError>>,
-    uptime: Option<Result<UptimeInfo, ModuleError>>,
-    locale: Option<Result<LocaleInfo, ModuleError>>,
-    #[cfg(feature = "player")]
-    player: Option<Result<Vec<PlayerInfo>, ModuleError>>,
-    editor: Option<Result<EditorInfo, ModuleError>>,
-    os: Option<Result<OSInfo, ModuleError>>,
-    initsys: Option<Result<InitSystemInfo, ModuleError>>,
-    processes: Option<Result<ProcessesInfo, ModuleError>>,
-    datetime: Option<DateTimeInfo>,
-    localip: Option<Result<Vec<LocalIPInfo>, ModuleError>>,
+    if !extras.prefix.is_empty() || !extras.suffix.is_empty() {
+        let prefix: String = formatter::replace_color_placeholders(&extras.prefix, config);
+        let suffix: String = formatter::replace_color_placeholders(&extras.suffix, config);
+        for line in output.iter_mut().skip(start) {
+            // Put on the title if there is one, so inline values still line up
+            if line.0.is_empty() {
+                line.1 = format!("{}{}", prefix, line.1);
+            } else {
+                line.0 = format!("{}{}", prefix, line.0);
+            }
+            line.1.push_str(&suffix);
+        }
+    }
 }
-impl ModuleOutputs {
-    fn new() -> Self {
-        Self {
-            hostname: None,
-            cpu: None,
-            gpu: None,
-            memory: None,
-            swap: None,
-            mounts: None,
-            host: None,
-            displays: None,
-            packages: None,
-            desktop: None,
-            terminal: None,
-            shell: None,
-            battery: None,
-            uptime: None,
-            locale: None,
-            #[cfg(feature = "player")]
-            player: None,
-            editor: None,
-            os: None,
-            initsys: None,
-            processes: None,
-            datetime: None,
-            localip: None,
+
+// Puts the columns of output side by side, with each column starting at the given output index
+// The titles of the first column are left alone so inline values still line up, the rest get merged
+// into the value
+fn merge_columns(output: Vec<(String, String)>, breaks: &[usize], inline_values: bool, gap: u16) -> Vec<(String, String)> {
+    let mut columns: Vec<Vec<(String, String)>> = Vec::new();
+    for (i, start) in breaks.iter().enumerate() {
+        let end: usize = breaks.get(i + 1).copied().unwrap_or(output.len());
+        columns.push(output[*start..end].to_vec());
+    }
+
+    let title_len = |x: &(String, String)| strip_ansi_escapes::strip_str(&x.0).chars().count();
+    let mut merged: Vec<(String, String)> = columns.remove(0);
+    for column in columns {
+        // Inline values pad every title out to the longest one
+        let left_max_title: usize = merged.iter().map(title_len).max().unwrap_or(0);
+        let right_max_title: usize = column.iter().map(title_len).max().unwrap_or(0);
+        let width = |x: &(String, String)| {
+            let title: usize = if inline_values && !x.0.is_empty() { left_max_title } else { title_len(x) };
+            title + strip_ansi_escapes::strip_str(&x.1).chars().count()
+        };
+        let left_width: usize = merged.iter().map(width).max().unwrap_or(0);
+
+        let mut new_merged: Vec<(String, String)> = Vec::new();
+        for i in 0..max(merged.len(), column.len()) {
+            let left: (String, String) = merged.get(i).cloned().unwrap_or_default();
+            let right: &(String, String) = match column.get(i) {
+                Some(r) => r,
+                None => {
+                    new_merged.push(left);
+                    continue;
+                },
+            };
+
+            let padding: usize = left_width - width(&left) + gap as usize;
+            let right_padding: usize = if inline_values && !right.0.is_empty() { right_max_title - title_len(right) } else { 0 };
+            let value: String = format!("{}{}{}{}{}", left.1, " ".repeat(padding), right.0, " ".repeat(right_padding), right.1);
+            new_merged.push((left.0, value));
         }
+        merged = new_merged;
+    }
+
+    merged
+}
+
+// What to do with the side ASCII when the terminal is too narrow for it, going by layout.narrow_ascii
+// The image logo has to go with it, as it's drawn regardless of ascii.display
+fn narrow_ascii_fallback(config: &mut Configuration, image: &mut Option<ImageLogo>) {
+    match config.layout.narrow_ascii.as_str() {
+        "hide" => {
+            config.ascii.display = false;
+            *image = None;
+        },
+        _ => config.ascii.side = "top".to_string(),
     }
 }
 
@@ -223,8 +313,8 @@ fn main() {
 
     // Are we defo in Linux?
     if env::consts::OS != "linux" && env::consts::OS != "android" {
-        println!("CrabFetch only supports Linux! If you want to go through and add support for your own OS, make a pull request :)");
-        exit(-1);
+        eprintln!("CrabFetch only supports Linux! If you want to go through and add support for your own OS, make a pull request :)");
+        exit(EXIT_UNSUPPORTED_OS);
     }
 
     // 
@@ -268,31 +358,109 @@ fn main() {
         #[cfg(not(feature = "rpm_packages"))]
         println!(" - rpm_packages");
 
-        exit(0);
+        exit(EXIT_SUCCESS);
     }
     if args.generate_config_file {
         let bench: Option<Instant> = benchmark_point(args.benchmark); 
         config_manager::generate_config_file(args.config.clone());
         print_bench_time(args.benchmark, args.benchmark_warn, "Generating Config File", bench);
-        exit(0);
+        exit(EXIT_SUCCESS);
+    }
+    if let Some(paths) = &args.update_checksums {
+        let mut package_managers: ManagerInfo = ManagerInfo::new();
+        package_managers.probe_and_cache();
+        versions::update_checksums(paths, &package_managers);
+        exit(EXIT_SUCCESS);
     }
     let bench: Option<Instant> = benchmark_point(args.benchmark); 
     let mut config: Configuration = match config_manager::parse(&args.config, &args.module_override, &args.ignore_config_file) {
         Ok(r) => r,
         Err(e) => {
-            println!("{}", e);
-            exit(-1);
+            if !args.quiet {
+                eprintln!("{}", e);
+            }
+            exit(EXIT_CONFIG_ERROR);
         },
     };
     print_bench_time(args.benchmark, args.benchmark_warn, "Parsing Config", bench);
 
+    let config_warnings: Vec<String> = config_manager::lint(&config);
+    if args.check_config {
+        for warning in &config_warnings {
+            println!("Config warning: {}", warning);
+        }
+        if config_warnings.is_empty() {
+            println!("No problems found.");
+        }
+        exit(EXIT_SUCCESS);
+    }
+    if args.debug {
+        for warning in &config_warnings {
+            eprintln!("Config warning: {}", warning);
+        }
+    }
+
+    // The flag wins over the config
+    let distro_override: Option<String> = match &args.distro_override {
+        Some(r) => Some(r.to_string()),
+        None if !config.ascii.override_distro.is_empty() => Some(config.ascii.override_distro.to_string()),
+        None => None,
+    };
+
+    if let Some(Command::Ascii { command }) = &args.command {
+        let detected_distro: Option<String> = distro_override.clone().or_else(|| {
+            modules::os::get_os(&config, &mut SyscallCache::new()).ok()
+                .map(|x| x.distro_id)
+                .filter(|x| x != "Unknown")
+        });
+        if let Err(e) = ascii::run_command(command, &config, detected_distro) {
+            eprintln!("{}", e);
+            exit(EXIT_COMMAND_FAILURE);
+        }
+        exit(EXIT_SUCCESS);
+    }
+
+    // If we're being piped into something, drop to plain output so we don't spew escape codes and
+    // ASCII art into someone's grep
+    let color_mode: ColorMode = if args.force_color { ColorMode::Always } else { args.color.clone() };
+    let is_terminal: bool = stdout().is_terminal();
+    if !is_terminal {
+        // Rust ignores SIGPIPE by default, meaning `crabfetch | head` panics when head closes early
+        unsafe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+        if color_mode != ColorMode::Always {
+            config.ascii.display = false;
+        }
+    }
+    let use_color: bool = match color_mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        // https://no-color.org
+        ColorMode::Auto => is_terminal && env::var("NO_COLOR").map_or(true, |x| x.is_empty()),
+    };
+    colored::control::set_override(use_color);
+    if !use_color {
+        // Image logos are nothing but escape codes, so the plain ASCII is used instead
+        config.ascii.image_path.clear();
+    }
+
+    // JSON wants everything we can find, not just what the formats ask for
+    if args.output == OutputType::Json {
+        config.detect_all_info = true;
+    }
+
     // if config isn't supprsesing errors, make it go down to args
-    let log_errors: bool = { if !config.suppress_errors { !args.suppress_errors } else { !config.suppress_errors } };
+    let log_errors: bool = { if !config.suppress_errors { !args.suppress_errors } else { !config.suppress_errors } } && !args.quiet;
+    if args.quiet {
+        config.ascii.display = false;
+    }
     // Inline values
-    let inline_values: bool = config.inline_values || args.inline_values;
+    let inline_values: bool = config.inline_values || config.align_values || args.inline_values;
 
-    // Define our module outputs, and figure out the max title length
-    let mut known_outputs: ModuleOutputs = ModuleOutputs::new();
+    // Set up every module we know how to run, and somewhere to put any warnings they have
+    let mut registry: ModuleRegistry = ModuleRegistry::builtin();
+    let mut warnings: Vec<ModuleWarning> = Vec::new();
     print_bench_time(args.benchmark, args.benchmark_warn, "Pre-Process", bench);
 
     // Pre-Process any package manager info we may need
@@ -306,18 +474,23 @@ fn main() {
 
     // Set the title color if we're usign os colors
     if config.use_os_color {
-        let id: &str = if let Some(ref x) = args.distro_override {
+        let id: &str = if let Some(ref x) = distro_override {
             x
         } else {
-            if known_outputs.os.is_none() {
-                let os_bench: Option<Instant> = benchmark_point(args.benchmark); 
-                known_outputs.os = Some(os::get_os(&config, &mut syscall_cache));
-                print_bench_time(args.benchmark, args.benchmark_warn, "OS (for OS Color)", os_bench);
-            }
-            &known_outputs.os.as_ref().unwrap().as_ref().unwrap().distro_id
+            let os_bench: Option<Instant> = benchmark_point(args.benchmark); 
+            let mut context: ModuleContext = ModuleContext {
+                config: &config,
+                package_managers: &package_managers,
+                syscall_cache: &mut syscall_cache,
+                warnings: &mut warnings,
+                argument: "",
+            };
+            let os: &Result<OSInfo, ModuleError> = registry.detect::<OSInfo>("os", &mut context).unwrap();
+            print_bench_time(args.benchmark, args.benchmark_warn, "OS (for OS Color)", os_bench);
+            &os.as_ref().unwrap().distro_id
         };
 
-        let c: CrabFetchColor = formatter::find_os_color(&id);
+        let c: CrabFetchColor = formatter::find_os_color(id);
         config.title_color = c.clone();
         if config.ascii.display {
             config.ascii.colors = vec![c];
@@ -330,11 +503,29 @@ fn main() {
     let detect_bench: Option<Instant> = benchmark_point(args.benchmark); 
     let mut output: Vec<(String, String)> = Vec::new();
     let mut cur_segment_length: usize = 0;
-    for module in &config.modules {
+    // Which module each column starts at, and then where it starts in the output
+    let column_starts: Vec<usize> = config.layout.columns.iter()
+        .scan(0, |total, x| { let start: usize = *total; *total += x.len(); Some(start) })
+        .collect();
+    let mut column_breaks: Vec<usize> = Vec::new();
+    for (index, module) in config.modules.iter().enumerate() {
+        if column_starts.contains(&index) {
+            column_breaks.push(output.len());
+        }
         let module_parse_bench: Option<Instant> = benchmark_point(args.benchmark); 
-        let module_split: Vec<&str> = module.split(':').collect();
+        let module_split: Vec<&str> = module.splitn(2, ':').collect();
         let module_name: &str = module_split[0];
+        let output_start: usize = output.len();
         match module_name {
+            "banner" => {
+                let bench: Option<Instant> = benchmark_point(args.benchmark); 
+                let text: String = formatter::replace_text_placeholders(module_split.get(1).unwrap_or(&""), &config);
+                let text: String = formatter::replace_color_placeholders(&text, &config);
+                for line in banner::render_banner(&text) {
+                    output.push((String::new(), line));
+                }
+                print_bench_time(args.benchmark, args.benchmark_warn, "Banner Module", bench);
+            },
             "space" => {
                 let bench: Option<Instant> = benchmark_point(args.benchmark); 
                 output.push((String::new(), String::new()));
@@ -350,213 +541,27 @@ fn main() {
                 let bench: Option<Instant> = benchmark_point(args.benchmark); 
                 let segment_name: &str = module_split[1];  
                 let segment_string: String = config.segment_top.replace("{name}", segment_name);
-                output.push((String::new(), formatter::replace_color_placeholders(&segment_string, &config)));
+                output.push((String::new(), formatter::replace_color_placeholders(&formatter::replace_text_placeholders(&segment_string, &config), &config)));
                 cur_segment_length = segment_name.len();
                 print_bench_time(args.benchmark, args.benchmark_warn, "Segment Start", bench);
             },
             "end_segment" => {
                 let bench: Option<Instant> = benchmark_point(args.benchmark); 
 
-                let index: usize = config.segment_bottom.find("{name_sized_gap:").unwrap();
-                let split: &Vec<char> = &config.segment_bottom[index+16..].chars().collect::<Vec<char>>();
-                let char: &char = split.first().unwrap();
+                // Either {name_sized_gap:X} to fill it with X, or just {name_sized_gap} to use dashes
+                let segment_string: String = match config.segment_bottom.find("{name_sized_gap:") {
+                    Some(index) => {
+                        let split: &Vec<char> = &config.segment_bottom[index+16..].chars().collect::<Vec<char>>();
+                        let char: &char = split.first().unwrap();
 
-                let target = format!("{{name_sized_gap:{}}}", char);
-                let segment_string: String = config.segment_bottom.replace(&target, &char.to_string().repeat(cur_segment_length + 2));
-                output.push((String::new(), formatter::replace_color_placeholders(&segment_string, &config)));
-                print_bench_time(args.benchmark, args.benchmark_warn, "Segment End", bench);
-            },
-            "hostname" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(hostname, HostnameInfo, get_hostname, known_outputs.hostname, config, log_errors, output, &config, &mut syscall_cache);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Hostname Module", bench);
-            },
-            "cpu" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(cpu, CPUInfo, get_cpu, known_outputs.cpu, config, log_errors, output, &config);
-                print_bench_time(args.benchmark, args.benchmark_warn, "CPU Module", bench);
-            },
-            "gpu" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.gpu.is_none() {
-                    known_outputs.gpu = Some(gpu::get_gpus(&config));
-                }
-                match known_outputs.gpu.as_ref().unwrap() {
-                    Ok(gpus) => {
-                        let mut index: u8 = 1;
-                        for gpu in gpus {
-                            let mut gpu = gpu.clone();
-                            gpu.set_index(index);
-                            output.push(gpu.style(&config));
-                            index += 1;
-                        }
-                    },
-                    Err(e) => {
-                        if log_errors {
-                            output.push((String::new(), e.to_string()));
-                        } else {
-                            output.push(GPUInfo::unknown_output(&config));
-                        }
+                        let target = format!("{{name_sized_gap:{}}}", char);
+                        config.segment_bottom.replace(&target, &char.to_string().repeat(cur_segment_length + 2))
                     },
-                }; 
-                print_bench_time(args.benchmark, args.benchmark_warn, "GPU Module", bench);
-            },
-            "memory" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(memory, MemoryInfo, get_memory, known_outputs.memory, config, log_errors, output, );
-                print_bench_time(args.benchmark, args.benchmark_warn, "Memory Module", bench);
-            },
-            "swap" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(swap, SwapInfo, get_swap, known_outputs.swap, config, log_errors, output, &mut syscall_cache);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Swap Module", bench);
-            },
-            "mounts" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.mounts.is_none() {
-                    known_outputs.mounts = Some(mounts::get_mounted_drives(&config));
-                }
-                match known_outputs.mounts.as_ref().unwrap() {
-                    Ok(mounts) => {
-                        for mount in mounts {
-                            if mount.is_ignored(&config) {
-                                continue;
-                            }
-                            output.push(mount.style(&config))
-                        }
-                    },
-                    Err(e) => {
-                        if log_errors {
-                            output.push((String::new(), e.to_string()));
-                        } else {
-                            output.push(MountInfo::unknown_output(&config));
-                        }
-                    },
-                }; 
-                print_bench_time(args.benchmark, args.benchmark_warn, "Mounts Module", bench);
-            },
-            "host" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.host.is_none() {
-                    known_outputs.host = Some(host::get_host(&config));
-                }
-                match known_outputs.host.as_ref().unwrap() {
-                    Ok(host) => {
-                        output.push(host.style(&config));
-                        if config.host.newline_chassis {
-                            output.push(host.style_chassis(&config));
-                        }
-                    },
-                    Err(e) => {
-                        if log_errors {
-                            output.push((String::new(), e.to_string()));
-                        } else {
-                            output.push(HostInfo::unknown_output(&config));
-                        }
-                    },
-                }; 
-                print_bench_time(args.benchmark, args.benchmark_warn, "Host Module", bench);
-            },
-            "displays" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_multiline_module!(displays, DisplayInfo, get_displays, known_outputs.displays, config, log_errors, output, &config);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Displays Module", bench);
-            },
-            "os" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.os.is_none() {
-                    known_outputs.os = Some(os::get_os(&config, &mut syscall_cache));
-                }
-                match known_outputs.os.as_ref().unwrap() {
-                    Ok(os) => {
-                        output.push(os.style(&config));
-                        if config.os.newline_kernel {
-                            output.push(os.style_kernel(&config));
-                        }
-                    },
-                    Err(e) => {
-                        if log_errors {
-                            output.push((String::new(), e.to_string()));
-                        } else {
-                            output.push(OSInfo::unknown_output(&config));
-                        }
-                    },
-                }; 
-                print_bench_time(args.benchmark, args.benchmark_warn, "OS Module", bench);
-            },
-            "packages" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.packages.is_none() {
-                    known_outputs.packages = Some(packages::get_packages(&package_managers));
-                }
-                output.push(known_outputs.packages.as_ref().unwrap().style(&config));
-                print_bench_time(args.benchmark, args.benchmark_warn, "Packages Module", bench);
-            },
-            "desktop" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(desktop, DesktopInfo, get_desktop, known_outputs.desktop, config, log_errors, output, &config);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Desktop Module", bench);
-            },
-            "terminal" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(terminal, TerminalInfo, get_terminal, known_outputs.terminal, config, log_errors, output, &config, &package_managers);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Terminal Module", bench);
-            },
-            "shell" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(shell, ShellInfo, get_shell, known_outputs.shell, config, log_errors, output, &config, &package_managers);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Shell Module", bench);
-            },
-            "battery" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_multiline_module!(battery, BatteryInfo, get_batteries, known_outputs.battery, config, log_errors, output, );
-                print_bench_time(args.benchmark, args.benchmark_warn, "Battery Module", bench);
-            },
-            "uptime" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(uptime, UptimeInfo, get_uptime, known_outputs.uptime, config, log_errors, output, &mut syscall_cache);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Uptime Module", bench);
-            },
-            "locale" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(locale, LocaleInfo, get_locale, known_outputs.locale, config, log_errors, output, );
-                print_bench_time(args.benchmark, args.benchmark_warn, "Locale Module", bench);
-            },
-            #[cfg(feature = "player")]
-            "player" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_multiline_module!(player, PlayerInfo, get_players, known_outputs.player, config, log_errors, output, &config);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Player Module", bench);
-            },
-            "editor" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(editor, EditorInfo, get_editor, known_outputs.editor, config, log_errors, output, &config, &package_managers);
-                print_bench_time(args.benchmark, args.benchmark_warn, "Editor Module", bench);
-            },
-            "initsys" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(initsys, InitSystemInfo, get_init_system, known_outputs.initsys, config, log_errors, output, &config, &package_managers);
-                print_bench_time(args.benchmark, args.benchmark_warn, "InitSys Module", bench);
-            },
-            "processes" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_generic_module!(processes, ProcessesInfo, get_process_count, known_outputs.processes, config, log_errors, output, );
-                print_bench_time(args.benchmark, args.benchmark_warn, "Processes Module", bench);
-            },
-            "datetime" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                if known_outputs.datetime.is_none() {
-                    known_outputs.datetime = Some(datetime::get_date_time());
-                }
-                output.push(known_outputs.datetime.as_ref().unwrap().style(&config));
-                print_bench_time(args.benchmark, args.benchmark_warn, "Datetime Module", bench);
+                    None => config.segment_bottom.replace("{name_sized_gap}", &"-".repeat(cur_segment_length)),
+                };
+                output.push((String::new(), formatter::replace_color_placeholders(&formatter::replace_text_placeholders(&segment_string, &config), &config)));
+                print_bench_time(args.benchmark, args.benchmark_warn, "Segment End", bench);
             },
-            "localip" => {
-                let bench: Option<Instant> = benchmark_point(args.benchmark); 
-                run_multiline_module!(localip, LocalIPInfo, get_local_ips, known_outputs.localip, config, log_errors, output, );
-                print_bench_time(args.benchmark, args.benchmark_warn, "Local IP Module", bench);
-            }
-
             // i hate what's below as well, don't worry
             "colors" => {
                 let bench: Option<Instant> = benchmark_point(args.benchmark); 
@@ -642,50 +647,124 @@ fn main() {
                 output.push((String::new(), str));
                 print_bench_time(args.benchmark, args.benchmark_warn, "Bright Colors Module", bench);
             }
+            _ if registry.bench_name(module_name).is_some() => {
+                let bench: Option<Instant> = benchmark_point(args.benchmark); 
+                let mut context: ModuleContext = ModuleContext {
+                    config: &config,
+                    package_managers: &package_managers,
+                    syscall_cache: &mut syscall_cache,
+                    warnings: &mut warnings,
+                    argument: module_split.get(1).unwrap_or(&""),
+                };
+                output.extend(registry.run(module_name, &mut context, log_errors).unwrap());
+                print_bench_time(args.benchmark, args.benchmark_warn, registry.bench_name(module_name).unwrap(), bench);
+            },
             _ => {
                 let bench: Option<Instant> = benchmark_point(args.benchmark); 
                 if config.unknown_as_text {
-                    output.push((String::new(), formatter::replace_color_placeholders(module_name, &config)));
-                } else {
+                    output.push((String::new(), formatter::replace_color_placeholders(&formatter::replace_text_placeholders(module, &config), &config)));
+                } else if !args.quiet {
                     output.push((String::new(), format!("Unknown module: {}", module_name)));
                 }
                 print_bench_time(args.benchmark, args.benchmark_warn, "Unknown Module / Custom Text", bench);
             }
         }
+        if let Some(extras) = config.module_extras.get(module_name) {
+            apply_module_extras(&mut output, output_start, module_name, extras, &config, &mut warnings);
+        }
         print_bench_time(args.benchmark, args.benchmark_warn, "  Entire Module Parse/Detection", module_parse_bench);
     }
     print_bench_time(args.benchmark, args.benchmark_warn, "Entire detection step", detect_bench);
 
+    if args.output == OutputType::Json {
+        println!("{}", serde_json::to_string_pretty(&registry.to_json(&config.modules)).unwrap());
+        print_warnings(&warnings, log_errors || args.debug);
+        print_permission_hints(config.permission_hints && !args.quiet);
+        if args.strict && registry.has_failures() {
+            exit(EXIT_MODULE_FAILURE);
+        }
+        exit(EXIT_SUCCESS);
+    }
+
 
     // 
     //  Display
     //
+    // Colors that didn't come through colored (e.g from a post_command, or a custom ASCII) still
+    // need to go
+    if !use_color {
+        output = output.iter().map(|x| (strip_ansi_escapes::strip_str(&x.0), strip_ansi_escapes::strip_str(&x.1))).collect();
+    }
     let ascii_bench: Option<Instant> = benchmark_point(args.benchmark); 
     let mut ascii_split: Vec<&str> = Vec::new();
     let mut ascii_length: usize = 0;
     let mut ascii_target_length: u16 = 0;
     // :(
     let fuck_off_borrow_checker: String;
-    if config.ascii.display {
-        if known_outputs.os.is_none() {
-            let os_bench: Option<Instant> = benchmark_point(args.benchmark); 
-            known_outputs.os = Some(os::get_os(&config, &mut syscall_cache));
-            print_bench_time(args.benchmark, args.benchmark_warn, "OS (for ASCII)", os_bench);
+    let image_lines: Vec<String>;
+    let mut image: Option<ImageLogo> = None;
+    if config.ascii.display && !config.ascii.image_path.is_empty() {
+        // Falls back to the ASCII if the image can't be loaded
+        match image::get_image(&config, image::detect_protocol()) {
+            Ok(r) => image = Some(r),
+            Err(e) => warnings.push(ModuleWarning::new("ASCII", e)),
         }
-        if known_outputs.os.as_ref().unwrap().is_ok() {
+    }
+    if let Some(image) = &image {
+        // The image itself is drawn once up front, the ASCII is then just blank space around it
+        // (or the image itself, if it's made of text)
+        image_lines = image.text_lines();
+        ascii_split = image_lines.iter().map(|x| x.as_str()).collect();
+        ascii_length = image.height as usize;
+        ascii_target_length = image.width + config.ascii.margin;
+    } else if config.ascii.display {
+        let os_bench: Option<Instant> = benchmark_point(args.benchmark); 
+        let mut context: ModuleContext = ModuleContext {
+            config: &config,
+            package_managers: &package_managers,
+            syscall_cache: &mut syscall_cache,
+            warnings: &mut warnings,
+            argument: "",
+        };
+        let os: &Result<OSInfo, ModuleError> = registry.detect::<OSInfo>("os", &mut context).unwrap();
+        print_bench_time(args.benchmark, args.benchmark_warn, "OS (for ASCII)", os_bench);
+        if let Ok(os) = os {
             // Calculate the ASCII stuff while we're here
-            let ascii: (String, u16) = if args.distro_override.is_some() {
-                ascii::get_ascii(&args.distro_override.clone().unwrap())
+            let ascii: (String, u16) = if let Some(ref x) = distro_override {
+                ascii::get_ascii(x, &config)
             } else {
-                ascii::get_ascii(&known_outputs.os.as_ref().unwrap().as_ref().unwrap().distro_id)
+                ascii::get_ascii(&os.distro_id, &config)
             };
-            fuck_off_borrow_checker = ascii.0;
-            ascii_split = fuck_off_borrow_checker.split('\n').filter(|x| x.trim() != "").collect();
+            fuck_off_borrow_checker = if use_color { ascii.0 } else { strip_ansi_escapes::strip_str(&ascii.0) };
+            ascii_split = fuck_off_borrow_checker.split('\n').filter(|x| strip_ansi_escapes::strip_str(x).trim() != "").collect();
             ascii_length = ascii_split.len();
             ascii_target_length = ascii.1 + config.ascii.margin;
         }
     }
 
+    if column_breaks.len() > 1 {
+        output = merge_columns(output, &column_breaks, inline_values, config.layout.column_gap);
+    }
+
+    // Move the ASCII out of the way if it'd wrap alongside the modules
+    if config.ascii.display && (config.ascii.side == "left" || config.ascii.side == "right") {
+        if let Some(columns) = util::terminal_columns() {
+            let needed: usize = if config.layout.min_width_for_side_ascii != 0 {
+                config.layout.min_width_for_side_ascii as usize
+            } else {
+                let max_title: usize = output.iter().map(|x| strip_ansi_escapes::strip_str(&x.0).chars().count()).max().unwrap_or(0);
+                let max_line: usize = output.iter().map(|x| {
+                    let title: usize = if inline_values && !x.0.is_empty() { max_title } else { strip_ansi_escapes::strip_str(&x.0).chars().count() };
+                    title + strip_ansi_escapes::strip_str(&x.1).chars().count()
+                }).max().unwrap_or(0);
+                ascii_target_length as usize + max_line
+            };
+            if (columns as usize) < needed {
+                narrow_ascii_fallback(&mut config, &mut image);
+            }
+        }
+    }
+
     // get the maximum module line length for both right side ascii + inline values
     let mut max_title_len: usize = 0;
     let mut max_total_len: usize = 0;
@@ -705,6 +784,13 @@ fn main() {
     // the actual outputs
     let bench: Option<Instant> = benchmark_point(args.benchmark); 
     let mut current_line: usize = 0;
+    if let Some(image) = &image {
+        match config.ascii.side.as_str() {
+            "right" => print!("{}", image.draw(max_total_len as u16 + config.ascii.margin)),
+            "bottom" => {}, // done below
+            _ => print!("{}", image.draw(0)),
+        }
+    }
     // top ascii
     if config.ascii.display && config.ascii.side == "top" {
         #[allow(clippy::mut_range_bound)]
@@ -717,6 +803,7 @@ fn main() {
     }
 
     // the modules + left/right ascii alongside them
+    let output_lines: usize = output.len();
     for out in output {
         // left ascii
         if config.ascii.display && config.ascii.side == "left" {
@@ -747,6 +834,9 @@ fn main() {
         // Margin
         print!("{}", "\n".repeat(config.ascii.margin as usize));
 
+        if let Some(image) = &image {
+            print!("{}", image.draw(0));
+        }
         for x in 0..ascii_length {
             println!("{}", get_ascii_line(x, &ascii_split, &ascii_target_length, &config));
         }
@@ -754,18 +844,63 @@ fn main() {
 
     // remaning ascii from left/right in case we're using less modules than required lines
     if current_line < ascii_length && config.ascii.display && (config.ascii.side == "left" || config.ascii.side == "right") {
-        let mut ascii_line: usize = current_line;
-        for _ in current_line..ascii_length {
+        for ascii_line in current_line..ascii_length {
             if config.ascii.side == "right" {
                 print!("{}", " ".repeat(max_total_len + config.ascii.margin as usize));
             }
             print!("{}", get_ascii_line(ascii_line, &ascii_split, &ascii_target_length, &config));
-            ascii_line += 1;
             println!();
         }
     }
     print_bench_time(args.benchmark, args.benchmark_warn, "Module + ASCII Output", bench);
 
+    if !config.after_render_command.is_empty() {
+        let stats: [(&str, String); 5] = [
+            ("CRABFETCH_RENDER_MS", full_runtime_bench.map(|x| x.elapsed().as_millis()).unwrap_or(0).to_string()),
+            ("CRABFETCH_MODULES", config.modules.len().to_string()),
+            ("CRABFETCH_FAILED_MODULES", registry.failure_count().to_string()),
+            ("CRABFETCH_LINES", output_lines.to_string()),
+            ("CRABFETCH_WARNINGS", warnings.len().to_string()),
+        ];
+        if let Err(e) = util::run_post_command(&config.after_render_command, "", &stats, config.post_command_timeout) {
+            warnings.push(ModuleWarning::new("After Render", e));
+        }
+    }
+
     print_bench_time(args.benchmark, args.benchmark_warn, "Full Runtime of CrabFetch", full_runtime_bench);
+    print_warnings(&warnings, log_errors || args.debug);
+    print_permission_hints(config.permission_hints && !args.quiet);
+
+    if args.strict && registry.has_failures() {
+        exit(EXIT_MODULE_FAILURE);
+    }
 }
 
+
+mod tests {
+    // Checks the image logo is dropped along with the ASCII when it's hidden, and kept when it moves
+    #[test]
+    fn narrow_ascii_fallback() {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("crabfetch-narrow-{}.png", std::process::id()));
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\n").unwrap();
+        let mut config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        config.ascii.image_path = path.display().to_string();
+        config.ascii.side = "left".to_string();
+
+        config.layout.narrow_ascii = "hide".to_string();
+        let mut image: Option<crate::image::ImageLogo> = crate::image::get_image(&config, crate::image::ImageProtocol::Kitty).ok();
+        assert!(image.is_some());
+        super::narrow_ascii_fallback(&mut config, &mut image);
+        assert!(!config.ascii.display);
+        assert!(image.is_none());
+
+        config.ascii.display = true;
+        config.layout.narrow_ascii = "top".to_string();
+        let mut image: Option<crate::image::ImageLogo> = crate::image::get_image(&config, crate::image::ImageProtocol::Kitty).ok();
+        super::narrow_ascii_fallback(&mut config, &mut image);
+        std::fs::remove_file(&path).unwrap();
+        assert!(config.ascii.display);
+        assert_eq!(config.ascii.side, "top");
+        assert!(image.is_some());
+    }
+}
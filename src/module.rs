@@ -1,32 +1,52 @@
-use std::fmt::{Debug, Display};
+use std::{fmt::{Debug, Display}, sync::Mutex};
 
-use colored::{ColoredString, Colorize};
-
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}};
 
 pub trait Module {
     fn new() -> Self;
     fn style(&self, config: &Configuration) -> (String, String);
     fn unknown_output(config: &Configuration) -> (String, String);
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String;
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String;
     fn gen_info_flags(format: &str) -> u32;
 
+    // The placeholders in `text` this module doesn't have, so they can be warned about instead of
+    // just being left in the output
+    // Each one's rendered by itself against a blank module, and it's unknown if it comes back untouched
+    fn unknown_placeholders(text: &FormatTemplate, config: &Configuration) -> Vec<String> where Self: Sized {
+        let blank: Self = Self::new();
+        text.placeholder_names().into_iter().filter(|x| {
+            let placeholder: String = format!("{{{}}}", x);
+            !formatter::is_style_placeholder(x) && blank.replace_placeholders(&FormatTemplate::parse(&placeholder), config) == placeholder
+        }).collect()
+    }
+
+    // Use this over gen_info_flags directly, as this respects if we need to detect everything
+    // regardless of the format, e.g for JSON output
+    fn get_info_flags(format: &str, config: &Configuration) -> u32 {
+        if config.detect_all_info {
+            return u32::MAX;
+        }
+        Self::gen_info_flags(&FormatTemplate::without_places(format))
+    }
+
     // TODO: Move these params into some kinda struct or some shit idk, cus it just sucks
-    fn default_style(_: &Configuration, title: &str, title_color: &CrabFetchColor, title_bold: bool, title_italic: bool, separator: &str, value: &str) -> (String, String) {
+    #[allow(clippy::too_many_arguments)]
+    fn default_style(config: &Configuration, title: &str, title_color: &CrabFetchColor, title_bold: bool, title_italic: bool, hide_title: bool, separator: &str, value: &str) -> (String, String) {
         let mut title_final: String = String::new();
         let mut value_final: String = String::new();
 
         // Title
-        if !title.trim().is_empty() {
-            let mut title: ColoredString = title_color.color_string(title);
+        // Goes through the placeholders as well, so titles can have their own colors or gradients
+        if !hide_title && !title.trim().is_empty() {
+            let mut styles: Vec<&'static str> = Vec::new();
             if title_bold {
-                title = title.bold();
+                styles.push("bold");
             }
             if title_italic {
-                title = title.italic();
+                styles.push("italic");
             }
 
-            title_final.push_str(&title.to_string());
+            title_final.push_str(&formatter::style_placeholders(title, config, Some(title_color.clone()), styles));
             value_final.push_str(separator)
         }
 
@@ -62,3 +82,62 @@ impl Debug for ModuleError {
         write!(f, "Module {} failed: {}", self.module_name, self.message)
     }
 }
+
+// A warning from a module; something went wrong, but not enough to fail the whole module
+// e.g a single display's EDID being unreadable
+// These get collected up and shown after the output, so they don't end up mid-render
+pub struct ModuleWarning {
+    module_name: String,
+    message: String
+}
+impl ModuleWarning {
+    pub fn new(module: &str, message: String) -> ModuleWarning {
+        ModuleWarning {
+            module_name: module.to_string(),
+            message
+        }
+    }
+}
+impl Display for ModuleWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Module {} warning: {}", self.module_name, self.message)
+    }
+}
+
+// A hint that something couldn't be read due to permissions, and what would let us read it
+// These are collected up and shown once at the end, rather than one warning per failed read
+// Kept globally behind a mutex so any module can add to it, no matter where it's run from
+pub struct PermissionHint {
+    module_name: String,
+    placeholders: String,
+    needs: String,
+}
+impl Display for PermissionHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "  {} {} - needs {}", self.module_name, self.placeholders, self.needs)
+    }
+}
+
+static PERMISSION_HINTS: Mutex<Vec<PermissionHint>> = Mutex::new(Vec::new());
+
+// `placeholders` being what would become available, e.g "{path}, {version}", and `needs` being
+// what's required for it, e.g "root" or "to be in the video group"
+pub fn hint_permission(module: &str, placeholders: &str, needs: &str) {
+    if let Ok(mut hints) = PERMISSION_HINTS.lock() {
+        // Some modules hit the same thing multiple times, e.g multi-line ones
+        if hints.iter().any(|x| x.module_name == module && x.placeholders == placeholders) {
+            return;
+        }
+        hints.push(PermissionHint {
+            module_name: module.to_string(),
+            placeholders: placeholders.to_string(),
+            needs: needs.to_string(),
+        });
+    }
+}
+pub fn take_permission_hints() -> Vec<PermissionHint> {
+    match PERMISSION_HINTS.lock() {
+        Ok(mut r) => r.drain(..).collect(),
+        Err(_) => Vec::new(),
+    }
+}
@@ -0,0 +1,198 @@
+use std::{fs::{self, ReadDir}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util, ModuleError};
+
+#[derive(Serialize)]
+pub struct AudioDeviceInfo {
+    index: u8,
+    id: String,
+    name: String,
+    driver: String,
+    // Only known while something's playing through it
+    sample_rate: Option<u32>,
+    sample_format: String,
+    channels: Option<u8>,
+}
+#[derive(Deserialize)]
+pub struct AudioDevicesConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+    pub ignore: Vec<String>,
+}
+impl Module for AudioDeviceInfo {
+    fn new() -> AudioDeviceInfo {
+        AudioDeviceInfo {
+            index: 0,
+            id: "Unknown".to_string(),
+            name: "Unknown".to_string(),
+            driver: "Unknown".to_string(),
+            sample_rate: None,
+            sample_format: String::new(),
+            channels: None,
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.audiodevices.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.audiodevices.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.audiodevices.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.audiodevices.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.audiodevices.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.audiodevices.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.audiodevices.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.audiodevices.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.audiodevices.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.audiodevices.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.audiodevices.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.audiodevices.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.audiodevices.title.render_values(&[
+            ("index", "Unknown"),
+            ("id", "Unknown"),
+            ("name", "Unknown"),
+            ("driver", "Unknown"),
+            ("sample_rate", ""),
+            ("sample_format", ""),
+            ("channels", ""),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "index" => Some(self.index.to_string()),
+            "id" => Some(self.id.to_string()),
+            "name" => Some(self.name.to_string()),
+            "driver" => Some(self.driver.to_string()),
+            "sample_rate" => Some(self.sample_rate.map(|y| y.to_string()).unwrap_or_default()),
+            "sample_format" => Some(self.sample_format.to_string()),
+            "channels" => Some(self.channels.map(|y| y.to_string()).unwrap_or_default()),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on audio devices module. This should never happen, please make a bug report!")
+    }
+}
+impl AudioDeviceInfo {
+    fn is_ignored(&self, config: &Configuration) -> bool {
+        config.audiodevices.ignore.iter().any(|x| self.name.starts_with(x) || self.id.starts_with(x))
+    }
+}
+
+pub fn get_audio_devices(config: &Configuration) -> Result<Vec<AudioDeviceInfo>, ModuleError> {
+    // Goes straight to ALSA, so this works the same under PipeWire, PulseAudio or nothing at all
+    let cards: String = match util::file_read(Path::new("/proc/asound/cards")) {
+        Ok(r) => r,
+        Err(e) => return Err(ModuleError::new("AudioDevices", format!("Can't read from /proc/asound/cards - {}", e))),
+    };
+
+    let mut devices: Vec<AudioDeviceInfo> = parse_cards(&cards);
+    devices.retain(|x| !x.is_ignored(config));
+    for device in devices.iter_mut() {
+        if let Some((rate, format, channels)) = active_hw_params(device.index) {
+            device.sample_rate = Some(rate);
+            device.sample_format = format;
+            device.channels = channels;
+        }
+    }
+
+    Ok(devices)
+}
+
+// Each card is two lines, e.g
+//  0 [PCH            ]: HDA-Intel - HDA Intel PCH
+//                       HDA Intel PCH at 0xf7f10000 irq 32
+// We only care about the first
+fn parse_cards(cards: &str) -> Vec<AudioDeviceInfo> {
+    cards.lines().filter_map(|line| {
+        let (index, line) = line.trim_start().split_once(' ')?;
+        let index: u8 = index.parse().ok()?;
+        let (id, line) = line.trim_start().strip_prefix('[')?.split_once("]:")?;
+        let (driver, name) = line.trim().split_once(" - ")?;
+
+        Some(AudioDeviceInfo {
+            index,
+            id: id.trim().to_string(),
+            name: name.trim().to_string(),
+            driver: driver.trim().to_string(),
+            ..AudioDeviceInfo::new()
+        })
+    }).collect()
+}
+
+// The sample rate, format and channels of the first playback stream that's open on the card
+fn active_hw_params(card: u8) -> Option<(u32, String, Option<u8>)> {
+    let card_dir: String = format!("/proc/asound/card{}", card);
+    let pcms: ReadDir = fs::read_dir(&card_dir).ok()?;
+    for pcm in pcms.filter_map(|x| x.ok()) {
+        let pcm_name: String = pcm.file_name().to_string_lossy().to_string();
+        if !pcm_name.starts_with("pcm") || !pcm_name.ends_with('p') {
+            continue;
+        }
+        let substreams: ReadDir = match fs::read_dir(pcm.path()) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        for substream in substreams.filter_map(|x| x.ok()) {
+            if !substream.file_name().to_string_lossy().starts_with("sub") {
+                continue;
+            }
+            if let Some(r) = util::file_read(&substream.path().join("hw_params")).ok().and_then(|x| parse_hw_params(&x)) {
+                return Some(r);
+            }
+        }
+    }
+
+    None
+}
+// Just "closed" if nothing's using it
+fn parse_hw_params(params: &str) -> Option<(u32, String, Option<u8>)> {
+    let mut rate: Option<u32> = None;
+    let mut format: String = String::new();
+    let mut channels: Option<u8> = None;
+    for line in params.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(r) => r,
+            None => continue,
+        };
+        let value: &str = value.trim();
+        match key.trim() {
+            // e.g "48000 (48000/1)"
+            "rate" => rate = value.split_whitespace().next().and_then(|x| x.parse().ok()),
+            "format" => format = value.to_string(),
+            "channels" => channels = value.parse().ok(),
+            _ => {},
+        }
+    }
+
+    Some((rate?, format, channels))
+}
+
+
+mod tests {
+    // Test the card list and stream parameters are read from their /proc/asound formats
+    #[test]
+    fn audio_devices() {
+        let cards: Vec<super::AudioDeviceInfo> = super::parse_cards(" 0 [PCH            ]: HDA-Intel - HDA Intel PCH\n                      HDA Intel PCH at 0xf7f10000 irq 32\n 1 [DAC            ]: USB-Audio - Modi 3+\n                      Schiit Audio Modi 3+ at usb-0000:00:14.0-2, high speed\n");
+        assert_eq!(cards.len(), 2);
+        assert_eq!((cards[1].index, cards[1].id.as_str(), cards[1].driver.as_str(), cards[1].name.as_str()), (1, "DAC", "USB-Audio", "Modi 3+"));
+
+        assert_eq!(super::parse_hw_params("closed\n"), None);
+        assert_eq!(super::parse_hw_params("access: RW_INTERLEAVED\nformat: S24_3LE\nsubformat: STD\nchannels: 2\nrate: 96000 (96000/1)\nperiod_size: 1024\n"), Some((96000, "S24_3LE".to_string(), Some(2))));
+    }
+}
@@ -1,21 +1,27 @@
 use std::{fs::{self, DirEntry, ReadDir}, path::PathBuf};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}, module::Module, util, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, util, ModuleError};
 
+#[derive(Serialize)]
 pub struct BatteryInfo {
     index: String,
     percentage: f32,
+    // Only when the battery reports them
+    temperature_celsius: Option<f32>,
+    voltage: Option<f32>,
+    energy_rate_watts: Option<f32>,
 }
 #[derive(Deserialize)]
 pub struct BatteryConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub progress_left_border: Option<String>,
     pub progress_right_border: Option<String>,
     pub progress_progress: Option<String>,
@@ -27,7 +33,10 @@ impl Module for BatteryInfo {
     fn new() -> BatteryInfo {
         BatteryInfo {
             index: "Unknown".to_string(),
-            percentage: 0.0
+            percentage: 0.0,
+            temperature_celsius: None,
+            voltage: None,
+            energy_rate_watts: None,
         }
     }
 
@@ -35,44 +44,69 @@ impl Module for BatteryInfo {
         let title_color: &CrabFetchColor = config.battery.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.battery.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.battery.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.battery.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.battery.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.battery.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.battery.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.battery.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.battery.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.battery.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.battery.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.battery.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.battery.title
-            .replace("{index}", "0").to_string()
-            .replace("{percentage}", "Unknown").to_string()
-            .replace("{bar}", "").to_string();
+        let title: String = config.battery.title.render_values(&[
+            ("index", "0"),
+            ("percentage", "Unknown"),
+            ("bar", ""),
+            ("temperature", "Unknown"),
+            ("voltage", "Unknown"),
+            ("energy_rate", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let dec_places: u32 = config.battery.decimal_places.unwrap_or(config.decimal_places);
 
-        let mut bar: String = String::new();
-        if text.contains("{bar}") {
-            let left_border: &str = config.battery.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
-            let right_border: &str = config.battery.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
-            let progress: &str = config.battery.progress_progress.as_ref().unwrap_or(&config.progress_progress);
-            let empty: &str = config.battery.progress_empty.as_ref().unwrap_or(&config.progress_empty);
-            let length: u8 = config.battery.progress_target_length.unwrap_or(config.progress_target_length);
-            formatter::make_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length);
-        }
-
-        formatter::process_percentage_placeholder(text, formatter::round(self.percentage as f64, dec_places) as f32, config)
-            .replace("{index}", &self.index)
-            .replace("{percentage}", &self.percentage.to_string())
-            .replace("{bar}", &bar)
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "percent" => Some(formatter::format_percentage(formatter::round(self.percentage as f64, dec_places) as f32, config)),
+            "index" => Some(self.index.to_string()),
+            "percentage" => Some(self.percentage.to_string()),
+            "temperature" => Some(match self.temperature_celsius {
+                Some(r) => formatter::format_temperature(r, dec_places, config),
+                None => "Unknown".to_string(),
+            }),
+            "voltage" => Some(match self.voltage {
+                Some(r) => format!("{}V", formatter::round(r as f64, dec_places)),
+                None => "Unknown".to_string(),
+            }),
+            "energy_rate" => Some(match self.energy_rate_watts {
+                Some(r) => format!("{}W", formatter::round(r as f64, dec_places)),
+                None => "Unknown".to_string(),
+            }),
+            "bar" | "bar_with_percent" => {
+                let left_border: &str = config.battery.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.battery.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.battery.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.battery.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.battery.progress_target_length.unwrap_or(config.progress_target_length);
+
+                let mut bar: String = String::new();
+                if x == "bar_with_percent" || config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length, config);
+                }
+                Some(bar)
+            },
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
@@ -113,12 +147,58 @@ pub fn get_batteries() -> Result<Vec<BatteryInfo>, ModuleError> {
             None => continue,
         };
 
+        let (temperature_celsius, voltage, energy_rate_watts): (Option<f32>, Option<f32>, Option<f32>) = match util::file_read(&path.join("uevent")) {
+            Ok(r) => parse_uevent(&r),
+            Err(_) => (None, None, None),
+        };
+
         batteries.push(BatteryInfo {
             index: id,
-            percentage
+            percentage,
+            temperature_celsius,
+            voltage,
+            energy_rate_watts,
         })
     }
 
 
     Ok(batteries)
 }
+
+// The temperature, voltage and how fast it's (dis)charging, from the battery's uevent
+// Most laptops only give some of these, if any
+// https://www.kernel.org/doc/html/latest/power/power_supply_class.html
+fn parse_uevent(uevent: &str) -> (Option<f32>, Option<f32>, Option<f32>) {
+    let value = |key: &str| -> Option<f32> {
+        uevent.lines()
+            .find_map(|x| x.strip_prefix(key)?.strip_prefix('='))
+            .and_then(|x| x.trim().parse::<f32>().ok())
+    };
+
+    // Tenths of a degree
+    let temperature: Option<f32> = value("POWER_SUPPLY_TEMP").map(|x| x / 10.0);
+    // Everything else is in micro-units
+    let voltage: Option<f32> = value("POWER_SUPPLY_VOLTAGE_NOW").map(|x| x / 1_000_000.0);
+    // Some only report the current, in which case the power can be worked out from that
+    let energy_rate: Option<f32> = match value("POWER_SUPPLY_POWER_NOW") {
+        Some(r) => Some(r / 1_000_000.0),
+        None => value("POWER_SUPPLY_CURRENT_NOW").zip(voltage).map(|(current, voltage)| current / 1_000_000.0 * voltage),
+    }.map(|x| x.abs());
+
+    (temperature, voltage, energy_rate)
+}
+
+
+mod tests {
+    // Test the temperature, voltage and energy rate are read out of the uevent in the right units
+    #[test]
+    fn battery_uevent() {
+        let uevent: &str = "POWER_SUPPLY_NAME=BAT0\nPOWER_SUPPLY_STATUS=Discharging\nPOWER_SUPPLY_VOLTAGE_NOW=12350000\nPOWER_SUPPLY_POWER_NOW=8200000\nPOWER_SUPPLY_TEMP=312\n";
+        assert_eq!(super::parse_uevent(uevent), (Some(31.2), Some(12.35), Some(8.2)));
+
+        // Current only, so it's worked out from the voltage
+        let uevent: &str = "POWER_SUPPLY_VOLTAGE_NOW=12000000\nPOWER_SUPPLY_CURRENT_NOW=-500000\n";
+        assert_eq!(super::parse_uevent(uevent), (None, Some(12.0), Some(6.0)));
+        assert_eq!(super::parse_uevent(""), (None, None, None));
+    }
+}
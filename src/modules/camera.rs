@@ -0,0 +1,225 @@
+use std::{fs::{self, File, OpenOptions, ReadDir}, mem, os::{fd::AsRawFd, unix::fs::OpenOptionsExt}, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util, ModuleError};
+
+#[derive(Serialize)]
+pub struct CameraInfo {
+    name: String,
+    path: String,
+    // The largest the camera can capture at
+    resolution: Option<(u32, u32)>,
+    in_use: bool,
+}
+#[derive(Deserialize)]
+pub struct CameraConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+    pub in_use_text: String,
+    pub not_in_use_text: String,
+    pub ignore: Vec<String>,
+}
+impl Module for CameraInfo {
+    fn new() -> CameraInfo {
+        CameraInfo {
+            name: "Unknown".to_string(),
+            path: "Unknown".to_string(),
+            resolution: None,
+            in_use: false,
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.camera.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.camera.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.camera.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.camera.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.camera.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.camera.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.camera.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.camera.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.camera.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.camera.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.camera.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.camera.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.camera.title.render_values(&[
+            ("name", "Unknown"),
+            ("path", "Unknown"),
+            ("resolution", "Unknown"),
+            ("in_use", "Unknown"),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "path" => Some(self.path.to_string()),
+            "resolution" => Some(match self.resolution {
+                Some((width, height)) => format!("{}x{}", width, height),
+                None => "Unknown".to_string(),
+            }),
+            "in_use" => Some(match self.in_use {
+                true => config.camera.in_use_text.to_string(),
+                false => config.camera.not_in_use_text.to_string(),
+            }),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on camera module. This should never happen, please make a bug report!")
+    }
+}
+
+pub fn get_cameras(config: &Configuration) -> Result<Vec<CameraInfo>, ModuleError> {
+    // JSON wants everything, regardless of the format
+    let placeholders: Vec<String> = [&config.camera.format, &config.camera.title].iter().flat_map(|x| x.placeholder_names()).collect();
+    let uses = |placeholder: &str| config.detect_all_info || placeholders.iter().any(|x| x == placeholder);
+
+    let dir: ReadDir = match fs::read_dir("/sys/class/video4linux") {
+        Ok(r) => r,
+        Err(e) => return Err(ModuleError::new("Camera", format!("Can't read from /sys/class/video4linux - {}", e))),
+    };
+    let mut nodes: Vec<(u32, PathBuf)> = dir.filter_map(|x| x.ok())
+        .filter_map(|x| Some((x.file_name().to_string_lossy().strip_prefix("video")?.parse::<u32>().ok()?, x.path())))
+        .collect();
+    nodes.sort_by_key(|x| x.0);
+
+    // Only scanned once, and only if it's needed as it's the slowest part of this
+    let open_devices: Vec<PathBuf> = match uses("in_use") {
+        true => open_device_paths(),
+        false => Vec::new(),
+    };
+
+    let mut cameras: Vec<CameraInfo> = Vec::new();
+    for (node, sys_path) in nodes {
+        // Webcams usually make a second node for their metadata, which isn't a camera of it's own
+        if util::file_read(&sys_path.join("index")).map(|x| x.trim() != "0").unwrap_or(false) {
+            continue;
+        }
+        let name: String = match util::file_read(&sys_path.join("name")) {
+            Ok(r) => r.trim().to_string(),
+            Err(_) => continue,
+        };
+        if config.camera.ignore.iter().any(|x| name.starts_with(x)) {
+            continue;
+        }
+
+        let path: PathBuf = PathBuf::from(format!("/dev/video{}", node));
+        cameras.push(CameraInfo {
+            name,
+            resolution: match uses("resolution") {
+                true => max_resolution(&path),
+                false => None,
+            },
+            in_use: open_devices.contains(&path),
+            path: path.display().to_string(),
+        });
+    }
+
+    Ok(cameras)
+}
+
+// Anything that's got the device open, e.g a video call
+// This can only see the processes we're allowed to look at, which is normally just our own user's
+fn open_device_paths() -> Vec<PathBuf> {
+    let dir: ReadDir = match fs::read_dir("/proc") {
+        Ok(r) => r,
+        Err(_) => return Vec::new(),
+    };
+
+    dir.filter_map(|x| x.ok())
+        .filter(|x| x.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter_map(|x| fs::read_dir(x.path().join("fd")).ok())
+        .flat_map(|x| x.filter_map(|y| y.ok()))
+        .filter_map(|x| fs::read_link(x.path()).ok())
+        .filter(|x| x.starts_with("/dev/video"))
+        .collect()
+}
+
+// Asks V4L2 for the largest frame size of the camera's first format
+// This doesn't start the camera, so it won't turn the light on
+// https://www.kernel.org/doc/html/latest/userspace-api/media/v4l/vidioc-enum-framesizes.html
+#[repr(C)]
+struct V4L2FmtDesc {
+    index: u32,
+    buf_type: u32,
+    flags: u32,
+    description: [u8; 32],
+    pixel_format: u32,
+    mbus_code: u32,
+    reserved: [u32; 3],
+}
+#[repr(C)]
+struct V4L2FrmSizeEnum {
+    index: u32,
+    pixel_format: u32,
+    size_type: u32,
+    // Either width, height for discrete sizes, or min width, max width, step width, min height, max height, step height
+    size: [u32; 6],
+    reserved: [u32; 2],
+}
+const fn iowr(nr: u64, size: usize) -> u64 {
+    (3 << 30) | ((size as u64) << 16) | ((b'V' as u64) << 8) | nr
+}
+const VIDIOC_ENUM_FMT: u64 = iowr(2, mem::size_of::<V4L2FmtDesc>());
+const VIDIOC_ENUM_FRAMESIZES: u64 = iowr(74, mem::size_of::<V4L2FrmSizeEnum>());
+const V4L2_BUF_TYPE_VIDEO_CAPTURE: u32 = 1;
+const V4L2_FRMSIZE_TYPE_DISCRETE: u32 = 1;
+
+fn max_resolution(path: &Path) -> Option<(u32, u32)> {
+    let file: File = OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path).ok()?;
+
+    let mut format: V4L2FmtDesc = unsafe { mem::zeroed() };
+    format.buf_type = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+    if unsafe { libc::ioctl(file.as_raw_fd(), VIDIOC_ENUM_FMT as _, &mut format) } != 0 {
+        return None;
+    }
+
+    let mut sizes: Vec<(u32, u32)> = Vec::new();
+    for index in 0..64 {
+        let mut size: V4L2FrmSizeEnum = unsafe { mem::zeroed() };
+        size.index = index;
+        size.pixel_format = format.pixel_format;
+        if unsafe { libc::ioctl(file.as_raw_fd(), VIDIOC_ENUM_FRAMESIZES as _, &mut size) } != 0 {
+            break;
+        }
+        if size.size_type != V4L2_FRMSIZE_TYPE_DISCRETE {
+            // Continuous/stepwise only have the one entry, with the range in it
+            sizes.push((size.size[1], size.size[4]));
+            break;
+        }
+        sizes.push((size.size[0], size.size[1]));
+    }
+
+    largest_resolution(&sizes)
+}
+fn largest_resolution(sizes: &[(u32, u32)]) -> Option<(u32, u32)> {
+    sizes.iter().max_by_key(|x| x.0 as u64 * x.1 as u64).copied()
+}
+
+
+mod tests {
+    // Test the ioctl numbers match the kernel's and the largest frame size is picked
+    #[test]
+    fn camera_resolution() {
+        assert_eq!(super::VIDIOC_ENUM_FMT, 0xc0405602);
+        assert_eq!(super::VIDIOC_ENUM_FRAMESIZES, 0xc02c564a);
+        assert_eq!(super::largest_resolution(&[(640, 480), (1920, 1080), (1280, 720)]), Some((1920, 1080)));
+        assert_eq!(super::largest_resolution(&[]), None);
+    }
+}
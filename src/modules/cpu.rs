@@ -1,32 +1,57 @@
 use core::str;
-use std::{fs::{read_dir, File, ReadDir}, io::{BufRead, BufReader, Read}, path::{Component, Path}};
+use std::{collections::HashSet, fs::{read_dir, File, ReadDir}, io::{BufRead, BufReader, Read}, path::{Component, Path}, thread, time::Duration};
 
 #[cfg(feature = "android")]
 use {android_system_properties::AndroidSystemProperties, std::env};
 #[cfg(target_arch = "x86_64")]
 use raw_cpuid::CpuId;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, soc, util::{self, is_flag_set_u32}, ModuleError};
 
+#[derive(Serialize)]
 pub struct CPUInfo {
     name: String,
     cores: u16,
     threads: u16,
     current_clock_mhz: f32,
     max_clock_mhz: f32,
-    arch: String
+    arch: String,
+    sockets: u16,
+    numa_nodes: u16,
+    // Always in celsius, it's converted to temperature_unit when it's shown
+    temperature_celsius: Option<f32>,
+    usage_percent: Option<f32>,
+    p_cores: u16,
+    e_cores: u16,
+    p_core_max_clock_mhz: Option<f32>,
+    e_core_max_clock_mhz: Option<f32>,
+    governor: String,
+    scaling_driver: String,
+    soc_name: String,
+    vendor: String,
+    family: String,
+    model_id: String,
+    stepping: String,
+    microcode: String
 }
 #[derive(Deserialize)]
 pub struct CPUConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub decimal_places: Option<u32>,
-    pub remove_trailing_processor: bool
+    pub remove_trailing_processor: bool,
+    pub usage_interval: u64,
+    pub progress_left_border: Option<String>,
+    pub progress_right_border: Option<String>,
+    pub progress_progress: Option<String>,
+    pub progress_empty: Option<String>,
+    pub progress_target_length: Option<u8>,
 }
 
 impl Module for CPUInfo {
@@ -37,7 +62,23 @@ impl Module for CPUInfo {
             threads: 0,
             current_clock_mhz: 0.0,
             max_clock_mhz: 0.0,
-            arch: "Unknown".to_string()
+            arch: "Unknown".to_string(),
+            sockets: 0,
+            numa_nodes: 0,
+            temperature_celsius: None,
+            usage_percent: None,
+            p_cores: 0,
+            e_cores: 0,
+            p_core_max_clock_mhz: None,
+            e_core_max_clock_mhz: None,
+            governor: "Unknown".to_string(),
+            scaling_driver: "Unknown".to_string(),
+            soc_name: String::new(),
+            vendor: "Unknown".to_string(),
+            family: "Unknown".to_string(),
+            model_id: "Unknown".to_string(),
+            stepping: "Unknown".to_string(),
+            microcode: "Unknown".to_string()
         }
     }
 
@@ -45,43 +86,111 @@ impl Module for CPUInfo {
         let title_color: &CrabFetchColor = config.cpu.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.cpu.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.cpu.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.cpu.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.cpu.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.cpu.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.cpu.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.cpu.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.cpu.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.cpu.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.cpu.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.cpu.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.cpu.title
-            .replace("{name}", "Unknown")
-            .replace("{core_count}", "Unknown")
-            .replace("{thread_count}", "Unknown")
-            .replace("{current_clock_mhz}", "Unknown")
-            .replace("{current_clock_ghz}", "Unknown")
-            .replace("{max_clock_mhz}", "Unknown")
-            .replace("{max_clock_ghz}", "Unknown")
-            .replace("{arch}", "Unknown");
+        let title: String = config.cpu.title.render_values(&[
+            ("name", "Unknown"),
+            ("core_count", "Unknown"),
+            ("thread_count", "Unknown"),
+            ("current_clock_mhz", "Unknown"),
+            ("current_clock_ghz", "Unknown"),
+            ("max_clock_mhz", "Unknown"),
+            ("max_clock_ghz", "Unknown"),
+            ("arch", "Unknown"),
+            ("sockets", "Unknown"),
+            ("numa_nodes", "Unknown"),
+            ("temperature", "Unknown"),
+            ("usage_percent", "Unknown"),
+            ("usage_bar", "Unknown"),
+            ("p_core_count", "Unknown"),
+            ("e_core_count", "Unknown"),
+            ("p_core_max_clock_mhz", "Unknown"),
+            ("p_core_max_clock_ghz", "Unknown"),
+            ("e_core_max_clock_mhz", "Unknown"),
+            ("e_core_max_clock_ghz", "Unknown"),
+            ("governor", "Unknown"),
+            ("scaling_driver", "Unknown"),
+            ("soc_name", ""),
+            ("vendor", "Unknown"),
+            ("family", "Unknown"),
+            ("model_id", "Unknown"),
+            ("stepping", "Unknown"),
+            ("microcode", "Unknown"),
+        ]);
         
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let dec_places: u32 = config.cpu.decimal_places.unwrap_or(config.decimal_places);
 
-        text.replace("{name}", &self.name)
-            .replace("{core_count}", &self.cores.to_string())
-            .replace("{thread_count}", &self.threads.to_string())
-            .replace("{current_clock_mhz}", &formatter::round(self.current_clock_mhz as f64, dec_places).to_string())
-            .replace("{current_clock_ghz}", &formatter::round((self.current_clock_mhz / 1000.0) as f64, dec_places).to_string())
-            .replace("{max_clock_mhz}", &formatter::round(self.max_clock_mhz as f64, dec_places).to_string())
-            .replace("{max_clock_ghz}", &formatter::round((self.max_clock_mhz / 1000.0) as f64, dec_places).to_string())
-            .replace("{arch}", &self.arch.to_string())
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "name" => Some(self.name.to_string()),
+            "core_count" => Some(self.cores.to_string()),
+            "thread_count" => Some(self.threads.to_string()),
+            "current_clock_mhz" => Some(formatter::round(self.current_clock_mhz as f64, dec_places).to_string()),
+            "current_clock_ghz" => Some(formatter::round((self.current_clock_mhz / 1000.0) as f64, dec_places).to_string()),
+            "max_clock_mhz" => Some(formatter::round(self.max_clock_mhz as f64, dec_places).to_string()),
+            "max_clock_ghz" => Some(formatter::round((self.max_clock_mhz / 1000.0) as f64, dec_places).to_string()),
+            "p_core_count" => Some(self.p_cores.to_string()),
+            "e_core_count" => Some(self.e_cores.to_string()),
+            "p_core_max_clock_mhz" => Some(format_clock(self.p_core_max_clock_mhz, 1.0, dec_places)),
+            "p_core_max_clock_ghz" => Some(format_clock(self.p_core_max_clock_mhz, 1000.0, dec_places)),
+            "e_core_max_clock_mhz" => Some(format_clock(self.e_core_max_clock_mhz, 1.0, dec_places)),
+            "e_core_max_clock_ghz" => Some(format_clock(self.e_core_max_clock_mhz, 1000.0, dec_places)),
+            "governor" => Some(self.governor.to_string()),
+            "scaling_driver" => Some(self.scaling_driver.to_string()),
+            "soc_name" => Some(self.soc_name.to_string()),
+            "vendor" => Some(self.vendor.to_string()),
+            "family" => Some(self.family.to_string()),
+            "model_id" => Some(self.model_id.to_string()),
+            "stepping" => Some(self.stepping.to_string()),
+            "microcode" => Some(self.microcode.to_string()),
+            "arch" => Some(self.arch.to_string()),
+            "sockets" => Some(self.sockets.to_string()),
+            "numa_nodes" => Some(self.numa_nodes.to_string()),
+            "temperature" => Some(match self.temperature_celsius {
+                Some(r) => formatter::format_temperature(r, dec_places, config),
+                None => "Unknown".to_string(),
+            }),
+            "usage_percent" => Some(match self.usage_percent {
+                Some(r) => formatter::format_percentage(formatter::round(r as f64, dec_places) as f32, config),
+                None => "Unknown".to_string(),
+            }),
+            "usage_bar" => {
+                let usage: f32 = match self.usage_percent {
+                    Some(r) => r,
+                    None => return Some(String::new()),
+                };
+                let left_border: &str = config.cpu.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.cpu.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.cpu.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.cpu.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.cpu.progress_target_length.unwrap_or(config.progress_target_length);
+
+                let mut bar: String = String::new();
+                if config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, usage, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, usage, length, config);
+                }
+                Some(bar)
+            },
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -103,9 +212,33 @@ impl Module for CPUInfo {
         if format.contains("{max_clock_mhz}") || format.contains("{max_clock_ghz}") {
             info_flags |= CPU_INFOFLAG_MAX_CLOCK
         }
+        if ["{p_core_count}", "{e_core_count}", "{p_core_max_clock_mhz}", "{p_core_max_clock_ghz}", "{e_core_max_clock_mhz}", "{e_core_max_clock_ghz}"].iter().any(|x| format.contains(x)) {
+            info_flags |= CPU_INFOFLAG_HYBRID
+        }
+        if format.contains("{governor}") || format.contains("{scaling_driver}") {
+            info_flags |= CPU_INFOFLAG_SCALING
+        }
+        if format.contains("{soc_name}") {
+            info_flags |= CPU_INFOFLAG_SOC
+        }
+        if ["{vendor}", "{family}", "{model_id}", "{stepping}", "{microcode}"].iter().any(|x| format.contains(x)) {
+            info_flags |= CPU_INFOFLAG_IDENTITY
+        }
         if format.contains("{arch}") || format.contains("{arch}") {
             info_flags |= CPU_INFOFLAG_ARCH
         }
+        if format.contains("{sockets}") {
+            info_flags |= CPU_INFOFLAG_SOCKETS
+        }
+        if format.contains("{numa_nodes}") {
+            info_flags |= CPU_INFOFLAG_NUMA_NODES
+        }
+        if format.contains("{temperature}") {
+            info_flags |= CPU_INFOFLAG_TEMPERATURE
+        }
+        if format.contains("{usage_percent}") || format.contains("{usage_bar}") {
+            info_flags |= CPU_INFOFLAG_USAGE
+        }
 
         info_flags
     }
@@ -117,10 +250,18 @@ const CPU_INFOFLAG_THREADS: u32 = 4;
 const CPU_INFOFLAG_CURRENT_CLOCK: u32 = 8;
 const CPU_INFOFLAG_MAX_CLOCK: u32 = 16;
 const CPU_INFOFLAG_ARCH: u32 = 32;
+const CPU_INFOFLAG_SOCKETS: u32 = 64;
+const CPU_INFOFLAG_NUMA_NODES: u32 = 128;
+const CPU_INFOFLAG_TEMPERATURE: u32 = 256;
+const CPU_INFOFLAG_USAGE: u32 = 512;
+const CPU_INFOFLAG_HYBRID: u32 = 1024;
+const CPU_INFOFLAG_SCALING: u32 = 2048;
+const CPU_INFOFLAG_SOC: u32 = 4096;
+const CPU_INFOFLAG_IDENTITY: u32 = 8192;
 
 pub fn get_cpu(config: &Configuration) -> Result<CPUInfo, ModuleError> {
     let mut cpu: CPUInfo = CPUInfo::new();
-    let info_flags: u32 = CPUInfo::gen_info_flags(&config.cpu.format);
+    let info_flags: u32 = CPUInfo::get_info_flags(config.cpu.format.source(), config);
 
     // This ones split into 2 as theres a lot to parse
     match get_basic_info(&mut cpu, info_flags) {
@@ -131,6 +272,27 @@ pub fn get_cpu(config: &Configuration) -> Result<CPUInfo, ModuleError> {
         Ok(_) => {},
         Err(e) => return Err(e)
     };
+    get_topology(&mut cpu, info_flags);
+    get_hybrid_topology(&mut cpu, info_flags);
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_SOC) {
+        cpu.soc_name = soc::soc_name().unwrap_or_default();
+    }
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_SCALING) {
+        // Every CPU can technically have it's own, but they're all but always set the same
+        // https://docs.kernel.org/admin-guide/pm/cpufreq.html
+        if let Ok(r) = util::file_read(Path::new("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor")) {
+            cpu.governor = r.trim().to_string();
+        }
+        if let Ok(r) = util::file_read(Path::new("/sys/devices/system/cpu/cpu0/cpufreq/scaling_driver")) {
+            cpu.scaling_driver = r.trim().to_string();
+        }
+    }
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_TEMPERATURE) {
+        cpu.temperature_celsius = get_temperature();
+    }
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_USAGE) {
+        cpu.usage_percent = get_usage(config.cpu.usage_interval);
+    }
 
     if config.cpu.remove_trailing_processor {
         // Tried doing this with Regex but it added 400 micro secs so fuck that shit
@@ -167,6 +329,7 @@ fn get_basic_info(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError>
     let mut cpu_mhz_count: u8 = 0;
     let mut first_entry: bool = true;
     let mut cores: u16 = 0; // This acts as a backup for the "cpu cores" being missing
+    let mut physical_ids: HashSet<String> = HashSet::new();
     #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
     let mut arm_vendor: String = String::new();
     #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
@@ -183,6 +346,20 @@ fn get_basic_info(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError>
         }
 
         if first_entry {
+            // ARM has the same things under different names
+            if is_flag_set_u32(info_flags, CPU_INFOFLAG_IDENTITY) {
+                if let Some((key, value)) = line.split_once(':') {
+                    let value: String = value.trim().to_string();
+                    match key.trim() {
+                        "vendor_id" | "CPU implementer" => cpu.vendor = vendor_name(&value),
+                        "cpu family" | "CPU architecture" => cpu.family = value,
+                        "model" | "CPU part" => cpu.model_id = value,
+                        "stepping" | "CPU revision" => cpu.stepping = value,
+                        "microcode" => cpu.microcode = value,
+                        _ => {},
+                    }
+                }
+            }
             if line.starts_with("model name") && is_flag_set_u32(info_flags, CPU_INFOFLAG_MODEL_NAME) {
                 cpu.name = line.split(": ").collect::<Vec<&str>>()[1].to_string();
             }
@@ -222,6 +399,12 @@ fn get_basic_info(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError>
                 }
             }
         }
+        // Each socket has it's own physical id, so this needs to look past the first entry
+        if line.starts_with("physical id") && is_flag_set_u32(info_flags, CPU_INFOFLAG_SOCKETS) {
+            if let Some(id) = line.split(": ").nth(1) {
+                physical_ids.insert(id.trim().to_string());
+            }
+        }
         // This ignore's it's feature flag to prevent issues allow max freq to back up to this on
         // failure
         if line.starts_with("cpu MHz") {
@@ -245,7 +428,7 @@ fn get_basic_info(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError>
             Ok(_) => {},
             Err(e) => return Err(ModuleError::new("CPU", format!("Can't read from /sys/devices/system/cpu/present - {}", e))),
         }
-        cpu.threads = match contents.trim().split('-').last().unwrap().parse::<u16>() {
+        cpu.threads = match contents.trim().split('-').next_back().unwrap().parse::<u16>() {
             Ok(r) => r + 1,
             Err(e) => return Err(ModuleError::new("CPU", format!("Failed to parse thread count from /sys/devices/system/cpu/present - {}", e))),
         };
@@ -291,9 +474,196 @@ fn get_basic_info(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError>
         cpu.arch = "AArch64".to_string();
     }
 
+    // SoCs are better known by their own name than their cores', e.g Snapdragon 8 Gen 2 rather than Cortex-X3
+    // Failing that the board's name is still better than nothing
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_MODEL_NAME) {
+        if let Some(soc) = soc::soc_name() {
+            cpu.name = soc;
+        } else if cpu.name == "Unknown" {
+            if let Ok(model) = util::file_read(Path::new("/proc/device-tree/model")) {
+                cpu.name = model.trim_end_matches('\0').trim().to_string();
+            }
+        }
+    }
+
     cpu.current_clock_mhz /= cpu_mhz_count as f32;
+    cpu.sockets = physical_ids.len() as u16;
     Ok(())
 }
+// Turns the vendor ID (x86) or implementer code (ARM) into who actually made it
+fn vendor_name(id: &str) -> String {
+    match id {
+        "GenuineIntel" => "Intel",
+        "AuthenticAMD" => "AMD",
+        "HygonGenuine" => "Hygon",
+        "CentaurHauls" => "Centaur",
+        "Shanghai" => "Zhaoxin",
+        // https://github.com/util-linux/util-linux/blob/master/sys-utils/lscpu-arm.c
+        "0x41" => "ARM",
+        "0x42" => "Broadcom",
+        "0x48" => "HiSilicon",
+        "0x4e" => "NVIDIA",
+        "0x51" => "Qualcomm",
+        "0x53" => "Samsung",
+        "0x61" => "Apple",
+        "0xc0" => "Ampere",
+        _ => id,
+    }.to_string()
+}
+// Sockets are already counted from /proc/cpuinfo, but ARM doesn't give a physical id there so this
+// backs up to the package id of each CPU in sysfs
+// NUMA nodes are just the node directories in /sys/devices/system/node
+fn get_topology(cpu: &mut CPUInfo, info_flags: u32) {
+    if cpu.sockets == 0 && is_flag_set_u32(info_flags, CPU_INFOFLAG_SOCKETS) {
+        let mut package_ids: HashSet<String> = HashSet::new();
+        if let Ok(dir) = read_dir("/sys/devices/system/cpu/") {
+            for entry in dir.flatten() {
+                let file_name: String = entry.file_name().to_string_lossy().to_string();
+                if file_name.len() <= 3 || !file_name.starts_with("cpu") || !file_name[3..].chars().all(|x| x.is_ascii_digit()) {
+                    continue
+                }
+                if let Ok(id) = util::file_read(&entry.path().join("topology/physical_package_id")) {
+                    package_ids.insert(id.trim().to_string());
+                }
+            }
+        }
+        // Can't be less than 1 if we're running on it
+        cpu.sockets = u16::max(package_ids.len() as u16, 1);
+    }
+
+    if is_flag_set_u32(info_flags, CPU_INFOFLAG_NUMA_NODES) {
+        let mut nodes: u16 = 0;
+        if let Ok(dir) = read_dir("/sys/devices/system/node/") {
+            for entry in dir.flatten() {
+                let file_name: String = entry.file_name().to_string_lossy().to_string();
+                if file_name.len() > 4 && file_name.starts_with("node") && file_name[4..].chars().all(|x| x.is_ascii_digit()) {
+                    nodes += 1;
+                }
+            }
+        }
+        // Kernels without NUMA support don't have the directory at all, which is the same as one node
+        cpu.numa_nodes = u16::max(nodes, 1);
+    }
+}
+// Splits the cores up into performance and efficiency cores, e.g Intel's P and E cores or ARM's big.LITTLE
+// Intel lists it's E cores in the cpu_atom PMU, anything else goes by each CPU's capacity, with the
+// lowest ones being the efficiency cores. Without either, they're all performance cores
+fn get_hybrid_topology(cpu: &mut CPUInfo, info_flags: u32) {
+    if !is_flag_set_u32(info_flags, CPU_INFOFLAG_HYBRID) {
+        return
+    }
+
+    let cpus: Vec<u32> = match util::file_read(Path::new("/sys/devices/system/cpu/online")) {
+        Ok(r) => parse_cpu_list(&r),
+        Err(_) => return,
+    };
+    let e_cpus: Vec<u32> = match util::file_read(Path::new("/sys/devices/cpu_atom/cpus")) {
+        Ok(r) => parse_cpu_list(&r),
+        Err(_) => {
+            let capacities: Vec<(u32, u32)> = cpus.iter()
+                .filter_map(|x| {
+                    let capacity: String = util::file_read(Path::new(&format!("/sys/devices/system/cpu/cpu{}/cpu_capacity", x))).ok()?;
+                    Some((*x, capacity.trim().parse::<u32>().ok()?))
+                })
+                .collect();
+            lowest_capacity_cpus(&capacities)
+        },
+    };
+
+    // SMT threads share a core, so those are only counted once
+    let mut p_cores: HashSet<String> = HashSet::new();
+    let mut e_cores: HashSet<String> = HashSet::new();
+    for id in cpus {
+        let dir: String = format!("/sys/devices/system/cpu/cpu{}", id);
+        let core: String = util::file_read(Path::new(&format!("{}/topology/core_cpus_list", dir)))
+            .or_else(|_| util::file_read(Path::new(&format!("{}/topology/thread_siblings_list", dir))))
+            .map(|x| x.trim().to_string())
+            .unwrap_or(id.to_string());
+        // Same order of preference as get_max_clock
+        let max_clock: Option<f32> = ["bios_limit", "scaling_max_freq", "cpuinfo_max_freq"].iter()
+            .find_map(|x| util::file_read(Path::new(&format!("{}/cpufreq/{}", dir, x))).ok())
+            .and_then(|x| x.trim().parse::<f32>().ok())
+            .map(|x| x / 1000.0);
+
+        let (cores, clock): (&mut HashSet<String>, &mut Option<f32>) = match e_cpus.contains(&id) {
+            true => (&mut e_cores, &mut cpu.e_core_max_clock_mhz),
+            false => (&mut p_cores, &mut cpu.p_core_max_clock_mhz),
+        };
+        cores.insert(core);
+        *clock = (*clock).into_iter().chain(max_clock).reduce(f32::max);
+    }
+
+    cpu.p_cores = p_cores.len() as u16;
+    cpu.e_cores = e_cores.len() as u16;
+}
+// e.g "0-7,16,18-19"
+fn parse_cpu_list(list: &str) -> Vec<u32> {
+    list.trim().split(',').filter_map(|x| {
+        let (start, end) = x.split_once('-').unwrap_or((x, x));
+        Some(start.parse::<u32>().ok()?..=end.parse::<u32>().ok()?)
+    }).flatten().collect()
+}
+// Nothing's an efficiency core if every CPU has the same capacity
+fn lowest_capacity_cpus(capacities: &[(u32, u32)]) -> Vec<u32> {
+    let lowest: u32 = match capacities.iter().map(|x| x.1).min() {
+        Some(r) => r,
+        None => return Vec::new(),
+    };
+    if capacities.iter().all(|x| x.1 == lowest) {
+        return Vec::new();
+    }
+
+    capacities.iter().filter(|x| x.1 == lowest).map(|x| x.0).collect()
+}
+fn format_clock(mhz: Option<f32>, divisor: f32, dec_places: u32) -> String {
+    match mhz {
+        Some(r) => formatter::round((r / divisor) as f64, dec_places).to_string(),
+        None => "Unknown".to_string(),
+    }
+}
+// Looks for the CPU's own sensor driver first, as it's the most accurate, then backs up to the
+// thermal zones which most ARM boards use
+fn get_temperature() -> Option<f32> {
+    // temp1 is the package/die temperature on all of these
+    const CPU_HWMON_DRIVERS: [&str; 4] = ["coretemp", "k10temp", "zenpower", "cpu_thermal"];
+    if let Ok(dir) = read_dir("/sys/class/hwmon/") {
+        for entry in dir.flatten() {
+            let name: String = match util::file_read(&entry.path().join("name")) {
+                Ok(r) => r.trim().to_string(),
+                Err(_) => continue,
+            };
+            if !CPU_HWMON_DRIVERS.contains(&name.as_str()) {
+                continue;
+            }
+            if let Some(r) = read_millidegrees(&entry.path().join("temp1_input")) {
+                return Some(r);
+            }
+        }
+    }
+
+    const CPU_THERMAL_ZONES: [&str; 4] = ["x86_pkg_temp", "cpu-thermal", "cpu_thermal", "soc_thermal"];
+    if let Ok(dir) = read_dir("/sys/class/thermal/") {
+        for entry in dir.flatten() {
+            let zone_type: String = match util::file_read(&entry.path().join("type")) {
+                Ok(r) => r.trim().to_string(),
+                Err(_) => continue,
+            };
+            if !CPU_THERMAL_ZONES.contains(&zone_type.as_str()) {
+                continue;
+            }
+            if let Some(r) = read_millidegrees(&entry.path().join("temp")) {
+                return Some(r);
+            }
+        }
+    }
+
+    None
+}
+fn read_millidegrees(path: &Path) -> Option<f32> {
+    let contents: String = util::file_read(path).ok()?;
+    contents.trim().parse::<f32>().ok().map(|x| x / 1000.0)
+}
+
 fn get_max_clock(cpu: &mut CPUInfo, info_flags: u32) -> Result<(), ModuleError> {
     if !is_flag_set_u32(info_flags, CPU_INFOFLAG_MAX_CLOCK) {
         return Ok(())
@@ -614,3 +984,76 @@ const ARM_LOOKUP: [(&str, &str); 193] = [
     // Microsoft
     ("0x6d-0xd49", "Microsoft Azure-Cobalt-100")
 ];
+
+// How busy the CPU's been, from the difference between two samples of /proc/stat `interval` ms apart
+// An interval of 0 uses the totals since boot instead, which is instant but only gives an average
+fn get_usage(interval: u64) -> Option<f32> {
+    let first: (u64, u64) = read_cpu_times()?;
+    if interval == 0 {
+        return usage_between((0, 0), first);
+    }
+    thread::sleep(Duration::from_millis(interval));
+    let second: (u64, u64) = read_cpu_times()?;
+
+    usage_between(first, second)
+}
+fn usage_between(first: (u64, u64), second: (u64, u64)) -> Option<f32> {
+    let total: u64 = second.0.checked_sub(first.0)?;
+    let idle: u64 = second.1.checked_sub(first.1)?;
+    if total == 0 {
+        return None;
+    }
+
+    Some((total - idle.min(total)) as f32 / total as f32 * 100.0)
+}
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat: String = util::file_read(Path::new("/proc/stat")).ok()?;
+    parse_cpu_times(stat.lines().next()?)
+}
+// The total and idle time from the "cpu" line
+// https://man7.org/linux/man-pages/man5/proc_stat.5.html
+fn parse_cpu_times(line: &str) -> Option<(u64, u64)> {
+    let values: Vec<u64> = line.strip_prefix("cpu ")?
+        .split_whitespace()
+        .map(|x| x.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>().ok()?;
+    if values.len() < 4 {
+        return None;
+    }
+
+    // user, nice, system, idle, iowait, irq, softirq, steal. guest time is already counted in user
+    let total: u64 = values.iter().take(8).sum();
+    let idle: u64 = values[3] + values.get(4).unwrap_or(&0);
+    Some((total, idle))
+}
+
+
+mod tests {
+    // Test the CPU usage is worked out from the difference in time spent idle
+    #[test]
+    fn cpu_usage() {
+        let first: (u64, u64) = super::parse_cpu_times("cpu  100 0 100 700 100 0 0 0 50 0").unwrap();
+        assert_eq!(first, (1000, 800));
+        let second: (u64, u64) = super::parse_cpu_times("cpu  200 0 200 800 100 0 0 0 50 0").unwrap();
+        assert_eq!(super::usage_between(first, second).map(|x| x.round()), Some(67.0));
+        assert_eq!(super::usage_between(first, first), None);
+        assert_eq!(super::parse_cpu_times("cpu0 1 2 3 4"), None);
+    }
+
+    // Test vendor IDs are turned into the vendor's name, and anything unknown is left as is
+    #[test]
+    fn vendor_name() {
+        assert_eq!(super::vendor_name("GenuineIntel"), "Intel");
+        assert_eq!(super::vendor_name("0x61"), "Apple");
+        assert_eq!(super::vendor_name("VIA VIA VIA "), "VIA VIA VIA ");
+    }
+
+    // Test CPU lists are expanded and only the lowest capacity CPUs count as efficiency cores
+    #[test]
+    fn hybrid_topology() {
+        assert_eq!(super::parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(super::parse_cpu_list(""), Vec::<u32>::new());
+        assert_eq!(super::lowest_capacity_cpus(&[(0, 446), (1, 446), (2, 1024), (3, 1024)]), vec![0, 1]);
+        assert_eq!(super::lowest_capacity_cpus(&[(0, 1024), (1, 1024)]), Vec::<u32>::new());
+    }
+}
@@ -1,19 +1,21 @@
 use chrono::{DateTime, Local};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{formatter::CrabFetchColor, config_manager::Configuration, module::Module};
+use crate::{formatter::{CrabFetchColor, FormatTemplate}, config_manager::Configuration, module::Module};
 
+#[derive(Serialize)]
 pub struct DateTimeInfo {
     datetime: DateTime<Local>,
 }
 #[derive(Deserialize)]
 pub struct DateTimeConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
 }
 impl Module for DateTimeInfo {
     fn new() -> DateTimeInfo {
@@ -26,23 +28,34 @@ impl Module for DateTimeInfo {
         let title_color: &CrabFetchColor = config.datetime.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.datetime.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.datetime.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.datetime.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.datetime.separator.as_ref().unwrap_or(&config.separator);
 
+        // There's nothing of this module's own to put in the title, but it can still have the global placeholders
+        let title: String = config.datetime.title.render(|_| None);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.datetime.format, config), config);
 
-        Self::default_style(config, &config.datetime.title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.datetime.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.datetime.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.datetime.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.datetime.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.datetime.separator.as_ref().unwrap_or(&config.separator);
 
-        Self::default_style(config, &config.datetime.title, title_color, title_bold, title_italic, separator, "Unknown")
+        let title: String = config.datetime.title.render(|_| None);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        // This one's a chrono format string, not our placeholders
+        self.datetime.format(text.source()).to_string()
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        self.datetime.format(text).to_string()
+    fn unknown_placeholders(_: &FormatTemplate, _: &Configuration) -> Vec<String> {
+        Vec::new()
     }
 
     fn gen_info_flags(_: &str) -> u32 {
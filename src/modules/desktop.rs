@@ -1,27 +1,31 @@
-use std::env;
+use std::{cell::OnceCell, collections::HashMap, env};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util::{self, is_flag_set_u32}, ModuleError};
 
+#[derive(Serialize)]
 pub struct DesktopInfo {
     desktop: String,
-    display_type: String
+    display_type: String,
+    seat: String,
 }
 #[derive(Deserialize)]
 pub struct DesktopConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
 }
 impl Module for DesktopInfo {
     fn new() -> DesktopInfo {
         DesktopInfo {
             desktop: "Unknown".to_string(),
-            display_type: "Unknown".to_string()
+            display_type: "Unknown".to_string(),
+            seat: "Unknown".to_string(),
         }
     }
 
@@ -29,29 +33,37 @@ impl Module for DesktopInfo {
         let title_color: &CrabFetchColor = config.desktop.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.desktop.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.desktop.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.desktop.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.desktop.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.desktop.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.desktop.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.desktop.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.desktop.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.desktop.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.desktop.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.desktop.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.desktop.title
-            .replace("{desktop}", "Unknown")
-            .replace("{display_type}", "Unknown");
+        let title: String = config.desktop.title.render_values(&[
+            ("desktop", "Unknown"),
+            ("display_type", "Unknown"),
+            ("seat", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{desktop}", &self.desktop)
-            .replace("{display_type}", &self.display_type)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "desktop" => Some(self.desktop.to_string()),
+            "display_type" => Some(self.display_type.to_string()),
+            "seat" => Some(self.seat.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -63,6 +75,9 @@ impl Module for DesktopInfo {
         if format.contains("{display_type}") {
             info_flags |= DESKTOP_INFOFLAG_DISPLAY_TYPE
         }
+        if format.contains("{seat}") {
+            info_flags |= DESKTOP_INFOFLAG_SEAT
+        }
 
         info_flags
     }
@@ -70,10 +85,11 @@ impl Module for DesktopInfo {
 
 const DESKTOP_INFOFLAG_DESKTOP: u32 = 1;
 const DESKTOP_INFOFLAG_DISPLAY_TYPE: u32 = 2;
+const DESKTOP_INFOFLAG_SEAT: u32 = 4;
 
 pub fn get_desktop(config: &Configuration) -> Result<DesktopInfo, ModuleError> {
     let mut desktop: DesktopInfo = DesktopInfo::new();
-    let info_flags: u32 = DesktopInfo::gen_info_flags(&config.desktop.format);
+    let info_flags: u32 = DesktopInfo::get_info_flags(config.desktop.format.source(), config);
 
     if util::in_wsl() {
         // WSLG weird shit https://github.com/microsoft/wslg
@@ -82,12 +98,25 @@ pub fn get_desktop(config: &Configuration) -> Result<DesktopInfo, ModuleError> {
         return Ok(desktop);
     }
 
+    // When the env doesn't tell us anything (e.g over SSH or on a TTY), logind still knows what
+    // session we're in
+    // Only read lazily, so it's skipped unless a placeholder actually needs the fallback
+    let session: OnceCell<Option<HashMap<String, String>>> = OnceCell::new();
+    let session_value = |key: &str| -> Option<String> {
+        session.get_or_init(util::logind_session).as_ref()?.get(key).filter(|x| !x.is_empty()).cloned()
+    };
+
     if is_flag_set_u32(info_flags, DESKTOP_INFOFLAG_DESKTOP) {
         desktop.desktop = match env::var("XDG_CURRENT_DESKTOP") {
             Ok(r) => r,
                 Err(_) => match env::var("DESKTOP_SESSION") {
                     Ok(r) => r,
-                    Err(e) => return Err(ModuleError::new("Desktop", format!("Could not parse $XDG_CURRENT_DESKTOP or $DESKTOP_SESSION env variable: {}", e)))
+                    Err(e) => match session_value("DESKTOP") {
+                        Some(r) => r,
+                        // A TTY session simply has no desktop, that's not a failure
+                        None if session_value("TYPE").as_deref() == Some("tty") => "None".to_string(),
+                        None => return Err(ModuleError::new("Desktop", format!("Could not parse $XDG_CURRENT_DESKTOP or $DESKTOP_SESSION env variable: {}", e)))
+                    }
             }
         };
     }
@@ -100,10 +129,21 @@ pub fn get_desktop(config: &Configuration) -> Result<DesktopInfo, ModuleError> {
         } else {
             match env::var("XDG_SESSION_TYPE") {
                 Ok(r) => r,
-                Err(_) => return Err(ModuleError::new("Desktop", "Could not identify desktop session type.".to_string()))
+                Err(_) => match session_value("TYPE") {
+                    Some(r) => r,
+                    None => return Err(ModuleError::new("Desktop", "Could not identify desktop session type.".to_string()))
+                }
             }
         }
     }
 
+    if is_flag_set_u32(info_flags, DESKTOP_INFOFLAG_SEAT) {
+        desktop.seat = match env::var("XDG_SEAT") {
+            Ok(r) => r,
+            // Sessions without a seat are remote ones, e.g SSH
+            Err(_) => session_value("SEAT").unwrap_or("None".to_string()),
+        };
+    }
+
     Ok(desktop)
 }
@@ -1,54 +1,95 @@
 use core::str;
-use std::{collections::HashMap, env, fs::{self, read_dir, ReadDir}};
+use std::{collections::HashMap, env, fmt::{Display, Formatter}, fs::{self, read_dir, ReadDir}, path::Path};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use wayland_client::{protocol::{wl_output::{self, Transform}, wl_registry}, ConnectError, Connection, Dispatch, QueueHandle, WEnum};
-use x11rb::{connection::RequestConnection, protocol::{randr::{self, ConnectionExt, GetCrtcInfoReply, GetOutputInfoReply, GetScreenResourcesCurrentReply, ModeInfo, MonitorInfo, Rotation}, xproto::{self, Screen}}};
+use x11rb::{connection::RequestConnection, protocol::{randr::{self, ConnectionExt, GetCrtcInfoReply, GetOutputInfoReply, GetScreenResourcesCurrentReply, ModeFlag, ModeInfo, MonitorInfo, Rotation}, xproto::{self, Screen}}};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use self::xdg_output::{zxdg_output_manager_v1::{self, ZxdgOutputManagerV1}, zxdg_output_v1::{self, ZxdgOutputV1}};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::{Module, ModuleWarning}, util::{self, is_flag_set_u32}, ModuleError};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct DisplayInfo {
+    index: u8,
+    primary: bool,
     name: String,
     make: String,
     model: String,
     width: u16,
     height: u16,
+    logical_width: u16,
+    logical_height: u16,
     scale: i32,
     refresh_rate: u16,
     rotation: u16,
 }
 impl DisplayInfo {
     fn calc_rotation(&mut self) {
-        if self.rotation % 180 != 0 {
+        if !self.rotation.is_multiple_of(180) {
             // Swap width/height
             (self.width, self.height) = (self.height, self.width);
         }
     }
-    fn scale_resolution(&mut self) {
-        self.width /= self.scale as u16;
-        self.height /= self.scale as u16;
+    // Backends that can't give us the size the compositor uses (anything but Wayland w/ xdg-output)
+    // just get it from the integer scale instead
+    fn calc_logical_size(&mut self) {
+        if self.logical_width != 0 && self.logical_height != 0 {
+            return;
+        }
+        let scale: u16 = self.scale.max(1) as u16;
+        self.logical_width = self.width / scale;
+        self.logical_height = self.height / scale;
+    }
+}
+
+// Why the displays couldn't be found, so the diagnostics can say what's actually wrong
+pub enum DisplayError {
+    // Nothing to get the displays from, e.g no Wayland/X11 session and nothing connected through DRM
+    NoServer(String),
+    // The display server's there, but it's missing something we need, e.g X11's randr extension
+    ToolMissing(String),
+    // The display server answered, but not with anything we could use
+    ParseFailed(String),
+}
+impl Display for DisplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisplayError::NoServer(r) => write!(f, "No display server: {}", r),
+            DisplayError::ToolMissing(r) => write!(f, "Missing display server support: {}", r),
+            DisplayError::ParseFailed(r) => write!(f, "Bad response from display server: {}", r),
+        }
+    }
+}
+impl From<DisplayError> for ModuleError {
+    fn from(e: DisplayError) -> ModuleError {
+        ModuleError::new("Display", e.to_string())
     }
 }
 
 #[derive(Deserialize)]
 pub struct DisplayConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub scale_size: bool,
+    pub primary_only: bool,
 }
 impl Module for DisplayInfo {
     fn new() -> DisplayInfo {
         DisplayInfo {
+            index: 0,
+            primary: false,
             name: "Unknown".to_string(),
             make: "Unknown".to_string(),
             model: "Unknown".to_string(),
             width: 0,
             height: 0,
+            logical_width: 0,
+            logical_height: 0,
             scale: 0,
             refresh_rate: 0,
             rotation: 0
@@ -59,37 +100,61 @@ impl Module for DisplayInfo {
         let title_color: &CrabFetchColor = config.displays.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.displays.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.displays.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.displays.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.displays.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.displays.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.displays.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.displays.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.displays.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.displays.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.displays.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.displays.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.displays.title
-            .replace("{name}", "Unknown")
-            .replace("{make}", "Unknown")
-            .replace("{model}", "Unknown")
-            .replace("{width}", "Unknown")
-            .replace("{height}", "Unknown")
-            .replace("{refresh_rate}", "Unknown");
-
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        let title: String = config.displays.title.render_values(&[
+            ("index", "0"),
+            ("primary", ""),
+            ("name", "Unknown"),
+            ("make", "Unknown"),
+            ("model", "Unknown"),
+            ("width", "Unknown"),
+            ("height", "Unknown"),
+            ("physical_width", "Unknown"),
+            ("physical_height", "Unknown"),
+            ("logical_width", "Unknown"),
+            ("logical_height", "Unknown"),
+            ("refresh_rate", "Unknown"),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{name}", &self.name)
-            .replace("{make}", &self.make)
-            .replace("{model}", &self.model)
-            .replace("{width}", &self.width.to_string())
-            .replace("{height}", &self.height.to_string())
-            .replace("{refresh_rate}", &self.refresh_rate.to_string())
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
+        let (width, height): (u16, u16) = if config.displays.scale_size {
+            (self.logical_width, self.logical_height)
+        } else {
+            (self.width, self.height)
+        };
+
+        text.render(|x| match x {
+            "index" => Some(self.index.to_string()),
+            "primary" => Some(if self.primary {"Primary".to_string()} else {String::new()}),
+            "name" => Some(self.name.to_string()),
+            "make" => Some(self.make.to_string()),
+            "model" => Some(self.model.to_string()),
+            "width" => Some(width.to_string()),
+            "height" => Some(height.to_string()),
+            "physical_width" => Some(self.width.to_string()),
+            "physical_height" => Some(self.height.to_string()),
+            "logical_width" => Some(self.logical_width.to_string()),
+            "logical_height" => Some(self.logical_height.to_string()),
+            "refresh_rate" => Some(self.refresh_rate.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -106,10 +171,10 @@ impl Module for DisplayInfo {
             info_flags |= DISPLAYS_INFOFLAG_MODEL;
             info_flags |= DISPLAYS_INFOFLAG_DRM_NAME // DRM name is required for EDID
         }
-        if format.contains("{width}") {
+        if format.contains("{width}") || format.contains("{physical_width}") || format.contains("{logical_width}") {
             info_flags |= DISPLAYS_INFOFLAG_WIDTH
         }
-        if format.contains("{height}") {
+        if format.contains("{height}") || format.contains("{physical_height}") || format.contains("{logical_height}") {
             info_flags |= DISPLAYS_INFOFLAG_HEIGHT
         }
         if format.contains("{refresh_rate}") {
@@ -127,46 +192,134 @@ const DISPLAYS_INFOFLAG_WIDTH: u32 = 8;
 const DISPLAYS_INFOFLAG_HEIGHT: u32 = 16;
 const DISPLAYS_INFOFLAG_REFRESH_RATE: u32 = 32;
 
-pub fn get_displays(config: &Configuration) -> Result<Vec<DisplayInfo>, ModuleError> {
+pub fn get_displays(config: &Configuration, warnings: &mut Vec<ModuleWarning>) -> Result<Vec<DisplayInfo>, DisplayError> {
     // title is tagged onto the end here to account for the title placeholders
-    let info_flags: u32 = DisplayInfo::gen_info_flags(&format!("{}{}", config.displays.format, config.displays.title));
+    let info_flags: u32 = DisplayInfo::get_info_flags(&format!("{}{}", config.displays.format, config.displays.title), config);
 
     // Good news, during my college final deadline hell over the past 2 months, I learned how to
     // use a display server connection!
 
     // Instead of relying on XDG_SESSION_TYPE line Desktop, I simply just check the sockets as it
     // can report any string and break if someone's dumb enough to do that
-    if env::var("WAYLAND_DISPLAY").is_ok() {
-        fetch_wayland(config, info_flags)
+    let mut displays: Vec<DisplayInfo> = if env::var("WAYLAND_DISPLAY").is_ok() {
+        fetch_wayland(info_flags, warnings)?
     } else if env::var("DISPLAY").is_ok() {
-        fetch_xorg(info_flags)
+        fetch_xorg(info_flags, warnings)?
     } else {
-        Err(ModuleError::new("Display", "Could not identify desktop session type.".to_string()))
+        // No display server (e.g TTY, SSH or a seat without a session), but the kernel still knows
+        // what's plugged in
+        fetch_drm(info_flags, warnings)?
+    };
+    if displays.is_empty() {
+        return Err(DisplayError::NoServer("The display server didn't report any connected displays.".to_string()));
+    }
+    displays.iter_mut().for_each(|x| x.calc_logical_size());
+
+    // Only X11 has a real primary display, for everything else it's just the first one
+    if !displays.iter().any(|x| x.primary) {
+        if let Some(r) = displays.first_mut() {
+            r.primary = true;
+        }
+    }
+    for (index, display) in displays.iter_mut().enumerate() {
+        display.index = index as u8 + 1;
+    }
+    if config.displays.primary_only {
+        displays.retain(|x| x.primary);
+    }
+
+    Ok(displays)
+}
+
+fn fetch_drm(info_flags: u32, warnings: &mut Vec<ModuleWarning>) -> Result<Vec<DisplayInfo>, DisplayError> {
+    let dir: ReadDir = match read_dir("/sys/class/drm") {
+        Ok(r) => r,
+        Err(e) => return Err(DisplayError::NoServer(format!("No Wayland or X11 session, and unable to open /sys/class/drm: {}", e))),
+    };
+
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    for entry in dir.flatten() {
+        // Connectors are named e.g card1-HDMI-A-1, anything without the dash is the card itself
+        let dir_name: String = entry.file_name().to_string_lossy().to_string();
+        let drm_name: &str = match dir_name.split_once('-') {
+            Some(r) => r.1,
+            None => continue,
+        };
+
+        match util::file_read(&entry.path().join("status")) {
+            Ok(r) => if r.trim() != "connected" {
+                continue
+            },
+            Err(_) => continue,
+        }
+
+        let mut display: DisplayInfo = DisplayInfo::new();
+        display.name = drm_name.to_string();
+        display.scale = 1;
+        // Without a display server there's no "current" mode, so we use the monitor's preferred
+        // one which the kernel lists first
+        if is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_WIDTH) || is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_HEIGHT) {
+            match util::file_read(&entry.path().join("modes")) {
+                Ok(r) => {
+                    let mode: Option<(u16, u16)> = r.lines().next()
+                        .and_then(|x| x.split_once('x'))
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.trim_end_matches('i').parse().ok()?)));
+                    match mode {
+                        Some((w, h)) => (display.width, display.height) = (w, h),
+                        None => warnings.push(ModuleWarning::new("Display", format!("Unable to parse any modes for monitor {}", drm_name))),
+                    }
+                },
+                Err(e) => warnings.push(ModuleWarning::new("Display", format!("Unable to read modes for monitor {}: {}", drm_name, e))),
+            }
+        }
+        if is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MAKE) || is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MODEL) {
+            match get_edid_makemodel(drm_name) {
+                Ok(r) => (display.make, display.model) = r,
+                Err(e) => warnings.push(ModuleWarning::new("Display", format!("Failed to get make/model for monitor {}: {}", drm_name, e))),
+            };
+        }
+        displays.push(display);
+    }
+
+    if displays.is_empty() {
+        return Err(DisplayError::NoServer("No Wayland or X11 session, and no connected outputs in /sys/class/drm.".to_string()));
     }
+
+    displays.sort_by_key(|a| a.name.to_lowercase());
+    Ok(displays)
 }
 
 
-fn fetch_xorg(info_flags: u32) -> Result<Vec<DisplayInfo>, ModuleError> {
+fn fetch_xorg(info_flags: u32, warnings: &mut Vec<ModuleWarning>) -> Result<Vec<DisplayInfo>, DisplayError> {
     // This has really opened my eyes as to why more pieces of software haven't swapped over to
     // Wayland yet, it's so much more convoluted at times compared to X11
     let (conn, screen_num) = match x11rb::connect(None) {
         Ok(r) => r,
-        Err(e) => return Err(ModuleError::new("Display", format!("Can't connect to X11 server: {}", e))),
+        Err(e) => return Err(DisplayError::NoServer(format!("Can't connect to X11 server: {}", e))),
     };
 
     let screen: &Screen = &x11rb::connection::Connection::setup(&conn).roots[screen_num];
 
     if conn.extension_information(randr::X11_EXTENSION_NAME).is_err() {
-        return Err(ModuleError::new("Display", "X11 compositor doesn't have required 'randr' extension.".to_string()));
+        return Err(DisplayError::ToolMissing("X11 server doesn't have the required 'randr' extension.".to_string()));
     }
 
     let monitors: Vec<MonitorInfo> = match randr::get_monitors(&conn, screen.root, true) {
         Ok(r) => match r.reply() {
             Ok(r) => r.monitors,
-            Err(e) => return Err(ModuleError::new("Display", format!("Failed to get monitors from randr: {}", e))),
+            Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get monitors from randr: {}", e))),
         },
-        Err(e) => return Err(ModuleError::new("Display", format!("Failed to get monitors from randr: {}", e))),
+        Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get monitors from randr: {}", e))),
     };
+    // Used to look up each monitor's output/crtc/mode, only needs fetching the once
+    let resources: GetScreenResourcesCurrentReply = match conn.randr_get_screen_resources_current(screen.root) {
+        Ok(r) => match r.reply() {
+            Ok(r) => r,
+            Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get screen resources: {}", e))),
+        },
+        Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get screen resources: {}", e))),
+    };
+
     let mut displays: Vec<DisplayInfo> = Vec::new();
     for monitor in monitors {
         // Get the DRM name
@@ -174,65 +327,70 @@ fn fetch_xorg(info_flags: u32) -> Result<Vec<DisplayInfo>, ModuleError> {
         if is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_DRM_NAME) {
             drm_name = match xproto::get_atom_name(&conn, monitor.name) {
                 Ok(r) => match r.reply() {
-                    Ok(r) => String::from_utf8(r.name).unwrap(),
-                    Err(e) => return Err(ModuleError::new("Display", format!("Failed to get atomic name for monitor {}: {}", monitor.name, e))),
+                    Ok(r) => String::from_utf8_lossy(&r.name).to_string(),
+                    Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get atomic name for monitor {}: {}", monitor.name, e))),
                 },
-                Err(e) => return Err(ModuleError::new("Display", format!("Failed to get atomic name for monitor {}: {}", monitor.name, e))),
+                Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get atomic name for monitor {}: {}", monitor.name, e))),
             };
         }
         // Find the make/model from the EDID
         let (mut make, mut model): (String, String) = ("Unknown".to_string(), "Unknown".to_string());
         if is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MAKE) || is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MODEL) {
-            (make, model) = match get_edid_makemodel(&drm_name) {
-                Ok(r) => r,
-                Err(e) => return Err(ModuleError::new("Display", format!("Failed to get make/model for monitor {}: {}", monitor.name, e))),
+            match get_edid_makemodel(&drm_name) {
+                Ok(r) => (make, model) = r,
+                Err(e) => warnings.push(ModuleWarning::new("Display", format!("Failed to get make/model for monitor {}: {}", drm_name, e))),
             };
         }
 
-        // Find the screen rotation
-        let resources: GetScreenResourcesCurrentReply = match conn.randr_get_screen_resources_current(screen.root) {
-            Ok(r) => match r.reply() {
-                Ok(r) => r,
-                Err(e) => return Err(ModuleError::new("Display", format!("Failed to get screen resources: {}", e))),
-            },
-            Err(e) => return Err(ModuleError::new("Display", format!("Failed to get screen resources: {}", e))),
-        };
-
+        // Find the active mode + rotation
         let output: u32 = match monitor.outputs.first() {
             Some(r) => *r,
-            None => return Err(ModuleError::new("Display", format!("Monitor {} has no outputs", monitor.name))),
+            None => {
+                // Can happen with monitors made by hand through xrandr --setmonitor
+                warnings.push(ModuleWarning::new("Display", format!("Monitor {} has no outputs, skipping it", drm_name)));
+                continue;
+            },
         };
 
         let output_info: GetOutputInfoReply = match conn.randr_get_output_info(output, resources.config_timestamp) {
             Ok(r) => match r.reply() {
                 Ok(r) => r,
-                Err(e) => return Err(ModuleError::new("Display", format!("Failed to get output info: {}", e))),
+                Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get output info: {}", e))),
             }
-            Err(e) => return Err(ModuleError::new("Display", format!("Failed to get output info: {}", e))),
+            Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get output info: {}", e))),
         };
+        if output_info.crtc == 0 {
+            // Output isn't being driven by anything, so there's no mode to report
+            warnings.push(ModuleWarning::new("Display", format!("Monitor {} has no active crtc, skipping it", drm_name)));
+            continue;
+        }
 
         let crtc: GetCrtcInfoReply = match conn.randr_get_crtc_info(output_info.crtc, resources.config_timestamp) {
             Ok(r) => match r.reply() {
                 Ok(r) => r,
-                Err(e) => return Err(ModuleError::new("Display", format!("Failed to get crtc info: {}", e))),
+                Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get crtc info: {}", e))),
             }
-            Err(e) => return Err(ModuleError::new("Display", format!("Failed to get crtc info: {}", e))),
+            Err(e) => return Err(DisplayError::ParseFailed(format!("Failed to get crtc info: {}", e))),
         };
 
         // And finally
         let mode: &ModeInfo = match resources.modes.iter().find(|x| x.id == crtc.mode) {
             Some(r) => r,
-            None => return Err(ModuleError::new("Display", format!("Failed to find mode {}", crtc.mode))),
+            None => return Err(DisplayError::ParseFailed(format!("Failed to find mode {}", crtc.mode))),
         };
 
         let mut display = DisplayInfo {
+            index: 0,
+            primary: monitor.primary,
             name: drm_name,
             make,
             model,
             width: mode.width,
             height: mode.height,
+            logical_width: 0,
+            logical_height: 0,
             scale: 1,
-            refresh_rate: (mode.dot_clock / (mode.htotal as u32 * mode.vtotal as u32)) as u16,
+            refresh_rate: calc_mode_refresh_rate(mode),
             rotation: match crtc.rotation & 0b111 {
                 Rotation::ROTATE90 => 90,
                 Rotation::ROTATE180 => 180,
@@ -244,10 +402,26 @@ fn fetch_xorg(info_flags: u32) -> Result<Vec<DisplayInfo>, ModuleError> {
         displays.push(display);
     }
 
-    displays.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    displays.sort_by_key(|a| a.name.to_lowercase());
     Ok(displays)
 }
 
+// Same maths xrandr uses to show the refresh rate of a mode
+fn calc_mode_refresh_rate(mode: &ModeInfo) -> u16 {
+    let mut vtotal: f64 = mode.vtotal as f64;
+    if mode.mode_flags.contains(ModeFlag::DOUBLE_SCAN) {
+        vtotal *= 2.0;
+    }
+    if mode.mode_flags.contains(ModeFlag::INTERLACE) {
+        vtotal /= 2.0;
+    }
+    if mode.htotal == 0 || vtotal == 0.0 {
+        return 0;
+    }
+
+    (mode.dot_clock as f64 / (mode.htotal as f64 * vtotal)).round() as u16
+}
+
 fn get_edid_makemodel(drm_name: &str) -> Result<(String, String), String> {
     // Relative to /sys/class/drm
     // Scans the dir until it finds the first directory ending in that drm name
@@ -260,16 +434,11 @@ fn get_edid_makemodel(drm_name: &str) -> Result<(String, String), String> {
 
     let mut make: String = "Unknown".to_string();
     let mut model: String = "Unknown".to_string();
-    for x in dir {
-        if x.is_err() {
-            continue;
-        }
-        let x = x.unwrap();
-        let dir_name = x.file_name();
-        let dir_name = dir_name.to_str().unwrap();
+    for x in dir.flatten() {
+        let dir_name: String = x.file_name().to_string_lossy().to_string();
         if !dir_name.ends_with(drm_name) {
             // Strip the ending -{id} - Can happen with X11 seemingly
-            if !dir_name[..dir_name.len() - 2].ends_with(drm_name) {
+            if !dir_name.get(..dir_name.len().saturating_sub(2)).is_some_and(|x| x.ends_with(drm_name)) {
                 continue;
             }
         }
@@ -284,89 +453,153 @@ fn get_edid_makemodel(drm_name: &str) -> Result<(String, String), String> {
             continue; // Can happen with VM's, ignore it
         }
 
-        // Thanks to these wonderful sources;
-        // - https://glenwing.github.io/docs/VESA-EEDID-A2.pdf
-        // - https://github.com/tuomas56/edid-rs/tree/master?tab=readme-ov-file
-        //
-        // From what I can tell, manufacturer ID is at byte 8+2
-        // Display model name itself is somewhere buried within a display descriptor, which I have
-        // to go through and find
-
-        let manuid: u16 = ((edid_bytes[8] as u16) << 8) | (edid_bytes[9] as u16);
-        // + 64 to convert em to uppercase ascii
-        let char1: char = (((manuid & 0b011111_00000000) >> 10) as u8 + 64) as char;
-        let char2: char = (((manuid & 0b00000011_11100000) >> 5) as u8 + 64) as char;
-        let char3: char = ((manuid & 0b00000000_00011111) as u8 + 64) as char;
-        make = format!("{char1}{char2}{char3}");
-
-        // Now to scower the display descriptors
-        // Byte 48 is where this starts
-        let mut starting_byte: usize = 54;
-        for _ in 0..3 {
-            let is_display: u16 = ((edid_bytes[starting_byte] as u16) << 8) | edid_bytes[starting_byte + 1] as u16;
-            if is_display != 0 {
-                starting_byte += 18;
-                continue;
-            }
+        let (pnp_id, edid_model): (String, String) = match parse_edid(&edid_bytes) {
+            Some(r) => r,
+            None => return Err(format!("EDID for {} is too short to parse", dir_name)),
+        };
+        make = search_pnp_ids(&pnp_id).unwrap_or(pnp_id);
+        model = edid_model;
 
-            // Check the tag
-            let tag: u8 = edid_bytes[starting_byte + 3];
-            if tag != 252 {
-                starting_byte += 18;
-                continue;
-            }
+        break;
+    }
 
-            model = String::new();
-            // Read from byte 5+13 to find the full name
-            for byte in edid_bytes.iter().take(starting_byte + 13).skip(starting_byte + 5) {
-                model.push(*byte as char);
-            }
-            model = model.trim().to_string();
+    Ok((make, model))
+}
+// Returns the manufacturer's PNP ID (e.g SAM) and the monitor's model name
+fn parse_edid(edid_bytes: &[u8]) -> Option<(String, String)> {
+    // Thanks to these wonderful sources;
+    // - https://glenwing.github.io/docs/VESA-EEDID-A2.pdf
+    // - https://github.com/tuomas56/edid-rs/tree/master?tab=readme-ov-file
+    //
+    // From what I can tell, manufacturer ID is at byte 8+2
+    // Display model name itself is somewhere buried within a display descriptor, which I have
+    // to go through and find
+    if edid_bytes.len() < 128 {
+        return None;
+    }
 
-            break;
+    let manuid: u16 = ((edid_bytes[8] as u16) << 8) | (edid_bytes[9] as u16);
+    // + 64 to convert em to uppercase ascii
+    let char1: char = (((manuid & 0b01111100_00000000) >> 10) as u8 + 64) as char;
+    let char2: char = (((manuid & 0b00000011_11100000) >> 5) as u8 + 64) as char;
+    let char3: char = ((manuid & 0b00000000_00011111) as u8 + 64) as char;
+    let pnp_id: String = format!("{char1}{char2}{char3}");
+
+    // Now to scower the display descriptors
+    // Byte 54 is where these start, and there's 4 of them at 18 bytes each
+    let mut model: Option<String> = None;
+    for starting_byte in (54..126).step_by(18) {
+        let is_display: u16 = ((edid_bytes[starting_byte] as u16) << 8) | edid_bytes[starting_byte + 1] as u16;
+        if is_display != 0 {
+            continue;
         }
 
-        if model == "Unknown" {
-            // Now we go for the ID Product Code as a final grasp
-            // This appends the manufacturer on the front as this seems to be the common strategy
-            // for these, tested by my laptop as well as well as this issue's laptop screen
-            // https://github.com/LivacoNew/CrabFetch/issues/21
-            model = format!("{}{:X}", make, edid_bytes[10] as u16 | (edid_bytes[11] as u16) << 8);
+        // Check the tag
+        let tag: u8 = edid_bytes[starting_byte + 3];
+        if tag != 252 {
+            continue;
         }
 
+        // The name is bytes 5-18, terminated with a newline if it's shorter than that
+        let name: String = edid_bytes[starting_byte + 5..starting_byte + 18].iter()
+            .take_while(|x| **x != b'\n')
+            .map(|x| *x as char)
+            .collect();
+        model = Some(name.trim().to_string());
         break;
     }
 
-    Ok((make, model))
+    // Now we go for the ID Product Code as a final grasp
+    // This appends the manufacturer on the front as this seems to be the common strategy
+    // for these, tested by my laptop as well as well as this issue's laptop screen
+    // https://github.com/LivacoNew/CrabFetch/issues/21
+    let model: String = match model {
+        Some(r) if !r.is_empty() => r,
+        _ => format!("{}{:X}", pnp_id, edid_bytes[10] as u16 | (edid_bytes[11] as u16) << 8),
+    };
+
+    Some((pnp_id, model))
+}
+// Turns a PNP ID into the vendor's name, using pnp.ids from hwdata
+fn search_pnp_ids(pnp_id: &str) -> Option<String> {
+    let ids_path: &Path = util::find_first_path_exists(vec![
+        Path::new("/usr/share/hwdata/pnp.ids"),
+        Path::new("/usr/share/misc/pnp.ids")
+    ])?;
+    let contents: String = util::file_read(ids_path).ok()?;
+
+    // Each line is simply "{id}\t{name}"
+    contents.lines()
+        .find_map(|x| x.strip_prefix(pnp_id)?.strip_prefix('\t'))
+        .map(|x| x.trim().to_string())
 }
 
 
 //
 // The Wayland Zone
 //
+// wl_output only knows the integer scale, so for the logical size (what fractional scaling
+// actually ends up at) we need xdg-output
+// wayland-protocols isn't worth pulling in for one tiny protocol, so it's generated here instead
+mod xdg_output {
+    use wayland_client;
+    use wayland_client::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_client::backend as wayland_backend;
+        use wayland_client::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/xdg-output-unstable-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/xdg-output-unstable-v1.xml");
+}
+
 struct WaylandState {
-    complete: bool, // Tells us whether to break out of the event loop or not
-    num_outputs: u8, // How many outputs are waiting to be processed
-    outputs: HashMap<wl_output::WlOutput, DisplayInfo> // The output data as it stands
+    outputs: HashMap<wl_output::WlOutput, DisplayInfo>, // The output data as it stands
+    bound_outputs: Vec<wl_output::WlOutput>,
+    xdg_output_manager: Option<ZxdgOutputManagerV1>,
 }
 impl Dispatch<wl_registry::WlRegistry, ()> for WaylandState {
     fn event(state: &mut Self, reg: &wl_registry::WlRegistry, event: wl_registry::Event, _: &(), _: &Connection, qh: &QueueHandle<WaylandState>,) {
         if let wl_registry::Event::Global {name, interface, version} = event {
             if interface == "wl_output" {
                 // This is what we're looking for, bind to it
-                reg.bind::<wl_output::WlOutput, _, _>(name, version, qh, ());
-                state.num_outputs += 1;
+                // Capped to 4 as that's the newest version wayland-client knows about
+                state.bound_outputs.push(reg.bind::<wl_output::WlOutput, _, _>(name, version.min(4), qh, ()));
+            }
+            if interface == "zxdg_output_manager_v1" {
+                state.xdg_output_manager = Some(reg.bind::<ZxdgOutputManagerV1, _, _>(name, version.min(3), qh, ()));
             }
         }
     }
 }
+impl Dispatch<ZxdgOutputManagerV1, ()> for WaylandState {
+    fn event(_: &mut Self, _: &ZxdgOutputManagerV1, _: zxdg_output_manager_v1::Event, _: &(), _: &Connection, _: &QueueHandle<WaylandState>,) {
+        // Has no events
+    }
+}
+// User data is the wl_output it's describing
+impl Dispatch<ZxdgOutputV1, wl_output::WlOutput> for WaylandState {
+    fn event(state: &mut Self, _: &ZxdgOutputV1, event: zxdg_output_v1::Event, output: &wl_output::WlOutput, _: &Connection, _: &QueueHandle<WaylandState>,) {
+        if let zxdg_output_v1::Event::LogicalSize {width, height} = event {
+            let display: &mut DisplayInfo = state.outputs.entry(output.clone()).or_insert_with(|| {
+                let mut display: DisplayInfo = DisplayInfo::new();
+                display.scale = 1;
+                display
+            });
+            display.logical_width = u16::try_from(width).unwrap_or(0);
+            display.logical_height = u16::try_from(height).unwrap_or(0);
+        }
+    }
+}
 impl Dispatch<wl_output::WlOutput, ()> for WaylandState {
     fn event(state: &mut Self, output: &wl_output::WlOutput, event: wl_output::Event, _: &(), _: &Connection, _qh: &QueueHandle<WaylandState>,) {
-        if !state.outputs.contains_key(output) {
-            state.outputs.insert(output.clone(), DisplayInfo::new());
-        }
-
-        let display: &mut DisplayInfo = state.outputs.get_mut(output).unwrap();
+        let display: &mut DisplayInfo = state.outputs.entry(output.clone()).or_insert_with(|| {
+            let mut display: DisplayInfo = DisplayInfo::new();
+            display.scale = 1; // Scale events aren't sent by older compositors
+            display
+        });
         if let wl_output::Event::Name {name} = &event {
             display.name = name.to_string();
         }
@@ -384,35 +617,32 @@ impl Dispatch<wl_output::WlOutput, ()> for WaylandState {
                 WEnum::Unknown(_) => 0
             };
         }
-        if let wl_output::Event::Mode { width, height, refresh, .. } = &event {
-            display.width = width.to_string().parse::<u16>().unwrap_or(0);
-            display.height = height.to_string().parse::<u16>().unwrap_or(0);
-            display.refresh_rate = (*refresh as f32 / 1000.0).round().to_string().parse::<u16>().unwrap_or(0);
-        }
-
-        if !display.name.is_empty() && display.width != 0 && display.height != 0 && display.refresh_rate != 0 {
-            // We're done, release it
-            output.release();
-            if state.num_outputs == 0 {
-                state.complete = true;
-                return;
+        if let wl_output::Event::Mode { flags, width, height, refresh } = &event {
+            // Compositors can advertise every mode the monitor supports, we only care about the
+            // one it's actually using
+            let current: bool = match flags {
+                WEnum::Value(f) => f.contains(wl_output::Mode::Current),
+                WEnum::Unknown(_) => false,
+            };
+            if current {
+                display.width = u16::try_from(*width).unwrap_or(0);
+                display.height = u16::try_from(*height).unwrap_or(0);
+                display.refresh_rate = (*refresh as f32 / 1000.0).round() as u16;
             }
-            state.num_outputs -= 1;
         }
     }
 }
 // NOTE: Wayland will ignore info flags, as all the events have to be passed through *regardless*
 // It will only use them for make/model with EDID, nothing else
-fn fetch_wayland(config: &Configuration, info_flags: u32) -> Result<Vec<DisplayInfo>, ModuleError> {
+fn fetch_wayland(info_flags: u32, warnings: &mut Vec<ModuleWarning>) -> Result<Vec<DisplayInfo>, DisplayError> {
     let conn: Connection = match Connection::connect_to_env() {
         Ok(r) => r,
         Err(e) => {
-            let msg: &str = match e {
-                ConnectError::NoWaylandLib => "Unable to load the Wayland library.",
-                ConnectError::NoCompositor => "Unable to find a Wayland compositor.",
-                ConnectError::InvalidFd => "Found a Wayland compositor, but the socket contained garbage."
-            };
-            return Err(ModuleError::new("Display", format!("Failed to connect to Wayland compositor: {}", msg)));
+            return Err(match e {
+                ConnectError::NoWaylandLib => DisplayError::ToolMissing("Unable to load the Wayland library.".to_string()),
+                ConnectError::NoCompositor => DisplayError::NoServer("Unable to find a Wayland compositor.".to_string()),
+                ConnectError::InvalidFd => DisplayError::ParseFailed("Found a Wayland compositor, but the socket contained garbage.".to_string()),
+            });
         },
     };
     let display = conn.display();
@@ -422,21 +652,24 @@ fn fetch_wayland(config: &Configuration, info_flags: u32) -> Result<Vec<DisplayI
 
     let _registry = display.get_registry(&qh, ());
     let mut data: WaylandState = WaylandState {
-        complete: false,
-        num_outputs: 0,
-        outputs: HashMap::new()
+        outputs: HashMap::new(),
+        bound_outputs: Vec::new(),
+        xdg_output_manager: None,
     };
 
-    let mut loops: u16 = 0;
-    while !data.complete {
-        match event_queue.roundtrip(&mut data) {
-            Ok(r) => r,
-            Err(e) => return Err(ModuleError::new("Display", format!("Compositor roundtrip returned error: {}", e)))
-        };
-        loops += 1;
-        if loops > 1000 {
-            return Err(ModuleError::new("Display", "Wayland compositor took too long to respond; over 1000 event loops have passed.".to_string()));
-        }
+    // First roundtrip gets us the globals so we can bind the outputs, the second gets every output's
+    // initial state which the compositor sends straight after binding
+    if let Err(e) = event_queue.roundtrip(&mut data) {
+        return Err(DisplayError::ParseFailed(format!("Compositor roundtrip returned error: {}", e)));
+    }
+    match &data.xdg_output_manager {
+        Some(manager) => data.bound_outputs.iter().for_each(|x| {
+            manager.get_xdg_output(x, &qh, x.clone());
+        }),
+        None => warnings.push(ModuleWarning::new("Display", "Compositor doesn't support xdg-output, logical sizes will only account for integer scaling.".to_string())),
+    }
+    if let Err(e) = event_queue.roundtrip(&mut data) {
+        return Err(DisplayError::ParseFailed(format!("Compositor roundtrip returned error: {}", e)));
     }
 
     let mut displays: Vec<DisplayInfo> = data.outputs.into_iter()
@@ -444,10 +677,8 @@ fn fetch_wayland(config: &Configuration, info_flags: u32) -> Result<Vec<DisplayI
         .collect();
 
     displays.iter_mut().for_each(|x| {
+        // xdg-output's logical size already has the rotation applied
         x.calc_rotation();
-        if config.displays.scale_size {
-            x.scale_resolution();
-        }
 
         if is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MAKE) || is_flag_set_u32(info_flags, DISPLAYS_INFOFLAG_MODEL) {
             if util::in_wsl() {
@@ -455,9 +686,9 @@ fn fetch_wayland(config: &Configuration, info_flags: u32) -> Result<Vec<DisplayI
                 x.make = "N/A".to_string();
                 x.model = "N/A".to_string();
             } else {
-                (x.make, x.model) = match get_edid_makemodel(&x.name) {
-                    Ok(r) => r,
-                    Err(_) => return, // We're in a closure, can't return an error
+                match get_edid_makemodel(&x.name) {
+                    Ok(r) => (x.make, x.model) = r,
+                    Err(e) => warnings.push(ModuleWarning::new("Display", format!("Failed to get make/model for monitor {}: {}", x.name, e))),
                 };
             }
         }
@@ -467,6 +698,64 @@ fn fetch_wayland(config: &Configuration, info_flags: u32) -> Result<Vec<DisplayI
         }
     });
 
-    displays.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    displays.sort_by_key(|a| a.name.to_lowercase());
     Ok(displays)
 }
+
+
+mod tests {
+    // Test refresh rates come out the same as xrandr shows them
+    #[test]
+    fn mode_refresh_rate() {
+        let mut mode: super::ModeInfo = super::ModeInfo {
+            dot_clock: 148_500_000,
+            htotal: 2200,
+            vtotal: 1125,
+            ..Default::default()
+        };
+        assert_eq!(super::calc_mode_refresh_rate(&mode), 60);
+
+        // 1080i is half the lines per field
+        mode.dot_clock = 74_250_000;
+        mode.mode_flags = super::ModeFlag::INTERLACE;
+        assert_eq!(super::calc_mode_refresh_rate(&mode), 60);
+
+        mode.dot_clock = 586_580_000;
+        mode.htotal = 2720;
+        mode.vtotal = 1497;
+        mode.mode_flags = super::ModeFlag::from(0u16);
+        assert_eq!(super::calc_mode_refresh_rate(&mode), 144);
+
+        // Broken modes shouldn't divide by zero
+        mode.htotal = 0;
+        assert_eq!(super::calc_mode_refresh_rate(&mode), 0);
+    }
+
+    // Test the make/model are pulled out of an EDID, falling back to the product code
+    #[test]
+    fn parse_edid() {
+        let mut edid: Vec<u8> = vec![0; 128];
+        edid[8] = 0x4C; // SAM
+        edid[9] = 0x2D;
+        edid[10] = 0x34;
+        edid[11] = 0x12;
+        assert_eq!(super::parse_edid(&edid), Some(("SAM".to_string(), "SAM1234".to_string())));
+
+        // Model name descriptor in the second slot
+        edid[54] = 1;
+        edid[75] = 0xFC;
+        edid[77..90].copy_from_slice(b"U2720Q\n      ");
+        assert_eq!(super::parse_edid(&edid), Some(("SAM".to_string(), "U2720Q".to_string())));
+
+        assert_eq!(super::parse_edid(&edid[..64]), None);
+    }
+
+    // Test each kind of display error says what went wrong once it's a module error
+    #[test]
+    fn display_errors() {
+        let error: crate::ModuleError = super::DisplayError::ToolMissing("X11 server doesn't have the required 'randr' extension.".to_string()).into();
+        assert_eq!(error.to_string(), "Module Display failed: Missing display server support: X11 server doesn't have the required 'randr' extension.");
+        let error: crate::ModuleError = super::DisplayError::NoServer("Unable to find a Wayland compositor.".to_string()).into();
+        assert_eq!(error.to_string(), "Module Display failed: No display server: Unable to find a Wayland compositor.");
+    }
+}
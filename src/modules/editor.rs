@@ -1,9 +1,10 @@
 use std::env;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, package_managers::ManagerInfo, util::is_flag_set_u32, versions, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, package_managers::ManagerInfo, util::is_flag_set_u32, versions, ModuleError};
 
+#[derive(Serialize)]
 pub struct EditorInfo {
     name: String,
     path: String,
@@ -11,12 +12,13 @@ pub struct EditorInfo {
 }
 #[derive(Deserialize)]
 pub struct EditorConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub fancy: bool
 }
 impl Module for EditorInfo {
@@ -32,31 +34,37 @@ impl Module for EditorInfo {
         let title_color: &CrabFetchColor = config.editor.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.editor.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.editor.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.editor.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.editor.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.editor.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.editor.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.editor.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.editor.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.editor.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.editor.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.editor.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.editor.title
-            .replace("{name}", "Unknown")
-            .replace("{path}", "Unknown")
-            .replace("{version}", "Unknown");
+        let title: String = config.editor.title.render_values(&[
+            ("name", "Unknown"),
+            ("path", "Unknown"),
+            ("version", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{name}", &self.name)
-            .replace("{path}", &self.path)
-            .replace("{version}", &self.version)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "path" => Some(self.path.to_string()),
+            "version" => Some(self.version.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -86,7 +94,7 @@ const EDITOR_INFOFLAG_VERSION: u32 = 4;
 
 pub fn get_editor(config: &Configuration, package_managers: &ManagerInfo) -> Result<EditorInfo, ModuleError> {
     let mut editor: EditorInfo = EditorInfo::new();
-    let info_flags: u32 = EditorInfo::gen_info_flags(&config.editor.format);
+    let info_flags: u32 = EditorInfo::get_info_flags(config.editor.format.source(), config);
 
     let env_value: String = match env::var("EDITOR") {
         Ok(r) => r,
@@ -105,7 +113,7 @@ pub fn get_editor(config: &Configuration, package_managers: &ManagerInfo) -> Res
         };
     }
     if is_flag_set_u32(info_flags, EDITOR_INFOFLAG_NAME) {
-        editor.name = editor.path.split('/').last().unwrap().to_string();
+        editor.name = editor.path.split('/').next_back().unwrap().to_string();
     }
     if is_flag_set_u32(info_flags, EDITOR_INFOFLAG_VERSION) {
         editor.version = versions::find_version(&editor.path, Some(&editor.name), config.use_version_checksums, package_managers).unwrap_or("Unknown".to_string());
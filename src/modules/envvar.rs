@@ -0,0 +1,80 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, ModuleError};
+
+#[derive(Serialize)]
+pub struct EnvVarInfo {
+    name: String,
+    value: String,
+}
+#[derive(Deserialize)]
+pub struct EnvVarConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+}
+impl Module for EnvVarInfo {
+    fn new() -> EnvVarInfo {
+        EnvVarInfo {
+            name: "Unknown".to_string(),
+            value: "Unknown".to_string(),
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.env.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.env.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.env.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.env.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.env.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.env.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.env.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.env.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.env.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.env.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.env.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.env.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.env.title.render_values(&[
+            ("name", "Unknown"),
+            ("value", "Unknown"),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "value" => Some(self.value.to_string()),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on env module. This should never happen, please make a bug report!")
+    }
+}
+
+// A variable not being set isn't an error, it's just shown as unknown so that fallbacks can be used
+pub fn get_env_var(name: &str) -> Result<EnvVarInfo, ModuleError> {
+    if name.is_empty() {
+        return Err(ModuleError::new("Env", "No variable given, use it like env:<VAR>".to_string()));
+    }
+
+    Ok(EnvVarInfo {
+        name: name.to_string(),
+        value: env::var(name).unwrap_or("Unknown".to_string()),
+    })
+}
@@ -1,29 +1,43 @@
 use core::str;
-use std::{fs::{self, File, ReadDir}, io::{BufRead, BufReader}, path::Path};
+use std::{fs::{self, File, ReadDir}, io::{BufRead, BufReader}, path::{Path, PathBuf}};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, soc, util::{self, is_flag_set_u32}, ModuleError};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct GPUInfo {
+    #[serde(skip)]
     index: Option<u8>,
     vendor: String,
     model: String,
     vram_mb: u32,
+    vram_used_mb: Option<u32>,
+    // The kernel driver, with the module's version if it has one
+    driver: String,
+    // Only for GPUs that are part of a SoC
+    soc_name: String,
 }
 #[derive(Deserialize)]
 pub struct GPUConfiguration {
     pub amd_accuracy: bool,
     pub ignore_disabled_gpus: bool,
+    pub allow_wake: bool,
 
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
     pub use_ibis: Option<bool>,
-    pub format: String
+    pub decimal_places: Option<u32>,
+    pub progress_left_border: Option<String>,
+    pub progress_right_border: Option<String>,
+    pub progress_progress: Option<String>,
+    pub progress_empty: Option<String>,
+    pub progress_target_length: Option<u8>,
+    pub format: FormatTemplate
 }
 
 impl Module for GPUInfo {
@@ -32,7 +46,10 @@ impl Module for GPUInfo {
             index: None,
             vendor: "Unknown".to_string(),
             model: "Unknown".to_string(),
-            vram_mb: 0
+            vram_mb: 0,
+            vram_used_mb: None,
+            driver: "Unknown".to_string(),
+            soc_name: String::new()
         }
     }
 
@@ -40,36 +57,77 @@ impl Module for GPUInfo {
         let title_color: &CrabFetchColor = config.gpu.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.gpu.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.gpu.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.gpu.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.gpu.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.gpu.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.gpu.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
 
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.gpu.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.gpu.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.gpu.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.gpu.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.gpu.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.gpu.title
-            .replace("{vendor}", "Unknown")
-            .replace("{model}", "Unknown")
-            .replace("{vram}", "Unknown")
-            .replace("{index}", "0").to_string();
-
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        let title: String = config.gpu.title.render_values(&[
+            ("vendor", "Unknown"),
+            ("model", "Unknown"),
+            ("vram", "Unknown"),
+            ("vram_used", "Unknown"),
+            ("vram_percent", "Unknown"),
+            ("vram_bar", ""),
+            ("driver", "Unknown"),
+            ("index", "0"),
+            ("soc_name", ""),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let use_ibis: bool = config.gpu.use_ibis.unwrap_or(config.use_ibis);
-
-        text.replace("{vendor}", &self.vendor)
-            .replace("{model}", &self.model)
-            .replace("{vram}", &formatter::auto_format_bytes((self.vram_mb * 1000) as u64, use_ibis, 0))
-            .replace("{index}", &self.index.unwrap_or(0).to_string())
+        let dec_places: u32 = config.gpu.decimal_places.unwrap_or(config.decimal_places);
+
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "vendor" => Some(self.vendor.to_string()),
+            "model" => Some(self.model.to_string()),
+            "vram" => Some(formatter::auto_format_bytes((self.vram_mb * 1000) as u64, use_ibis, 0, config.unit_space)),
+            "vram_used" => Some(match self.vram_used_mb {
+                Some(r) => formatter::auto_format_bytes((r * 1000) as u64, use_ibis, 0, config.unit_space),
+                None => "Unknown".to_string(),
+            }),
+            "vram_percent" => Some(match self.vram_percentage() {
+                Some(r) => formatter::format_percentage(formatter::round(r as f64, dec_places) as f32, config),
+                None => "Unknown".to_string(),
+            }),
+            "vram_bar" => {
+                let percentage: f32 = match self.vram_percentage() {
+                    Some(r) => r,
+                    None => return Some(String::new()),
+                };
+                let left_border: &str = config.gpu.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.gpu.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.gpu.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.gpu.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.gpu.progress_target_length.unwrap_or(config.progress_target_length);
+
+                let mut bar: String = String::new();
+                if config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, percentage, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, percentage, length, config);
+                }
+                Some(bar)
+            },
+            "driver" => Some(self.driver.to_string()),
+            "index" => Some(self.index.unwrap_or(0).to_string()),
+            "soc_name" => Some(self.soc_name.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -87,6 +145,17 @@ impl Module for GPUInfo {
         if format.contains("{vram}") {
             info_flags |= GPU_INFOFLAG_VRAM
         }
+        if format.contains("{soc_name}") {
+            info_flags |= GPU_INFOFLAG_SOC
+        }
+        // The percentage needs the total too
+        if format.contains("{vram_used}") || format.contains("{vram_percent}") || format.contains("{vram_bar}") {
+            info_flags |= GPU_INFOFLAG_VRAM;
+            info_flags |= GPU_INFOFLAG_VRAM_USED
+        }
+        if format.contains("{driver}") {
+            info_flags |= GPU_INFOFLAG_DRIVER
+        }
 
         info_flags
     }
@@ -95,25 +164,37 @@ impl GPUInfo {
     pub fn set_index(&mut self, index: u8) {
         self.index = Some(index);
     }
+
+    fn vram_percentage(&self) -> Option<f32> {
+        if self.vram_mb == 0 {
+            return None;
+        }
+        Some(self.vram_used_mb? as f32 / self.vram_mb as f32 * 100.0)
+    }
 }
 
 const GPU_INFOFLAG_VENDOR: u32 = 1;
 const GPU_INFOFLAG_MODEL: u32 = 2;
 const GPU_INFOFLAG_VRAM: u32 = 4;
+const GPU_INFOFLAG_SOC: u32 = 8;
+const GPU_INFOFLAG_VRAM_USED: u32 = 16;
+const GPU_INFOFLAG_DRIVER: u32 = 32;
 
 pub fn get_gpus(config: &Configuration) -> Result<Vec<GPUInfo>, ModuleError> {
     let mut gpus: Vec<GPUInfo> = Vec::new();
-    let info_flags: u32 = GPUInfo::gen_info_flags(&config.gpu.format);
+    let info_flags: u32 = GPUInfo::get_info_flags(config.gpu.format.source(), config);
 
-    match fill_from_pcisysfile(&mut gpus, config.gpu.amd_accuracy, config.gpu.ignore_disabled_gpus, info_flags) {
-        Ok(_) => {},
-        Err(e) => return Err(e)
+    let pci: Result<(), ModuleError> = fill_from_pcisysfile(&mut gpus, config.gpu.amd_accuracy, config.gpu.ignore_disabled_gpus, config.gpu.allow_wake, info_flags);
+    fill_from_platform(&mut gpus, info_flags);
+    // Plenty of ARM boards don't have PCI at all, so that's only a problem if there's no SoC GPU either
+    if gpus.is_empty() {
+        pci?;
     }
 
     Ok(gpus)
 }
 
-fn fill_from_pcisysfile(gpus: &mut Vec<GPUInfo>, amd_accuracy: bool, ignore_disabled: bool, info_flags: u32) -> Result<(), ModuleError> {
+fn fill_from_pcisysfile(gpus: &mut Vec<GPUInfo>, amd_accuracy: bool, ignore_disabled: bool, allow_wake: bool, info_flags: u32) -> Result<(), ModuleError> {
     // This scans /sys/bus/pci/devices/ and checks the class to find the first display adapter it
     // can
     // This needs expanded at a later date
@@ -127,6 +208,11 @@ fn fill_from_pcisysfile(gpus: &mut Vec<GPUInfo>, amd_accuracy: bool, ignore_disa
     // I'll try to find it in as many places as possible but ultimately can't cover every place. If
     // you know the places, make a PR/Issue and i'll add it in. Fucking hate licences that work
     // like this but oh well.
+    //
+    // class, enable, vendor and device all come from the kernel's own copy of the PCI config, so
+    // they're safe to read even if the GPU's powered down (e.g the dGPU in an Optimus laptop)
+    // Anything from the driver isn't, as reading it can wake the GPU back up, which takes seconds
+    // and drains the battery. Those are only read if the GPU's awake already, unless allow_wake is on
 
     let dir: ReadDir = match fs::read_dir("/sys/bus/pci/devices") {
         Ok(r) => r,
@@ -168,20 +254,17 @@ fn fill_from_pcisysfile(gpus: &mut Vec<GPUInfo>, amd_accuracy: bool, ignore_disa
         }
 
         let mut gpu: GPUInfo = GPUInfo::new();
+        let vendor_id: String = match util::file_read(&d.path().join("vendor")) {
+            Ok(r) => r[2..].trim().to_string(),
+            Err(e) => return Err(ModuleError::new("GPU", format!("Can't read from file: {}", e))),
+        };
         // Vendor/Device
         if is_flag_set_u32(info_flags, GPU_INFOFLAG_MODEL) || is_flag_set_u32(info_flags, GPU_INFOFLAG_VENDOR) {
-            let vendor_id: String = match util::file_read(&d.path().join("vendor")) {
-                Ok(r) => r[2..].trim().to_string(),
-                Err(e) => return Err(ModuleError::new("GPU", format!("Can't read from file: {}", e))),
-            };
             let device_id: String = match util::file_read(&d.path().join("device")) {
                 Ok(r) => r[2..].trim().to_string(),
                 Err(e) => return Err(ModuleError::new("GPU", format!("Can't read from file: {}", e))),
             };
-            let device_data: (String, String) = match search_pci_ids(&vendor_id, &device_id) {
-                Ok(r) => r,
-                Err(e) => return Err(e)
-            };
+            let device_data: (String, String) = search_pci_ids(&vendor_id, &device_id)?;
 
             // TODO: Just directly search AMD, not the first pci.ids file
             gpu.vendor = device_data.0;
@@ -195,18 +278,161 @@ fn fill_from_pcisysfile(gpus: &mut Vec<GPUInfo>, amd_accuracy: bool, ignore_disa
             }
         }
 
+        // Just symlinks in sysfs, so this is fine to do while it's asleep
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_DRIVER) {
+            gpu.driver = kernel_driver(&d.path()).unwrap_or("Unknown".to_string());
+        }
+
+        let awake: bool = allow_wake || !is_runtime_suspended(&d.path());
+
         // Finally, Vram
-        if is_flag_set_u32(info_flags, GPU_INFOFLAG_VRAM) {
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_VRAM) && awake {
             if let Ok(r) = util::file_read(&d.path().join("mem_info_vram_total")) {
                 gpu.vram_mb = (r.trim().parse::<u64>().unwrap() / 1024 / 1024) as u32;
             }
         }
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_VRAM_USED) && awake {
+            if let Ok(r) = util::file_read(&d.path().join("mem_info_vram_used")) {
+                gpu.vram_used_mb = r.trim().parse::<u64>().ok().map(|x| (x / 1024 / 1024) as u32);
+            }
+        }
+        // NVIDIA's driver doesn't put any of it's memory info in sysfs, so nvidia-smi has to be asked
+        // instead. This is slow, so only done when the used memory's actually asked for
+        if vendor_id == "10de" && awake && is_flag_set_u32(info_flags, GPU_INFOFLAG_VRAM_USED) {
+            if let Some((used, total)) = nvidia_smi_vram(&d.file_name().to_string_lossy()) {
+                gpu.vram_used_mb = Some(used);
+                gpu.vram_mb = total;
+            }
+        }
 
         gpus.push(gpu);
     }
 
     Ok(())
 }
+// SoC GPUs aren't on PCI, they're platform devices described by the devicetree
+// This goes through the render nodes rather than the cards, as that skips display controllers
+// which have a card but can't render anything
+fn fill_from_platform(gpus: &mut Vec<GPUInfo>, info_flags: u32) {
+    let dir: ReadDir = match fs::read_dir("/sys/class/drm") {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    let mut devices: Vec<PathBuf> = Vec::new();
+    for entry in dir.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("renderD") {
+            continue;
+        }
+        let device: PathBuf = match fs::canonicalize(entry.path().join("device")) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        // Already done through PCI
+        if fs::read_link(device.join("subsystem")).is_ok_and(|x| x.ends_with("pci")) || devices.contains(&device) {
+            continue;
+        }
+        let compatible: String = match util::file_read(&device.join("of_node/compatible")) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        let mut gpu: GPUInfo = GPUInfo::new();
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_MODEL) || is_flag_set_u32(info_flags, GPU_INFOFLAG_VENDOR) {
+            (gpu.vendor, gpu.model) = match platform_gpu_name(&compatible) {
+                Some(r) => r,
+                // The driver's name is at least something to go off, e.g panfrost
+                None => ("Unknown".to_string(), fs::read_link(device.join("driver")).ok()
+                    .and_then(|x| x.file_name().map(|y| y.to_string_lossy().to_string()))
+                    .unwrap_or("Unknown".to_string())),
+            };
+        }
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_SOC) {
+            gpu.soc_name = soc::soc_name().unwrap_or_default();
+        }
+        if is_flag_set_u32(info_flags, GPU_INFOFLAG_DRIVER) {
+            gpu.driver = kernel_driver(&device).unwrap_or("Unknown".to_string());
+        }
+
+        devices.push(device);
+        gpus.push(gpu);
+    }
+}
+// The vendor and model from the GPU's devicetree compatible strings
+// https://www.kernel.org/doc/Documentation/devicetree/bindings/gpu/
+fn platform_gpu_name(compatible: &str) -> Option<(String, String)> {
+    for entry in compatible.split('\0') {
+        // Older ones are the model, newer ones are the chip ID instead e.g "qcom,adreno-43050a01"
+        if let Some(r) = entry.strip_prefix("qcom,adreno") {
+            let model: &str = r.trim_start_matches('-').split('.').next().unwrap_or("");
+            return Some(("Qualcomm".to_string(), match model.len() {
+                1..=3 => format!("Adreno {}", model),
+                _ => "Adreno".to_string(),
+            }));
+        }
+        // Either the model e.g "t860", or just the architecture e.g "bifrost" or "valhall-csf"
+        if let Some(r) = entry.strip_prefix("arm,mali-") {
+            if r.len() > 1 && r[1..].chars().all(|x| x.is_ascii_digit()) {
+                return Some(("ARM".to_string(), format!("Mali-{}", r.to_uppercase())));
+            }
+            let arch: &str = r.split('-').next().unwrap_or(r);
+            let mut chars = arch.chars();
+            let arch: String = chars.next().map(|x| x.to_uppercase().chain(chars).collect()).unwrap_or_default();
+            return Some(("ARM".to_string(), format!("Mali ({})", arch)));
+        }
+        if entry.starts_with("brcm,") && (entry.ends_with("-v3d") || entry.ends_with("-vc4")) {
+            let model: &str = match entry {
+                "brcm,2712-v3d" => "VideoCore VII",
+                "brcm,2711-v3d" => "VideoCore VI",
+                _ => "VideoCore IV",
+            };
+            return Some(("Broadcom".to_string(), model.to_string()));
+        }
+        if let Some(r) = entry.strip_prefix("apple,agx-g") {
+            return Some(("Apple".to_string(), format!("AGX G{}", r.to_uppercase())));
+        }
+        if let Some(r) = ["nvidia,gk20a", "nvidia,gm20b", "nvidia,gp10b", "nvidia,gv11b"].iter().find(|x| **x == entry) {
+            return Some(("NVIDIA".to_string(), r[7..].to_uppercase()));
+        }
+        if entry.starts_with("vivante,gc") {
+            return Some(("Vivante".to_string(), "GC".to_string()));
+        }
+        if entry.starts_with("img,img-") || entry.starts_with("img,powervr-") {
+            return Some(("Imagination".to_string(), "PowerVR".to_string()));
+        }
+    }
+
+    None
+}
+// The name of the driver bound to the device, e.g amdgpu
+// Out of tree modules like NVIDIA's have a version too, which gets added on, e.g nvidia 550.67
+fn kernel_driver(device: &Path) -> Option<String> {
+    let driver: PathBuf = device.join("driver");
+    let name: String = fs::read_link(&driver).ok()?.file_name()?.to_string_lossy().to_string();
+    match util::file_read(&driver.join("module/version")) {
+        Ok(r) if !r.trim().is_empty() => Some(format!("{} {}", name, r.trim())),
+        _ => Some(name),
+    }
+}
+
+// Used and total VRAM in MiB, for the GPU at the PCI address
+// Given a timeout so a stuck driver can't hold up the whole fetch
+fn nvidia_smi_vram(pci_address: &str) -> Option<(u32, u32)> {
+    let output: String = util::run_command("nvidia-smi", &["--query-gpu=memory.used,memory.total", "--format=csv,noheader,nounits", "-i", pci_address], 500).ok()?;
+
+    parse_nvidia_smi_vram(&output)
+}
+// e.g "1024, 8192"
+fn parse_nvidia_smi_vram(output: &str) -> Option<(u32, u32)> {
+    let (used, total) = output.lines().next()?.split_once(',')?;
+    Some((used.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+// Whether runtime power management has put the device to sleep
+// Devices without runtime PM don't have this, and are always awake
+fn is_runtime_suspended(device: &Path) -> bool {
+    util::file_read(&device.join("power/runtime_status")).is_ok_and(|x| x.trim() == "suspended")
+}
+
 fn search_pci_ids(vendor: &str, device: &str) -> Result<(String, String), ModuleError> {
     // Search all known locations
     let ids_path: &Path = match util::find_first_path_exists(vec![
@@ -304,3 +530,49 @@ fn search_amd_model(device: &str) -> Result<Option<String>, ModuleError> {
 
     Ok(Some(device_result.to_string()))
 }
+
+
+mod tests {
+    // Test nvidia-smi's memory output is read, and anything else is ignored
+    #[test]
+    fn nvidia_smi_vram() {
+        assert_eq!(super::parse_nvidia_smi_vram("1024, 8192\n"), Some((1024, 8192)));
+        assert_eq!(super::parse_nvidia_smi_vram("No devices were found\n"), None);
+        assert_eq!(super::parse_nvidia_smi_vram(""), None);
+    }
+
+    // Test the driver's name comes from it's symlink, with the module version added if there is one
+    #[test]
+    fn kernel_driver() {
+        use std::{fs, os::unix::fs::symlink, path::PathBuf};
+        let root: PathBuf = std::env::temp_dir().join(format!("crabfetch_test_gpu_driver_{}", std::process::id()));
+        fs::create_dir_all(root.join("drivers/nvidia")).unwrap();
+        fs::create_dir_all(root.join("drivers/amdgpu")).unwrap();
+        fs::create_dir_all(root.join("module/nvidia")).unwrap();
+        fs::create_dir_all(root.join("devices/a")).unwrap();
+        fs::create_dir_all(root.join("devices/b")).unwrap();
+        fs::create_dir_all(root.join("devices/c")).unwrap();
+        fs::write(root.join("module/nvidia/version"), "550.67\n").unwrap();
+        symlink("../../module/nvidia", root.join("drivers/nvidia/module")).unwrap();
+        symlink("../../drivers/nvidia", root.join("devices/a/driver")).unwrap();
+        symlink("../../drivers/amdgpu", root.join("devices/b/driver")).unwrap();
+
+        assert_eq!(super::kernel_driver(&root.join("devices/a")), Some("nvidia 550.67".to_string()));
+        assert_eq!(super::kernel_driver(&root.join("devices/b")), Some("amdgpu".to_string()));
+        assert_eq!(super::kernel_driver(&root.join("devices/c")), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    // Test SoC GPUs are named from their devicetree compatible strings
+    #[test]
+    fn platform_gpu_name() {
+        let name = |x: &str| super::platform_gpu_name(x).map(|y| format!("{} {}", y.0, y.1));
+        assert_eq!(name("rockchip,rk3588-mali\0arm,mali-valhall-csf\0"), Some("ARM Mali (Valhall)".to_string()));
+        assert_eq!(name("rockchip,rk3399-mali\0arm,mali-t860\0"), Some("ARM Mali-T860".to_string()));
+        assert_eq!(name("qcom,adreno-740.1\0qcom,adreno\0"), Some("Qualcomm Adreno 740".to_string()));
+        assert_eq!(name("qcom,adreno-43050a01\0qcom,adreno\0"), Some("Qualcomm Adreno".to_string()));
+        assert_eq!(name("brcm,2712-v3d\0"), Some("Broadcom VideoCore VII".to_string()));
+        assert_eq!(name("apple,agx-t8103\0apple,agx-g13g\0"), Some("Apple AGX G13G".to_string()));
+        assert_eq!(name("vendor,something\0"), None);
+    }
+}
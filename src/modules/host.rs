@@ -1,27 +1,29 @@
 use core::str;
-use std::path::Path;
+use std::{fs::{self, ReadDir}, path::{Path, PathBuf}};
 
 #[cfg(feature = "android")]
 use {android_system_properties::AndroidSystemProperties, std::env};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::{self, Module}, util::{self, is_flag_set_u32}, ModuleError};
 
+#[derive(Serialize)]
 pub struct HostInfo {
     host: String,
     chassis: String
 }
 #[derive(Deserialize)]
 pub struct HostConfiguration {
-    pub title: String,
-    pub format: String,
+    pub title: FormatTemplate,
+    pub format: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
     pub newline_chassis: bool,
     pub chassis_title: String,
-    pub chassis_format: String
+    pub chassis_format: FormatTemplate
 }
 impl Module for HostInfo {
     fn new() -> HostInfo {
@@ -35,29 +37,37 @@ impl Module for HostInfo {
         let title_color: &CrabFetchColor = config.host.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.host.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.host.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.host.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.host.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.host.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.host.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.host.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.host.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.host.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.host.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.host.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.host.title
-            .replace("{host}", "Unknown")
-            .replace("{chassis}", "Unknown");
+        let title: String = config.host.title.render_values(&[
+            ("host", "Unknown"),
+            ("chassis", "Unknown"),
+            ("chassis_icon", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{host}", &self.host)
-            .replace("{chassis}", &self.chassis)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "host" => Some(self.host.to_string()),
+            "chassis" => Some(self.chassis.to_string()),
+            "chassis_icon" => Some(chassis_icon(&self.chassis).to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -67,7 +77,7 @@ impl Module for HostInfo {
         if format.contains("{host}") {
             info_flags |= HOST_INFOFLAG_HOST;
         }
-        if format.contains("{chassis}") {
+        if format.contains("{chassis}") || format.contains("{chassis_icon}") {
             info_flags |= HOST_INFOFLAG_CHASSIS;
         }
 
@@ -80,14 +90,70 @@ impl HostInfo {
         let title_color: &CrabFetchColor = config.host.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.host.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.host.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.host.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.host.separator.as_ref().unwrap_or(&config.separator);
 
-        let value: String = self.replace_color_placeholders(&config.host.chassis_format.replace("{chassis}", &self.chassis), config);
+        let value: String = self.replace_color_placeholders(&config.host.chassis_format.render(|x| match x {
+            "chassis" => Some(self.chassis.to_string()),
+            "chassis_icon" => Some(chassis_icon(&self.chassis).to_string()),
+            _ => None,
+        }), config);
 
-        Self::default_style(config, &config.host.chassis_title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &config.host.chassis_title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
 }
 
+// A Nerd Font icon for the chassis, or nothing if there isn't one that fits
+fn chassis_icon(chassis: &str) -> &'static str {
+    match chassis {
+        "Desktop" | "Low Profile Desktop" | "Pizza Box" | "Mini Tower" | "Tower" | "All In One"
+            | "Space-saving" | "Lunch Box" | "Sealed-case PC" => "\u{f108}",
+        "Portable" | "Laptop" | "Notebook" | "Sub Notebook" | "Convertible" | "Detachable" => "\u{f109}",
+        "Tablet" => "\u{f10a}",
+        "Hand Held" => "\u{f10b}",
+        "Main Server Chassis" | "Rack Mount Chassis" | "Multi-system" | "Blade" | "Blade Enclosing" => "\u{f233}",
+        "IoT Gateway" | "Embedded PC" | "Mini PC" | "Stick PC" | "CompactPCI" | "AdvancedTCA" => "\u{f2db}",
+        _ => "",
+    }
+}
+
+// For when there's no DMI to ask, e.g on ARM boards and phones
+// Newer device trees say what they are, otherwise a battery means it's something portable and a
+// device tree without one is most likely a board like a raspberry pi
+fn guess_chassis(device_tree_chassis: Option<&str>, has_battery: bool, has_device_tree: bool) -> &'static str {
+    // https://github.com/devicetree-org/devicetree-specification/blob/main/source/chapter3-devicenodes.rst#root-node
+    match device_tree_chassis {
+        Some("desktop") => return "Desktop",
+        Some("laptop") => return "Laptop",
+        Some("convertible") => return "Convertible",
+        Some("server") => return "Main Server Chassis",
+        Some("tablet") => return "Tablet",
+        Some("handset") | Some("watch") => return "Hand Held",
+        Some("embedded") => return "Embedded PC",
+        _ => {},
+    }
+
+    if has_battery {
+        "Portable"
+    } else if has_device_tree {
+        "Embedded PC"
+    } else {
+        "Unknown"
+    }
+}
+// Peripherals (e.g wireless mice) show up here too, but they have a scope of "Device"
+fn has_battery() -> bool {
+    let dir: ReadDir = match fs::read_dir("/sys/class/power_supply/") {
+        Ok(r) => r,
+        Err(_) => return false,
+    };
+    dir.filter_map(|x| x.ok()).any(|x| {
+        let path: PathBuf = x.path();
+        util::file_read(&path.join("type")).is_ok_and(|y| y.trim() == "Battery")
+            && util::file_read(&path.join("scope")).map_or(true, |y| y.trim() != "Device")
+    })
+}
+
 const HOST_INFOFLAG_HOST: u32 = 1;
 const HOST_INFOFLAG_CHASSIS: u32 = 2;
 
@@ -96,9 +162,9 @@ pub fn get_host(config: &Configuration) -> Result<HostInfo, ModuleError> {
 
     let mut format: String = config.host.format.to_string();
     if config.host.newline_chassis {
-        format.push_str(&config.host.chassis_format);
+        format.push_str(config.host.chassis_format.source());
     }
-    let info_flags: u32 = HostInfo::gen_info_flags(&format);
+    let info_flags: u32 = HostInfo::get_info_flags(&format, config);
 
     // Android 
     #[cfg(feature = "android")]
@@ -138,6 +204,10 @@ pub fn get_host(config: &Configuration) -> Result<HostInfo, ModuleError> {
 
         host.host = match util::file_read(chosen_path) {
             Ok(r) => r.trim().to_string(),
+            Err(e) if util::is_permission_denied(chosen_path) => {
+                module::hint_permission("Host", "{host}", "root to read the DMI info");
+                return Err(ModuleError::new("Host", format!("Can't read from {} - {}", chosen_path.display(), e)));
+            },
             Err(e) => return Err(ModuleError::new("Host", format!("Can't read from {} - {}", chosen_path.display(), e))),
         };
     }
@@ -146,7 +216,12 @@ pub fn get_host(config: &Configuration) -> Result<HostInfo, ModuleError> {
     if is_flag_set_u32(info_flags, HOST_INFOFLAG_CHASSIS) {
         let p: &Path = Path::new("/sys/devices/virtual/dmi/id/chassis_type");
         // The file may not exist on some stuff, e.g raspberry pi's don't have it
-        if p.exists() {
+        if !p.exists() {
+            // Device tree strings end in a null
+            let device_tree_chassis: Option<String> = util::file_read(Path::new("/sys/firmware/devicetree/base/chassis-type")).ok()
+                .map(|x| x.trim_end_matches('\0').trim().to_string());
+            host.chassis = guess_chassis(device_tree_chassis.as_deref(), has_battery(), Path::new("/sys/firmware/devicetree/base").exists()).to_string();
+        } else {
             host.chassis = match util::file_read(Path::new("/sys/devices/virtual/dmi/id/chassis_type")) {
                 // http://git.savannah.nongnu.org/cgit/dmidecode.git/tree/dmidecode.c?id=d5af407ae937b0ab26b72e8c250112d5a8543a63#n602
                 // I have no idea if this is meant to be hex or decimal, so I'm taking a gamble that it's
@@ -190,10 +265,30 @@ pub fn get_host(config: &Configuration) -> Result<HostInfo, ModuleError> {
                     "36" => "Stick PC".to_string(),
                     _ => "Unknown".to_string()
                 },
-                Err(e) => return Err(ModuleError::new("Host", format!("Can't read from /sys/devices/virtual/dmi/id/chassis_type - {}", e))),
+                Err(e) => {
+                    if util::is_permission_denied(p) {
+                        module::hint_permission("Host", "{chassis}", "root to read the DMI info");
+                    }
+                    return Err(ModuleError::new("Host", format!("Can't read from /sys/devices/virtual/dmi/id/chassis_type - {}", e)))
+                },
             };
         }
     }
 
     Ok(host)
 }
+
+
+mod tests {
+    // Test the chassis is guessed from the device tree first, then from whether there's a battery
+    #[test]
+    fn guess_chassis() {
+        assert_eq!(super::guess_chassis(Some("handset"), true, true), "Hand Held");
+        assert_eq!(super::guess_chassis(Some("embedded"), false, true), "Embedded PC");
+        assert_eq!(super::guess_chassis(None, true, true), "Portable");
+        assert_eq!(super::guess_chassis(None, false, true), "Embedded PC");
+        assert_eq!(super::guess_chassis(Some("something-new"), false, false), "Unknown");
+        assert_eq!(super::chassis_icon("Embedded PC"), "\u{f2db}");
+        assert_eq!(super::chassis_icon("Unknown"), "");
+    }
+}
@@ -1,22 +1,24 @@
 use core::str;
 use std::{env, process::Command};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, syscalls::SyscallCache, util::is_flag_set_u32, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, syscalls::SyscallCache, util::is_flag_set_u32, ModuleError};
 
+#[derive(Serialize)]
 pub struct HostnameInfo {
     username: String,
     hostname: String,
 }
 #[derive(Deserialize)]
 pub struct HostnameConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String
+    pub format: FormatTemplate
 }
 impl Module for HostnameInfo {
     fn new() -> HostnameInfo {
@@ -29,30 +31,35 @@ impl Module for HostnameInfo {
         let title_color: &CrabFetchColor = config.hostname.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.hostname.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.hostname.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.hostname.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.hostname.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.hostname.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.hostname.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.hostname.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.hostname.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.hostname.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.hostname.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.hostname.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.hostname.title
-            .replace("{username}", "Unknown")
-            .replace("{hostname}", "Unknown");
+        let title: String = config.hostname.title.render_values(&[
+            ("username", "Unknown"),
+            ("hostname", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{username}", &self.username)
-            .replace("{hostname}", &self.hostname)
-            .to_string()
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "username" => Some(self.username.to_string()),
+            "hostname" => Some(self.hostname.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -75,7 +82,7 @@ const HOSTNAME_INFOFLAG_USERNAME: u32 = 2;
 
 pub fn get_hostname(config: &Configuration, syscall_cache: &mut SyscallCache) -> Result<HostnameInfo, ModuleError> {
     let mut hostname: HostnameInfo = HostnameInfo::new();
-    let info_flags: u32 = HostnameInfo::gen_info_flags(&config.hostname.format);
+    let info_flags: u32 = HostnameInfo::get_info_flags(config.hostname.format.source(), config);
 
     // We'll try the safe way first, then the backup way
     // This is purely cus reading that env variable is faster
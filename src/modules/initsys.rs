@@ -1,10 +1,11 @@
 use core::str;
 use std::fs;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::is_flag_set_u32, versions, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::is_flag_set_u32, versions, ModuleError};
 
+#[derive(Serialize)]
 pub struct InitSystemInfo {
     name: String,
     path: String,
@@ -12,11 +13,12 @@ pub struct InitSystemInfo {
 }
 #[derive(Deserialize)]
 pub struct InitSystemConfiguration {
-    pub title: String,
-    pub format: String,
+    pub title: FormatTemplate,
+    pub format: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
 }
 impl Module for InitSystemInfo {
@@ -32,31 +34,37 @@ impl Module for InitSystemInfo {
         let title_color: &CrabFetchColor = config.initsys.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.initsys.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.initsys.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.initsys.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.initsys.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.initsys.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.initsys.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.initsys.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.initsys.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.initsys.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.initsys.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.initsys.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.initsys.title
-            .replace("{name}", "Unknown")
-            .replace("{path}", "Unknown")
-            .replace("{version}", "Unknown");
+        let title: String = config.initsys.title.render_values(&[
+            ("name", "Unknown"),
+            ("path", "Unknown"),
+            ("version", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{name}", &self.name)
-            .replace("{path}", &self.path)
-            .replace("{version}", &self.version)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "path" => Some(self.path.to_string()),
+            "version" => Some(self.version.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -86,7 +94,7 @@ const INITSYS_INFOFLAG_VERSION: u32 = 4;
 
 pub fn get_init_system(config: &Configuration, package_managers: &ManagerInfo) -> Result<InitSystemInfo, ModuleError> {
     let mut initsys: InitSystemInfo = InitSystemInfo::new();
-    let info_flags: u32 = InitSystemInfo::gen_info_flags(&config.initsys.format);
+    let info_flags: u32 = InitSystemInfo::get_info_flags(config.initsys.format.source(), config);
 
     // Reads the /cmdline of process 1, either using that or redirecting to it's symlink 
     // Thanks to https://superuser.com/a/1183819
@@ -104,7 +112,7 @@ pub fn get_init_system(config: &Configuration, package_managers: &ManagerInfo) -
     }
     if is_flag_set_u32(info_flags, INITSYS_INFOFLAG_NAME) {
         initsys.name = initsys.path.split('/')
-            .last()
+            .next_back()
             .unwrap()
             .to_string();
     }
@@ -0,0 +1,157 @@
+use std::{env, io::{Read, Write}, os::unix::net::UnixStream, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util::is_flag_set_u32, ModuleError};
+
+#[derive(Serialize)]
+pub struct KeysInfo {
+    ssh_agent: bool,
+    ssh_keys: u32,
+    gpg_agent: bool,
+}
+#[derive(Deserialize)]
+pub struct KeysConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+}
+impl Module for KeysInfo {
+    fn new() -> KeysInfo {
+        KeysInfo {
+            ssh_agent: false,
+            ssh_keys: 0,
+            gpg_agent: false,
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.keys.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.keys.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.keys.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.keys.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.keys.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.keys.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.keys.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.keys.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.keys.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.keys.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.keys.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.keys.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.keys.title.render_values(&[
+            ("ssh_agent", "Unknown"),
+            ("ssh_keys", "Unknown"),
+            ("gpg", "Unknown"),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        let running = |x: bool| if x { "Running" } else { "Not running" }.to_string();
+        text.render(|x| match x {
+            "ssh_agent" => Some(running(self.ssh_agent)),
+            "ssh_keys" => Some(self.ssh_keys.to_string()),
+            "gpg" => Some(running(self.gpg_agent)),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(format: &str) -> u32 {
+        let mut info_flags: u32 = 0;
+
+        if format.contains("{ssh_agent}") || format.contains("{ssh_keys}") {
+            info_flags |= KEYS_INFOFLAG_SSH;
+        }
+        if format.contains("{gpg}") {
+            info_flags |= KEYS_INFOFLAG_GPG;
+        }
+
+        info_flags
+    }
+}
+
+const KEYS_INFOFLAG_SSH: u32 = 1;
+const KEYS_INFOFLAG_GPG: u32 = 2;
+
+pub fn get_keys(config: &Configuration) -> Result<KeysInfo, ModuleError> {
+    let mut keys: KeysInfo = KeysInfo::new();
+    let info_flags: u32 = KeysInfo::get_info_flags(config.keys.format.source(), config);
+
+    // Neither of these not running is an error, that's the whole point of the module
+    if is_flag_set_u32(info_flags, KEYS_INFOFLAG_SSH) {
+        if let Some(count) = get_ssh_key_count() {
+            keys.ssh_agent = true;
+            keys.ssh_keys = count;
+        }
+    }
+    if is_flag_set_u32(info_flags, KEYS_INFOFLAG_GPG) {
+        keys.gpg_agent = gpg_agent_socket().is_some_and(|x| UnixStream::connect(x).is_ok());
+    }
+
+    Ok(keys)
+}
+
+// Asks the agent for it's identities, the same as `ssh-add -l` does
+// https://datatracker.ietf.org/doc/html/draft-miller-ssh-agent#section-4.4
+fn get_ssh_key_count() -> Option<u32> {
+    let socket: String = env::var("SSH_AUTH_SOCK").ok()?;
+    let mut stream: UnixStream = UnixStream::connect(socket).ok()?;
+    // If the agent is stuck (e.g waiting on a hardware key), we don't want to be stuck with it
+    stream.set_read_timeout(Some(Duration::from_millis(250))).ok()?;
+    stream.set_write_timeout(Some(Duration::from_millis(250))).ok()?;
+
+    // Length of 1, then SSH_AGENTC_REQUEST_IDENTITIES
+    stream.write_all(&[0, 0, 0, 1, 11]).ok()?;
+    // We only need the start of the reply, the keys themselves come after
+    let mut reply: [u8; 9] = [0; 9];
+    stream.read_exact(&mut reply).ok()?;
+
+    parse_identities_answer(&reply)
+}
+// Length, then SSH_AGENT_IDENTITIES_ANSWER, then the number of keys
+fn parse_identities_answer(reply: &[u8; 9]) -> Option<u32> {
+    if reply[4] != 12 {
+        return None;
+    }
+
+    Some(u32::from_be_bytes([reply[5], reply[6], reply[7], reply[8]]))
+}
+
+// Where gpg-agent puts it's socket, newer versions keep it in the runtime dir rather than the
+// GnuPG home
+fn gpg_agent_socket() -> Option<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    if let Ok(home) = env::var("GNUPGHOME") {
+        paths.push(PathBuf::from(home).join("S.gpg-agent"));
+    }
+    if let Ok(runtime) = env::var("XDG_RUNTIME_DIR") {
+        paths.push(PathBuf::from(runtime).join("gnupg/S.gpg-agent"));
+    }
+    if let Ok(home) = env::var("HOME") {
+        paths.push(PathBuf::from(home).join(".gnupg/S.gpg-agent"));
+    }
+
+    paths.into_iter().find(|x| x.exists())
+}
+
+
+mod tests {
+    // Test the key count is read out of the agent's reply, and anything else is ignored
+    #[test]
+    fn identities_answer() {
+        assert_eq!(super::parse_identities_answer(&[0, 0, 1, 0, 12, 0, 0, 0, 2]), Some(2));
+        // SSH_AGENT_FAILURE
+        assert_eq!(super::parse_identities_answer(&[0, 0, 0, 1, 5, 0, 0, 0, 0]), None);
+    }
+}
@@ -1,21 +1,23 @@
 use std::env;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{formatter::CrabFetchColor, config_manager::Configuration, module::Module, ModuleError};
+use crate::{formatter::{CrabFetchColor, FormatTemplate}, config_manager::Configuration, module::Module, ModuleError};
 
+#[derive(Serialize)]
 pub struct LocaleInfo {
     language: String,
     encoding: String,
 }
 #[derive(Deserialize)]
 pub struct LocaleConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String
+    pub format: FormatTemplate
 }
 impl Module for LocaleInfo {
     fn new() -> LocaleInfo {
@@ -29,29 +31,35 @@ impl Module for LocaleInfo {
         let title_color: &CrabFetchColor = config.locale.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.locale.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.locale.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.locale.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.locale.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.locale.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.locale.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.locale.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.locale.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.locale.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.locale.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.locale.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.locale.title
-            .replace("{language}", "Unknown")
-            .replace("{encoding}", "Unknown");
+        let title: String = config.locale.title.render_values(&[
+            ("language", "Unknown"),
+            ("encoding", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{language}", &self.language)
-            .replace("{encoding}", &self.encoding)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "language" => Some(self.language.to_string()),
+            "encoding" => Some(self.encoding.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
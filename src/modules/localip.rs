@@ -1,27 +1,38 @@
-use std::{fs::{self, ReadDir}, mem, net::{IpAddr, Ipv4Addr, Ipv6Addr}};
+use std::{ffi::CStr, fs::{self, ReadDir}, io::{Read, Write}, mem, net::{IpAddr, Ipv4Addr, Ipv6Addr}, os::unix::net::UnixStream, path::Path, time::Duration};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, util, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util, ModuleError};
 
+#[derive(Serialize)]
 pub struct LocalIPInfo {
     interface: String,
     ip_addr: String,
+    hostname: String,
+    scope: String,
+    overlay: String,
 }
 #[derive(Deserialize)]
 pub struct LocalIPConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String
+    pub format: FormatTemplate,
+    pub resolve_hostnames: bool,
+    pub show_temporary: bool,
+    pub tailscale_node_name: bool,
 }
 impl Module for LocalIPInfo {
     fn new() -> LocalIPInfo {
         LocalIPInfo {
             interface: "Unknown".to_string(),
-            ip_addr: "Unknown".to_string()
+            ip_addr: "Unknown".to_string(),
+            hostname: "Unknown".to_string(),
+            scope: "Unknown".to_string(),
+            overlay: "Unknown".to_string(),
         }
     }
 
@@ -29,29 +40,41 @@ impl Module for LocalIPInfo {
         let title_color: &CrabFetchColor = config.localip.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.localip.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.localip.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.localip.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.localip.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.localip.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.localip.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.localip.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.localip.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.localip.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.localip.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.localip.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.locale.title
-            .replace("{interface}", "Unknown")
-            .replace("{addr}", "Unknown");
+        let title: String = config.localip.title.render_values(&[
+            ("interface", "Unknown"),
+            ("addr", "Unknown"),
+            ("hostname", "Unknown"),
+            ("scope", "Unknown"),
+            ("overlay", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{interface}", &self.interface)
-            .replace("{addr}", &self.ip_addr)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "interface" => Some(self.interface.to_string()),
+            "addr" => Some(self.ip_addr.to_string()),
+            "hostname" => Some(self.hostname.to_string()),
+            "scope" => Some(self.scope.to_string()),
+            "overlay" => Some(self.overlay.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
@@ -59,10 +82,30 @@ impl Module for LocalIPInfo {
     }
 }
 
-pub fn get_local_ips() -> Result<Vec<LocalIPInfo>, ModuleError> {
+pub fn get_local_ips(config: &Configuration) -> Result<Vec<LocalIPInfo>, ModuleError> {
     // no info flags here as it's all from the same source
     let mut addrs: Vec<LocalIPInfo> = Vec::new();
 
+    // Reverse lookups can take a while, so only do them if we really need them
+    // JSON wants everything, regardless of the format
+    let resolve_hostnames: bool = config.localip.resolve_hostnames
+        && (config.detect_all_info || [&config.localip.format, &config.localip.title].iter().any(|x| x.placeholder_names().iter().any(|y| y == "hostname")));
+
+    // getifaddrs doesn't tell us which IPv6 addresses are temporary, so that comes from here
+    let temporary_v6: Vec<Ipv6Addr> = match config.localip.show_temporary {
+        true => Vec::new(),
+        false => match util::file_read(Path::new("/proc/net/if_inet6")) {
+            Ok(r) => parse_temporary_v6(&r),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    // Only asked for once, as it's the same for every address on the interface
+    let tailscale_node_name: Option<String> = match config.localip.tailscale_node_name {
+        true => get_tailscale_node_name(),
+        false => None,
+    };
+
     // First, scan /sys/devices/virtual/net and find any known virtual devices
     // Credit to https://stackoverflow.com/a/52561720 for the initial explanation on how the kernel
     // handles this stuff
@@ -98,8 +141,9 @@ pub fn get_local_ips() -> Result<Vec<LocalIPInfo>, ModuleError> {
                 Err(e) => return Err(ModuleError::new("LocalIP", format!("Failed to convert interface name into Rust string: {}", e)))
             };
 
-            // Ignore any virtual devices
-            if virt_interfaces.contains(&interface_name) {
+            // Ignore any virtual devices, apart from overlay networks as people want to see those
+            let overlay: Option<&str> = get_overlay(&interface_name);
+            if virt_interfaces.contains(&interface_name) && overlay.is_none() {
                 if ifaddrs.ifa_next.is_null() || inf_loop_protection > 25 {
                     break;
                 }
@@ -107,33 +151,59 @@ pub fn get_local_ips() -> Result<Vec<LocalIPInfo>, ModuleError> {
                 continue;
             }
 
+            let overlay: String = match (overlay, &tailscale_node_name) {
+                (Some("Tailscale"), Some(node)) => format!("Tailscale ({})", node),
+                (Some(r), _) => r.to_string(),
+                (None, _) => String::new(),
+            };
+
+            // Point-to-point interfaces (e.g WireGuard) have no link-layer address, so their
+            // AF_PACKET entry comes back without one
+            let family: i32 = match address_family(ifaddrs.ifa_addr) {
+                Some(r) => r,
+                None => {
+                    if ifaddrs.ifa_next.is_null() || inf_loop_protection > 25 {
+                        break;
+                    }
+                    ifaddrs = *ifaddrs.ifa_next;
+                    continue;
+                }
+            };
+
             // the spooky part, at least for me
-            if (*ifaddrs.ifa_addr).sa_family as i32 == libc::AF_INET {
+            if family == libc::AF_INET {
                 // ipv4
                 let addr: libc::sockaddr_in = *((ifaddrs.ifa_addr) as *mut libc::sockaddr_in);
                 let ipaddr: Ipv4Addr = Ipv4Addr::from((addr).sin_addr.s_addr.to_be());
 
                 let data: LocalIPInfo = LocalIPInfo {
                     interface: interface_name,
-                    ip_addr: IpAddr::V4(ipaddr).to_string()
+                    ip_addr: IpAddr::V4(ipaddr).to_string(),
+                    hostname: resolve_hostname(ifaddrs.ifa_addr, mem::size_of::<libc::sockaddr_in>(), resolve_hostnames)
+                        .unwrap_or(ipaddr.to_string()),
+                    scope: get_scope(&IpAddr::V4(ipaddr)).to_string(),
+                    overlay,
                 };
                 addrs.push(data);
-            } else if (*ifaddrs.ifa_addr).sa_family as i32 == libc::AF_INET6 {
+            } else if family == libc::AF_INET6 {
                 // ipv6
                 let addr: libc::sockaddr_in6 = *((ifaddrs.ifa_addr) as *mut libc::sockaddr_in6);
                 // https://man7.org/linux/man-pages/man7/ipv6.7.html
                 // "Linux supports it only for link-local addresses"
                 // I'm guessing that means if it's not 0, it's not a global IP, and thus not a
                 // "real" ip and should be discarded? Not too sure tbh
-                if addr.sin6_scope_id == 0 {
-                    let ipaddr: Ipv6Addr = Ipv6Addr::from(addr.sin6_addr.s6_addr);
-
+                let ipaddr: Ipv6Addr = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                if addr.sin6_scope_id == 0 && !temporary_v6.contains(&ipaddr) {
                     let mut interface_name: String = interface_name.clone();
                     interface_name.push_str(" (v6)");
 
                     let data: LocalIPInfo = LocalIPInfo {
                         interface: interface_name,
-                        ip_addr: IpAddr::V6(ipaddr).to_string()
+                        ip_addr: IpAddr::V6(ipaddr).to_string(),
+                        hostname: resolve_hostname(ifaddrs.ifa_addr, mem::size_of::<libc::sockaddr_in6>(), resolve_hostnames)
+                            .unwrap_or(ipaddr.to_string()),
+                        scope: get_scope(&IpAddr::V6(ipaddr)).to_string(),
+                        overlay,
                     };
                     addrs.push(data);
                 }
@@ -148,3 +218,172 @@ pub fn get_local_ips() -> Result<Vec<LocalIPInfo>, ModuleError> {
 
     Ok(addrs)
 }
+
+// getifaddrs can hand back entries with no address at all
+fn address_family(addr: *const libc::sockaddr) -> Option<i32> {
+    if addr.is_null() {
+        return None;
+    }
+    Some(unsafe { (*addr).sa_family as i32 })
+}
+
+// Goes through the system resolver, so this covers DNS as well as mDNS if nss-mdns is set up
+fn resolve_hostname(addr: *const libc::sockaddr, addr_len: usize, enabled: bool) -> Option<String> {
+    if !enabled {
+        return None;
+    }
+
+    let mut host: [libc::c_char; libc::NI_MAXHOST as usize] = [0; libc::NI_MAXHOST as usize];
+    // NI_NAMEREQD makes it fail rather than just giving us back the address
+    let result: i32 = unsafe {
+        libc::getnameinfo(addr, addr_len as libc::socklen_t, host.as_mut_ptr(), host.len() as libc::socklen_t, std::ptr::null_mut(), 0, libc::NI_NAMEREQD)
+    };
+    if result != 0 {
+        return None;
+    }
+
+    let host: &CStr = unsafe { CStr::from_ptr(host.as_ptr()) };
+    host.to_str().ok().map(|x| x.to_string())
+}
+
+// Works off the address itself, same as the kernel does
+fn get_scope(addr: &IpAddr) -> &'static str {
+    match addr {
+        IpAddr::V4(x) => {
+            if x.is_loopback() {
+                "host"
+            } else if x.is_link_local() {
+                "link"
+            } else {
+                "global"
+            }
+        },
+        IpAddr::V6(x) => {
+            let segment: u16 = x.segments()[0];
+            if x.is_loopback() {
+                "host"
+            } else if segment & 0xffc0 == 0xfe80 {
+                "link"
+            } else if segment & 0xffc0 == 0xfec0 {
+                "site"
+            } else {
+                "global"
+            }
+        },
+    }
+}
+
+// Each line is; address, interface index, prefix length, scope, flags, interface name
+// https://tldp.org/HOWTO/Linux+IPv6-HOWTO/ch11s04.html
+fn parse_temporary_v6(contents: &str) -> Vec<Ipv6Addr> {
+    // IFA_F_TEMPORARY, from linux/if_addr.h
+    const TEMPORARY_FLAG: u32 = 0x01;
+
+    let mut addrs: Vec<Ipv6Addr> = Vec::new();
+    for line in contents.lines() {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        if values.len() < 5 {
+            continue;
+        }
+        let flags: u32 = match u32::from_str_radix(values[4], 16) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        if flags & TEMPORARY_FLAG == 0 {
+            continue;
+        }
+        match u128::from_str_radix(values[0], 16) {
+            Ok(r) => addrs.push(Ipv6Addr::from(r)),
+            Err(_) => continue,
+        }
+    }
+
+    addrs
+}
+
+// Tailscale always names it's interface tailscale0, WireGuard can be anything so the kernel is
+// asked as a backup
+fn get_overlay(interface: &str) -> Option<&'static str> {
+    if interface.starts_with("tailscale") {
+        return Some("Tailscale");
+    }
+    if interface.starts_with("wg") {
+        return Some("WireGuard");
+    }
+    match util::file_read(&Path::new("/sys/class/net").join(interface).join("uevent")) {
+        Ok(r) if r.lines().any(|x| x == "DEVTYPE=wireguard") => Some("WireGuard"),
+        _ => None,
+    }
+}
+
+// Asks tailscaled through it's local API, the same one the tailscale CLI uses
+fn get_tailscale_node_name() -> Option<String> {
+    let mut stream: UnixStream = ["/var/run/tailscale/tailscaled.sock", "/run/tailscale/tailscaled.sock"].iter()
+        .find_map(|x| UnixStream::connect(x).ok())?;
+    // If tailscaled is stuck, we don't want to be stuck with it
+    stream.set_read_timeout(Some(Duration::from_millis(250))).ok()?;
+    stream.set_write_timeout(Some(Duration::from_millis(250))).ok()?;
+
+    // HTTP 1.0 so that it closes the connection once it's done, rather than us having to parse
+    // the length out
+    stream.write_all(b"GET /localapi/v0/status?peers=false HTTP/1.0\r\nHost: local-tailscaled.sock\r\n\r\n").ok()?;
+    let mut response: String = String::new();
+    stream.read_to_string(&mut response).ok()?;
+
+    parse_tailscale_status(&response)
+}
+fn parse_tailscale_status(response: &str) -> Option<String> {
+    let (_, body) = response.split_once("\r\n\r\n")?;
+    let status: serde_json::Value = serde_json::from_str(body).ok()?;
+    let name: &str = status["Self"]["HostName"].as_str()?;
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(name.to_string())
+}
+
+
+mod tests {
+    // Test temporary IPv6 addresses are picked out of /proc/net/if_inet6
+    #[test]
+    fn temporary_v6() {
+        let contents: &str = "2a0102b8000000001c2f6d1ffe8b9a41 02 40 00 01     wlan0
+2a0102b800000000a1b2c3fffed4e5f6 02 40 00 00     wlan0
+fe80000000000000a1b2c3fffed4e5f6 02 40 20 80     wlan0
+00000000000000000000000000000001 01 80 10 80        lo";
+        let addrs: Vec<std::net::Ipv6Addr> = super::parse_temporary_v6(contents);
+        assert_eq!(addrs, vec!["2a01:2b8::1c2f:6d1f:fe8b:9a41".parse::<std::net::Ipv6Addr>().unwrap()]);
+    }
+
+    // Test the node name is pulled out of tailscaled's response
+    #[test]
+    fn tailscale_status() {
+        let response: &str = "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"Version\": \"1.70.0\", \"Self\": {\"HostName\": \"crab\", \"DNSName\": \"crab.tail1234.ts.net.\"}}";
+        assert_eq!(super::parse_tailscale_status(response), Some("crab".to_string()));
+        assert_eq!(super::parse_tailscale_status("HTTP/1.0 403 Forbidden\r\n\r\naccess denied"), None);
+    }
+
+    // Test entries without an address are skipped rather than dereferenced
+    #[test]
+    fn address_family() {
+        assert_eq!(super::address_family(std::ptr::null()), None);
+
+        let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+        addr.sin_family = libc::AF_INET as libc::sa_family_t;
+        assert_eq!(super::address_family(&addr as *const libc::sockaddr_in as *const libc::sockaddr), Some(libc::AF_INET));
+    }
+
+    // Test address scopes
+    #[test]
+    fn address_scope() {
+        let scope = |x: &str| super::get_scope(&x.parse().unwrap());
+        assert_eq!(scope("192.168.1.5"), "global");
+        assert_eq!(scope("169.254.10.1"), "link");
+        assert_eq!(scope("127.0.0.1"), "host");
+        assert_eq!(scope("::1"), "host");
+        assert_eq!(scope("fe80::1"), "link");
+        assert_eq!(scope("fec0::1"), "site");
+        assert_eq!(scope("2001:db8::1"), "global");
+    }
+}
@@ -1,23 +1,31 @@
-use std::fs::File;
+use std::fs::{read_dir, File};
 use std::io::{BufRead, BufReader};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{formatter::{self, CrabFetchColor}, config_manager::Configuration, module::Module, ModuleError};
+use crate::{formatter::{self, CrabFetchColor, FormatTemplate}, config_manager::Configuration, module::Module, util, ModuleError};
 
+#[derive(Serialize)]
 pub struct MemoryInfo {
     used_kb: u64,
     max_kb: u64,
-    percentage: f32
+    percentage: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    nodes: Vec<MemoryInfo>
 }
 #[derive(Deserialize)]
 pub struct MemoryConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
+    pub per_node: bool,
+    pub node_title: FormatTemplate,
     pub progress_left_border: Option<String>,
     pub progress_right_border: Option<String>,
     pub progress_progress: Option<String>,
@@ -31,7 +39,9 @@ impl Module for MemoryInfo {
         MemoryInfo {
             used_kb: 0,
             max_kb: 0,
-            percentage: 0.0
+            percentage: 0.0,
+            node: None,
+            nodes: Vec::new()
         }
     }
 
@@ -39,46 +49,61 @@ impl Module for MemoryInfo {
         let title_color: &CrabFetchColor = config.memory.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.memory.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.memory.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.memory.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.memory.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = self.replace_placeholders(&config.memory.title, config);
+        // Each NUMA node gets it's own title, to tell them apart
+        let title: String = match self.node {
+            Some(_) => self.replace_placeholders(&config.memory.node_title, config),
+            None => self.replace_placeholders(&config.memory.title, config),
+        };
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.memory.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.memory.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.memory.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.memory.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.memory.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.memory.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.memory.title
-            .replace("{used}", "Unknown")
-            .replace("{max}", "Unknown")
-            .replace("{bar}", "")
-            .replace("{percentage}", "Unknown");
+        let title: String = config.memory.title.render_values(&[
+            ("used", "Unknown"),
+            ("max", "Unknown"),
+            ("bar", ""),
+            ("percentage", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let dec_places: u32 = config.memory.decimal_places.unwrap_or(config.decimal_places);
         let use_ibis: bool = config.memory.use_ibis.unwrap_or(config.use_ibis);
 
-        let mut bar: String = String::new();
-        if text.contains("{bar}") {
-            let left_border: &str = config.memory.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
-            let right_border: &str = config.memory.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
-            let progress: &str = config.memory.progress_progress.as_ref().unwrap_or(&config.progress_progress);
-            let empty: &str = config.memory.progress_empty.as_ref().unwrap_or(&config.progress_empty);
-            let length: u8 = config.memory.progress_target_length.unwrap_or(config.progress_target_length);
-            formatter::make_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length);
-        }
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "percent" => Some(formatter::format_percentage(formatter::round(self.percentage as f64, dec_places) as f32, config)),
+            "used" => Some(formatter::auto_format_bytes(self.used_kb, use_ibis, dec_places, config.unit_space)),
+            "max" => Some(formatter::auto_format_bytes(self.max_kb, use_ibis, dec_places, config.unit_space)),
+            "node" => Some(self.node.map(|x| x.to_string()).unwrap_or_default()),
+            "bar" | "bar_with_percent" => {
+                let left_border: &str = config.memory.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.memory.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.memory.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.memory.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.memory.progress_target_length.unwrap_or(config.progress_target_length);
 
-        formatter::process_percentage_placeholder(text, formatter::round(self.percentage as f64, dec_places) as f32, config)
-            .replace("{used}", &formatter::auto_format_bytes(self.used_kb, use_ibis, dec_places))
-            .replace("{max}", &formatter::auto_format_bytes(self.max_kb, use_ibis, dec_places))
-            .replace("{bar}", &bar.to_string())
+                let mut bar: String = String::new();
+                if x == "bar_with_percent" || config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, self.percentage, length, config);
+                }
+                Some(bar)
+            },
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
@@ -86,7 +111,14 @@ impl Module for MemoryInfo {
     }
 }
 
-pub fn get_memory() -> Result<MemoryInfo, ModuleError> {
+impl MemoryInfo {
+    // The per NUMA node breakdown, if it's enabled and the system has the info for it
+    pub fn nodes(&self) -> &[MemoryInfo] {
+        &self.nodes
+    }
+}
+
+pub fn get_memory(config: &Configuration) -> Result<MemoryInfo, ModuleError> {
     // no info flags here as while it would've had a slight benefit, all the info requires eachother anyway so
     // it's hardly worth it
     let mut memory: MemoryInfo = MemoryInfo::new();
@@ -129,5 +161,96 @@ pub fn get_memory() -> Result<MemoryInfo, ModuleError> {
     memory.used_kb = memory.max_kb - mem_available;
     memory.percentage = (memory.used_kb as f32 / memory.max_kb as f32) * 100.0;
 
+    if config.memory.per_node || config.detect_all_info {
+        memory.nodes = get_nodes();
+    }
+
     Ok(memory)
 }
+
+// Reads each NUMA node's usage from /sys/devices/system/node/node*/meminfo
+// Anything that can't be read is just left out, and it'll back up to the total if there's none
+fn get_nodes() -> Vec<MemoryInfo> {
+    let mut nodes: Vec<MemoryInfo> = Vec::new();
+    let dir = match read_dir("/sys/devices/system/node/") {
+        Ok(r) => r,
+        Err(_) => return nodes,
+    };
+    for entry in dir.flatten() {
+        let file_name: String = entry.file_name().to_string_lossy().to_string();
+        let id: u32 = match file_name.strip_prefix("node").map(|x| x.parse::<u32>()) {
+            Some(Ok(r)) => r,
+            _ => continue,
+        };
+        let contents: String = match util::file_read(&entry.path().join("meminfo")) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+        if let Some(mut node) = parse_node_meminfo(&contents) {
+            node.node = Some(id);
+            nodes.push(node);
+        }
+    }
+    nodes.sort_by_key(|x| x.node);
+
+    nodes
+}
+
+// The node meminfo files don't have a MemAvailable, so it's worked out like free's "used" instead;
+// anything in the page cache or reclaimable slab is counted as free
+// Shared memory lives in the page cache but can't be reclaimed, so that's still used
+fn parse_node_meminfo(contents: &str) -> Option<MemoryInfo> {
+    let mut total: Option<u64> = None;
+    let mut free: u64 = 0;
+    let mut file_pages: u64 = 0;
+    let mut reclaimable: u64 = 0;
+    let mut shmem: u64 = 0;
+    for line in contents.lines() {
+        // Lines look like "Node 0 MemTotal:       16337284 kB"
+        let mut split = line.split_whitespace().skip(2);
+        let key: &str = match split.next() {
+            Some(r) => r.trim_end_matches(':'),
+            None => continue,
+        };
+        let value: u64 = match split.next().map(|x| x.parse::<u64>()) {
+            Some(Ok(r)) => r,
+            _ => continue,
+        };
+        match key {
+            "MemTotal" => total = Some(value),
+            "MemFree" => free = value,
+            "FilePages" => file_pages = value,
+            "SReclaimable" => reclaimable = value,
+            "Shmem" => shmem = value,
+            _ => {},
+        }
+    }
+
+    let total: u64 = total?;
+    let used: u64 = (total + shmem).saturating_sub(free + file_pages + reclaimable).min(total);
+    let mut memory: MemoryInfo = MemoryInfo::new();
+    // Same conversion as /proc/meminfo above
+    memory.max_kb = (total as f64 * 1.024) as u64;
+    memory.used_kb = (used as f64 * 1.024) as u64;
+    memory.percentage = (memory.used_kb as f32 / memory.max_kb as f32) * 100.0;
+
+    Some(memory)
+}
+
+mod tests {
+    // Checks the used memory is worked out without what's reclaimable
+    #[test]
+    fn node_meminfo() {
+        let contents: &str = "Node 0 MemTotal:        1000000 kB
+Node 0 MemFree:          200000 kB
+Node 0 MemUsed:          800000 kB
+Node 0 FilePages:        300000 kB
+Node 0 Shmem:             50000 kB
+Node 0 SReclaimable:     100000 kB
+Node 0 HugePages_Total:     0";
+        let memory: super::MemoryInfo = super::parse_node_meminfo(contents).unwrap();
+        assert_eq!(memory.max_kb, 1024000);
+        assert_eq!(memory.used_kb, 460800);
+        assert!(super::parse_node_meminfo("Node 0 MemFree: 5 kB").is_none());
+    }
+}
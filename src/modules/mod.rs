@@ -21,3 +21,10 @@ pub mod initsys;
 pub mod processes;
 pub mod datetime;
 pub mod localip;
+pub mod publicip;
+pub mod keys;
+pub mod envvar;
+pub mod services;
+pub mod audiodevices;
+pub mod camera;
+pub mod printers;
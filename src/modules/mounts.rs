@@ -1,14 +1,15 @@
-use std::{fs::{self, File}, io::{BufRead, BufReader, Error}, path::{Path, PathBuf}};
+use std::{fs::{self, File}, io::{BufRead, BufReader, Error}, path::{Path, PathBuf}, sync::mpsc::{self, Receiver, Sender}, thread, time::{Duration, Instant}};
 use std::mem;
 
 #[cfg(feature = "android")]
 use std::env;
 
-use libc::statfs;
-use serde::Deserialize;
+use libc::statvfs;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}, module::Module, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::{Module, ModuleWarning}, util::{self, is_flag_set_u32}, ModuleError};
 
+#[derive(Serialize)]
 pub struct MountInfo {
     device: String,     // /dev/sda
     mount: String,      // /hdd
@@ -19,12 +20,13 @@ pub struct MountInfo {
 }
 #[derive(Deserialize)]
 pub struct MountConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub progress_left_border: Option<String>,
     pub progress_right_border: Option<String>,
     pub progress_progress: Option<String>,
@@ -32,7 +34,9 @@ pub struct MountConfiguration {
     pub progress_target_length: Option<u8>,
     pub decimal_places: Option<u32>,
     pub use_ibis: Option<bool>,
-    pub ignore: Vec<String>
+    pub ignore: Vec<String>,
+    pub show_container_mounts: bool,
+    pub time_budget: u64,
 }
 impl Module for MountInfo {
     fn new() -> MountInfo {
@@ -50,53 +54,63 @@ impl Module for MountInfo {
         let title_color: &CrabFetchColor = config.mounts.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.mounts.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.mounts.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.mounts.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.mounts.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.mounts.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.mounts.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.mounts.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.mounts.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.mounts.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.mounts.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.mounts.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.mounts.title
-            .replace("{device}", "Unknown")
-            .replace("{mount}", "Unknown")
-            .replace("{filesystem}", "Unknown")
-            .replace("{space_used}", "Unknown")
-            .replace("{space_avail}", "Unknown")
-            .replace("{space_total}", "Unknown")
-            .replace("{bar}", " ");
-
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        let title: String = config.mounts.title.render_values(&[
+            ("device", "Unknown"),
+            ("mount", "Unknown"),
+            ("filesystem", "Unknown"),
+            ("space_used", "Unknown"),
+            ("space_avail", "Unknown"),
+            ("space_total", "Unknown"),
+            ("bar", " "),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let dec_places: u32 = config.mounts.decimal_places.unwrap_or(config.decimal_places);
         let use_ibis: bool = config.mounts.use_ibis.unwrap_or(config.use_ibis);
 
-        let mut bar: String = String::new();
-        if text.contains("{bar}") {
-            let left_border: &str = config.mounts.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
-            let right_border: &str = config.mounts.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
-            let progress: &str = config.mounts.progress_progress.as_ref().unwrap_or(&config.progress_progress);
-            let empty: &str = config.mounts.progress_empty.as_ref().unwrap_or(&config.progress_empty);
-            let length: u8 = config.mounts.progress_target_length.unwrap_or(config.progress_target_length);
-            formatter::make_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length);
-        }
-
-        formatter::process_percentage_placeholder(text, formatter::round(self.percent as f64, dec_places) as f32, config)
-            .replace("{device}", &self.device)
-            .replace("{mount}", &self.mount)
-            .replace("{filesystem}", &self.filesystem)
-            .replace("{space_used}", &formatter::auto_format_bytes(self.space_total_kb - self.space_avail_kb, use_ibis, dec_places))
-            .replace("{space_avail}", &formatter::auto_format_bytes(self.space_avail_kb, use_ibis, dec_places))
-            .replace("{space_total}", &formatter::auto_format_bytes(self.space_total_kb, use_ibis, dec_places))
-            .replace("{bar}", &bar.to_string())
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "percent" => Some(formatter::format_percentage(formatter::round(self.percent as f64, dec_places) as f32, config)),
+            "device" => Some(self.device.to_string()),
+            "mount" => Some(self.mount.to_string()),
+            "filesystem" => Some(self.filesystem.to_string()),
+            "space_used" => Some(formatter::auto_format_bytes(self.space_total_kb - self.space_avail_kb, use_ibis, dec_places, config.unit_space)),
+            "space_avail" => Some(formatter::auto_format_bytes(self.space_avail_kb, use_ibis, dec_places, config.unit_space)),
+            "space_total" => Some(formatter::auto_format_bytes(self.space_total_kb, use_ibis, dec_places, config.unit_space)),
+            "bar" | "bar_with_percent" => {
+                let left_border: &str = config.mounts.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.mounts.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.mounts.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.mounts.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.mounts.progress_target_length.unwrap_or(config.progress_target_length);
+
+                let mut bar: String = String::new();
+                if x == "bar_with_percent" || config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length, config);
+                }
+                Some(bar)
+            },
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -138,10 +152,10 @@ const MOUNTS_INFOFLAG_SPACE_USED: u32 = 4;
 const MOUNTS_INFOFLAG_SPACE_TOTAL: u32 = 8;
 const MOUNTS_INFOFLAG_SPACE_AVAIL: u32 = 16;
 
-pub fn get_mounted_drives(config: &Configuration) -> Result<Vec<MountInfo>, ModuleError> {
+pub fn get_mounted_drives(config: &Configuration, warnings: &mut Vec<ModuleWarning>) -> Result<Vec<MountInfo>, ModuleError> {
     let mut mounts: Vec<MountInfo> = Vec::new();
     // title is tagged onto the end here to account for the title placeholders
-    let info_flags: u32 = MountInfo::gen_info_flags(&format!("{}{}", config.mounts.format, config.mounts.title));
+    let info_flags: u32 = MountInfo::get_info_flags(&format!("{}{}", config.mounts.format, config.mounts.title), config);
 
     #[cfg(not(feature = "android"))]
     let path: &str = "/etc/mtab";
@@ -179,18 +193,26 @@ pub fn get_mounted_drives(config: &Configuration) -> Result<Vec<MountInfo>, Modu
             Some(r) => r,
             None => continue, // Invalid device or not a device we want
         };
+        let mount_point: String = entries[1].replace("\\040", " ");
+
+        // These are all the same few filesystems over and over, so they're left to the statvfs
+        // filesystem ID check to sort out instead of the device
+        let container: bool = is_container_mount(&mount_point, entries[2]);
+        if container && !config.mounts.show_container_mounts {
+            continue;
+        }
+
         // skipped on android as we want fuse
         #[cfg(not(feature = "android"))]
-        if device_cache.contains(&mount.device) {
+        if !container && device_cache.contains(&mount.device) {
             continue; // Already processed
         }
         device_cache.push(device_name.to_string());
 
-        if !is_device_wanted(&mount.device) {
+        if !container && !is_device_wanted(&mount.device) {
             continue; // bullshit
         }
 
-        let mount_point: String = entries[1].replace("\\040", " ");
         if mount_point == "none" || mount_point == "swap" {
             continue
         }
@@ -207,39 +229,96 @@ pub fn get_mounted_drives(config: &Configuration) -> Result<Vec<MountInfo>, Modu
             continue;
         }
 
-        // statfs to get space data
-        if is_flag_set_u32(info_flags, MOUNTS_INFOFLAG_SPACE_AVAIL | MOUNTS_INFOFLAG_SPACE_USED | MOUNTS_INFOFLAG_SPACE_TOTAL) {
-            call_statfs(&mount_point, &mut mount)?;
-        }
-
         mounts.push(mount);
     }
 
+    // statvfs to get space data
+    // This is done after all the mounts are found, so that it can be kept to time_budget
+    if is_flag_set_u32(info_flags, MOUNTS_INFOFLAG_SPACE_AVAIL | MOUNTS_INFOFLAG_SPACE_USED | MOUNTS_INFOFLAG_SPACE_TOTAL) {
+        mounts = fill_space(mounts, Duration::from_millis(config.mounts.time_budget), warnings);
+    }
+
     Ok(mounts)
 }
 
+// Gets the space of each mount, dropping any that are the same filesystem as one before it
+// Bind mounts share a device with their source, but aren't always listed with it, so they're
+// caught by the filesystem ID statvfs gives back instead
+// The mount, and it's filesystem ID from call_statvfs
+type StatvfsResult = (MountInfo, Result<u64, String>);
+// statvfs is done on a thread of it's own, as one on a dead network share can block forever
+// Once the time budget's gone, the rest are skipped and the thread's left behind, so slow mounts
+// can't hold up the whole fetch
+fn fill_space(mounts: Vec<MountInfo>, budget: Duration, warnings: &mut Vec<ModuleWarning>) -> Vec<MountInfo> {
+    let start: Instant = Instant::now();
+    let total: usize = mounts.len();
+    let (sender, receiver): (Sender<StatvfsResult>, Receiver<StatvfsResult>) = mpsc::channel();
+    thread::spawn(move || {
+        for mut mount in mounts {
+            let result: Result<u64, String> = call_statvfs(&mut mount);
+            // Nobody's listening anymore, the budget's ran out
+            if sender.send((mount, result)).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut filled: Vec<MountInfo> = Vec::with_capacity(total);
+    let mut seen_ids: Vec<u64> = Vec::new();
+    let mut received: usize = 0;
+    while let Ok((mount, result)) = receiver.recv_timeout(budget.saturating_sub(start.elapsed())) {
+        received += 1;
+
+        // One bad mount (e.g a dead network share) shouldn't take out the rest
+        let fs_id: u64 = match result {
+            Ok(r) => r,
+            Err(e) => {
+                warnings.push(ModuleWarning::new("Mounts", e));
+                continue;
+            },
+        };
+        // Some filesystems don't give an ID at all
+        if fs_id != 0 {
+            if seen_ids.contains(&fs_id) {
+                continue;
+            }
+            seen_ids.push(fs_id);
+        }
+
+        filled.push(mount);
+    }
+    let skipped: usize = total - received;
+    if skipped != 0 {
+        warnings.push(ModuleWarning::new("Mounts", format!("Ran out of time after {}ms, skipped {} mount(s). Raise mounts.time_budget to see them", budget.as_millis(), skipped)));
+    }
+
+    filled
+}
+
 // Credit to sysinfo crate for letting me see how to impl this in Rust (and no it's not just copy
 // pasted i swear)
 // https://github.com/GuillaumeGomez/sysinfo/blob/master/src/unix/linux/disk.rs#L96
-fn call_statfs(path: &str, mount: &mut MountInfo) -> Result<(), ModuleError> {
-    let mut bytes: Vec<u8> = path.as_bytes().to_vec();
+// Returns the filesystem ID
+fn call_statvfs(mount: &mut MountInfo) -> Result<u64, String> {
+    let mut bytes: Vec<u8> = mount.mount.as_bytes().to_vec();
     bytes.push(0);
     unsafe { // spooky
-        let mut buffer: statfs = mem::zeroed();
-        let x: i32 = statfs(bytes.as_ptr() as *const _, &mut buffer);
+        let mut buffer: statvfs = mem::zeroed();
+        let x: i32 = statvfs(bytes.as_ptr() as *const _, &mut buffer);
         if x != 0 {
             let c: String = match Error::last_os_error().raw_os_error() {
                 Some(r) => r.to_string(),
                 None => "N/A".to_string(),
             };
-            return Err(ModuleError::new("Mounts", format!("'statfs' syscall failed for mount point {} (code {})", path, c)))
+            return Err(format!("'statvfs' syscall failed for mount point {} (code {})", mount.mount, c))
         }
 
-        mount.space_total_kb = (buffer.f_blocks * buffer.f_bsize as u64) / 1000;
-        mount.space_avail_kb = (buffer.f_bfree * buffer.f_bsize as u64) / 1000;
+        mount.space_total_kb = (buffer.f_blocks as u64 * buffer.f_frsize as u64) / 1000;
+        mount.space_avail_kb = (buffer.f_bfree as u64 * buffer.f_frsize as u64) / 1000;
         mount.percent = ((((mount.space_total_kb - mount.space_avail_kb) as f64) / mount.space_total_kb as f64) * 100.0) as f32;
+
+        Ok(buffer.f_fsid as u64)
     }
-    Ok(())
 }
 
 fn get_device_name(device_name: &str) -> Option<String> {
@@ -284,6 +363,18 @@ fn get_device_name(device_name: &str) -> Option<String> {
     Some(dev)
 }
 
+// Mounts that are made by containers and app sandboxes rather than the user, e.g Docker's overlays,
+// snaps and Flatpak's document portal
+// There can be dozens of these on a container host, which would flood the output
+fn is_container_mount(mount_point: &str, filesystem: &str) -> bool {
+    const FILESYSTEMS: [&str; 3] = ["overlay", "squashfs", "fuse.portal"];
+    const MOUNT_POINTS: [&str; 6] = ["/var/lib/docker/", "/var/lib/containerd/", "/var/lib/containers/", "/run/containerd/", "/snap/", "/var/snap/"];
+
+    FILESYSTEMS.contains(&filesystem)
+        || MOUNT_POINTS.iter().any(|x| mount_point.starts_with(x))
+        || (mount_point.starts_with("/run/user/") && mount_point.ends_with("/doc"))
+}
+
 fn is_device_wanted(device_name: &str) -> bool {
     // WSL
     // FIXME: This is INCREDIBLY hacky
@@ -306,3 +397,16 @@ fn is_device_wanted(device_name: &str) -> bool {
     
     true
 }
+
+mod tests {
+    // Test container and sandbox mounts are picked out, leaving real ones alone
+    #[test]
+    fn container_mounts() {
+        assert!(super::is_container_mount("/var/lib/docker/overlay2/4f1c/merged", "overlay"));
+        assert!(super::is_container_mount("/snap/firefox/4336", "squashfs"));
+        assert!(super::is_container_mount("/run/user/1000/doc", "fuse.portal"));
+        assert!(super::is_container_mount("/var/lib/containers/storage/overlay", "xfs"));
+        assert!(!super::is_container_mount("/", "ext4"));
+        assert!(!super::is_container_mount("/home", "btrfs"));
+    }
+}
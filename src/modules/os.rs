@@ -4,26 +4,32 @@ use std::path::Path;
 #[cfg(feature = "android")]
 use {android_system_properties::AndroidSystemProperties, std::env};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, syscalls::SyscallCache, util::{self, is_flag_set_u32}, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, syscalls::SyscallCache, util::{self, is_flag_set_u32}, ModuleError};
 
+#[derive(Serialize)]
 pub struct OSInfo {
     distro: String,
     pub distro_id: String,
     kernel: String,
+    container_name: String,
+    host_distro: String,
+    home_url: String,
+    entropy: Option<u32>,
 }
 #[derive(Deserialize)]
 pub struct OSConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub newline_kernel: bool,
     pub kernel_title: String,
-    pub kernel_format: String
+    pub kernel_format: FormatTemplate
 }
 impl Module for OSInfo {
     fn new() -> OSInfo {
@@ -31,6 +37,10 @@ impl Module for OSInfo {
             distro: "Unknown".to_string(),
             distro_id: "Unknown".to_string(),
             kernel: "Unknown".to_string(),
+            container_name: String::new(),
+            host_distro: "Unknown".to_string(),
+            home_url: String::new(),
+            entropy: None,
         }
     }
 
@@ -38,40 +48,64 @@ impl Module for OSInfo {
         let title_color: &CrabFetchColor = config.os.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.os.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.os.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.os.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.os.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.os.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.os.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.os.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.os.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.os.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.os.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.os.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.os.title
-            .replace("{distro}", "Unknown")
-            .replace("{kernel}", "Unknown");
+        let title: String = config.os.title.render_values(&[
+            ("distro", "Unknown"),
+            ("kernel", "Unknown"),
+            ("container_name", ""),
+            ("host_distro", "Unknown"),
+            ("home_url", ""),
+            ("entropy", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{distro}", &self.distro)
-            .replace("{kernel}", &self.kernel)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "distro" => Some(self.distro.to_string()),
+            "kernel" => Some(self.kernel.to_string()),
+            "container_name" => Some(self.container_name.to_string()),
+            "host_distro" => Some(self.host_distro.to_string()),
+            "home_url" => Some(self.home_url.to_string()),
+            "entropy" => Some(self.entropy_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
         let mut info_flags: u32 = 0;
 
-        if format.contains("{distro}") {
+        // The homepage comes from the same place as the distro's name
+        if format.contains("{distro}") || format.contains("{home_url}") {
            info_flags |= OS_INFOFLAG_DISTRO;
         }
         if format.contains("{kernel}") {
             info_flags |= OS_INFOFLAG_KERNEL;
         }
+        if format.contains("{container_name}") {
+            info_flags |= OS_INFOFLAG_CONTAINER_NAME;
+        }
+        if format.contains("{host_distro}") {
+            info_flags |= OS_INFOFLAG_HOST_DISTRO;
+        }
+        if format.contains("{entropy}") {
+            info_flags |= OS_INFOFLAG_ENTROPY;
+        }
 
         info_flags
     }
@@ -82,28 +116,44 @@ impl OSInfo {
         let title_color: &CrabFetchColor = config.os.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.os.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.os.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.os.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.os.separator.as_ref().unwrap_or(&config.separator);
 
-        let value: String = self.replace_color_placeholders(&config.os.kernel_format.replace("{kernel}", &self.kernel), config);
+        let value: String = self.replace_color_placeholders(&config.os.kernel_format.render(|x| match x {
+            "kernel" => Some(self.kernel.to_string()),
+            "entropy" => Some(self.entropy_string()),
+            _ => None,
+        }), config);
 
-        Self::default_style(config, &config.os.kernel_title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &config.os.kernel_title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+
+    fn entropy_string(&self) -> String {
+        match self.entropy {
+            Some(r) => r.to_string(),
+            None => "Unknown".to_string(),
+        }
     }
 }
 
 const OS_INFOFLAG_DISTRO: u32 = 1;
 const OS_INFOFLAG_KERNEL: u32 = 2;
+const OS_INFOFLAG_CONTAINER_NAME: u32 = 4;
+const OS_INFOFLAG_HOST_DISTRO: u32 = 8;
+const OS_INFOFLAG_ENTROPY: u32 = 16;
 
 pub fn get_os(config: &Configuration, syscall_cache: &mut SyscallCache) -> Result<OSInfo, ModuleError> {
     let mut os: OSInfo = OSInfo::new();
 
     let mut format: String = config.os.format.to_string();
     if config.os.newline_kernel {
-        format.push_str(&config.os.kernel_format);
+        format.push_str(config.os.kernel_format.source());
     }
-    let info_flags: u32 = OSInfo::gen_info_flags(&format);
+    let info_flags: u32 = OSInfo::get_info_flags(&format, config);
 
     // Grabs the distro name from /etc/os-release
     // Grabs the kernel release from /proc/sys/kernel/osrelease
+    // Grabs the available entropy from /proc/sys/kernel/random/entropy_avail
 
     // Distro
     if is_flag_set_u32(info_flags, OS_INFOFLAG_DISTRO) || config.ascii.display {
@@ -119,11 +169,28 @@ pub fn get_os(config: &Configuration, syscall_cache: &mut SyscallCache) -> Resul
             }
             os.distro_id = "android".to_string();
         } else {
-            parse_os_release(&mut os)?;
+            (os.distro, os.distro_id, os.home_url) = parse_os_release(Path::new("/etc/os-release"))?;
         }
 
         #[cfg(not(feature = "android"))]
-        parse_os_release(&mut os)?;
+        {
+            (os.distro, os.distro_id, os.home_url) = parse_os_release(Path::new("/etc/os-release"))?;
+        }
+    }
+
+    // Container
+    if is_flag_set_u32(info_flags, OS_INFOFLAG_CONTAINER_NAME) {
+        os.container_name = util::container_name().unwrap_or_default();
+    }
+    if is_flag_set_u32(info_flags, OS_INFOFLAG_HOST_DISTRO) {
+        os.host_distro = match util::host_root() {
+            Some(r) => parse_os_release(&r.join("etc/os-release"))?.0,
+            // Either not in a container, or can't see out of it
+            None => match is_flag_set_u32(info_flags, OS_INFOFLAG_DISTRO) || config.ascii.display {
+                true => os.distro.to_string(),
+                false => parse_os_release(Path::new("/etc/os-release"))?.0,
+            },
+        };
     }
 
     // Kernel
@@ -131,24 +198,39 @@ pub fn get_os(config: &Configuration, syscall_cache: &mut SyscallCache) -> Resul
         os.kernel = syscall_cache.get_uname_cached().release;
     }
 
+    // Entropy
+    // Since 5.18 this is always 256, as the pool can no longer run dry
+    if is_flag_set_u32(info_flags, OS_INFOFLAG_ENTROPY) {
+        os.entropy = util::file_read(Path::new("/proc/sys/kernel/random/entropy_avail")).ok()
+            .and_then(|x| x.trim().parse::<u32>().ok());
+    }
+
     Ok(os)
 }
 
-fn parse_os_release(os: &mut OSInfo) -> Result<(), ModuleError> {
-    let contents = match util::file_read(Path::new("/etc/os-release")) {
+// Returns the pretty name, ID and homepage
+pub fn parse_os_release(path: &Path) -> Result<(String, String, String), ModuleError> {
+    let contents = match util::file_read(path) {
         Ok(r) => r,
-        Err(e) => return Err(ModuleError::new("OS", format!("Can't read from /etc/os-release - {}", e))),
+        Err(e) => return Err(ModuleError::new("OS", format!("Can't read from {} - {}", path.display(), e))),
     };
+    let mut distro: String = "Unknown".to_string();
+    let mut distro_id: String = "Unknown".to_string();
+    let mut home_url: String = String::new();
     for line in contents.trim().split('\n').collect::<Vec<&str>>() {
         if line.starts_with("PRETTY_NAME=") {
-            os.distro = line[13..line.len() - 1].to_string();
+            distro = line[13..line.len() - 1].to_string();
             continue;
         }
         if line.starts_with("ID=") {
-            os.distro_id = line[3..line.len()].trim().to_string();
+            distro_id = line[3..line.len()].trim().to_string();
+            continue;
+        }
+        if let Some(url) = line.strip_prefix("HOME_URL=") {
+            home_url = url.trim().trim_matches('"').to_string();
             continue;
         }
     }
 
-    Ok(())
+    Ok((distro, distro_id, home_url))
 }
@@ -1,22 +1,26 @@
 use core::str;
+use std::path::Path;
 
-use colored::{ColoredString, Colorize};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, package_managers::{self, MANAGER_DPKG, MANAGER_HOMEBREW, MANAGER_PACMAN, MANAGER_XBPS}};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, package_managers::{self, MANAGER_DPKG, MANAGER_HOMEBREW, MANAGER_PACMAN, MANAGER_XBPS}, util};
 
+#[derive(Serialize)]
 pub struct PackagesInfo {
     packages: Vec<ManagerInfo>
 }
 #[derive(Deserialize)]
 pub struct PackagesConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
     pub ignore: Vec<String>,
-    pub format: String
+    pub format: FormatTemplate,
+    pub include_host: bool,
+    pub sort: String,
 }
 impl Module for PackagesInfo {
     fn new() -> PackagesInfo {
@@ -29,23 +33,11 @@ impl Module for PackagesInfo {
         let title_color: &CrabFetchColor = config.packages.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.packages.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.packages.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.packages.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.packages.separator.as_ref().unwrap_or(&config.separator);
 
-        // Full style
-        let mut title_final: String = String::new();
-
-        // Title
-        if !config.packages.title.trim().is_empty() {
-            let mut title: ColoredString = title_color.color_string(&config.packages.title);
-            if title_bold {
-                title = title.bold();
-            }
-            if title_italic {
-                title = title.italic();
-            }
-
-            title_final.push_str(&title.to_string());
-        }
+        // There's nothing of this module's own to put in the title, but it can still have the global placeholders
+        let title: String = config.packages.title.render(|_| None);
 
         let mut value: String = String::new();
         for manager in &self.packages {
@@ -60,17 +52,19 @@ impl Module for PackagesInfo {
                 value.push_str(", ");
             }
             // :(
-            value.push_str(&config.packages.format.replace("{manager}", &manager.manager_name)
-                .replace("{count}", &manager.package_count.to_string()));
+            value.push_str(&config.packages.format.render(|x| match x {
+                "manager" => Some(manager.manager_name.to_string()),
+                "count" => Some(manager.package_count.to_string()),
+                _ => None,
+            }));
         }
 
-        let mut format_final: String = separator.to_string();
-        format_final.push_str(&self.replace_color_placeholders(&value, config));
+        let value: String = self.replace_color_placeholders(&value, config);
 
-        (title_final, format_final)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
 
-    fn replace_placeholders(&self, _: &str, _: &Configuration) -> String {
+    fn replace_placeholders(&self, _: &FormatTemplate, _: &Configuration) -> String {
         // done in style() instead
         unimplemented!()
     }
@@ -81,11 +75,18 @@ impl Module for PackagesInfo {
         panic!("Packages should never fail, something's wrong. Report this to my GitHub please.");
     }
 
+    fn unknown_placeholders(text: &FormatTemplate, _: &Configuration) -> Vec<String> {
+        text.placeholder_names().into_iter()
+            .filter(|x| x != "manager" && x != "count" && !formatter::is_style_placeholder(x) && !formatter::is_global_placeholder(x))
+            .collect()
+    }
+
     fn gen_info_flags(_: &str) -> u32 {
         panic!("gen_info_flags called on packages module. This should never happen, please make a bug report!")
     }
 }
 
+#[derive(Serialize)]
 pub struct ManagerInfo {
     manager_name: String,
     package_count: u64
@@ -99,7 +100,7 @@ impl ManagerInfo {
     }
 }
 
-pub fn get_packages(package_managers: &package_managers::ManagerInfo) -> PackagesInfo {
+pub fn get_packages(package_managers: &package_managers::ManagerInfo, config: &Configuration) -> PackagesInfo {
     let mut packages: PackagesInfo = PackagesInfo::new();
 
     packages.packages.push(ManagerInfo::fill("pacman", package_managers.find_all_packages_from(MANAGER_PACMAN).values().len() as u64));
@@ -107,25 +108,61 @@ pub fn get_packages(package_managers: &package_managers::ManagerInfo) -> Package
     packages.packages.push(ManagerInfo::fill("xbps", package_managers.find_all_packages_from(MANAGER_XBPS).values().len() as u64));
     packages.packages.push(ManagerInfo::fill("brew", package_managers.find_all_packages_from(MANAGER_HOMEBREW).values().len() as u64));
 
-    if let Some(r) = package_managers.process_flatpak_packages_count() {
+    if let Some(r) = package_managers.process_flatpak_packages_count(Path::new("/")) {
         packages.packages.push(ManagerInfo::fill("flatpak", r));
     }
 
     #[cfg(feature = "rpm_packages")]
-    if let Some(r) = process_rpm_packages() {
+    if let Some(r) = process_rpm_packages(Path::new("/")) {
         packages.packages.push(ManagerInfo::fill("rpm", r));
     }
 
+    // Inside a container, the host's packages are a seperate set to ours
+    // Homebrew's left out as it lives in /home, which the container shares
+    if config.packages.include_host {
+        if let Some(root) = util::host_root() {
+            get_host_packages(&mut packages, &root);
+        }
+    }
+    sort_managers(&mut packages.packages, &config.packages.sort);
+
     packages
 }
 
+// Anything else, e.g config_order, keeps the order they're checked in above
+fn sort_managers(managers: &mut [ManagerInfo], sort: &str) {
+    match sort {
+        "count_desc" => managers.sort_by(|a, b| b.package_count.cmp(&a.package_count).then_with(|| a.manager_name.cmp(&b.manager_name))),
+        "alphabetical" => managers.sort_by(|a, b| a.manager_name.cmp(&b.manager_name)),
+        _ => {},
+    }
+}
+
+fn get_host_packages(packages: &mut PackagesInfo, root: &Path) {
+    let mut host_managers: package_managers::ManagerInfo = package_managers::ManagerInfo::new();
+    host_managers.probe_and_cache_system(root);
+
+    for (name, manager) in [("pacman", MANAGER_PACMAN), ("dpkg", MANAGER_DPKG), ("xbps", MANAGER_XBPS)] {
+        let count: u64 = host_managers.find_all_packages_from(manager).values().len() as u64;
+        packages.packages.push(ManagerInfo::fill(&format!("host {}", name), count));
+    }
+    if let Some(r) = host_managers.process_flatpak_packages_count(root) {
+        packages.packages.push(ManagerInfo::fill("host flatpak", r));
+    }
+
+    #[cfg(feature = "rpm_packages")]
+    if let Some(r) = process_rpm_packages(root) {
+        packages.packages.push(ManagerInfo::fill("host rpm", r));
+    }
+}
+
 #[cfg(feature = "rpm_packages")]
-fn process_rpm_packages() -> Option<u64> {
+fn process_rpm_packages(root: &Path) -> Option<u64> {
     let mut result: u64 = 0;
 
     // Expected in my test env: 1981
     // Grabs from /var/lib/rpm/rpmdb.sqlite
-    let db: sqlite::Connection = match sqlite::open("/var/lib/rpm/rpmdb.sqlite") {
+    let db: sqlite::Connection = match sqlite::open(root.join("var/lib/rpm/rpmdb.sqlite")) {
         Ok(r) => r,
         Err(_) => return None,
     };
@@ -145,3 +182,20 @@ fn process_rpm_packages() -> Option<u64> {
         Err(_) => None,
     }
 }
+
+
+mod tests {
+    // Test the managers are sorted by their count or name, or left alone
+    #[test]
+    fn sort_managers() {
+        let managers = || vec![super::ManagerInfo::fill("pacman", 1024), super::ManagerInfo::fill("flatpak", 12), super::ManagerInfo::fill("brew", 1024)];
+        let sorted = |sort: &str| {
+            let mut managers: Vec<super::ManagerInfo> = managers();
+            super::sort_managers(&mut managers, sort);
+            managers.into_iter().map(|x| x.manager_name).collect::<Vec<String>>()
+        };
+        assert_eq!(sorted("count_desc"), ["brew", "pacman", "flatpak"]);
+        assert_eq!(sorted("alphabetical"), ["brew", "flatpak", "pacman"]);
+        assert_eq!(sorted("config_order"), ["pacman", "flatpak", "brew"]);
+    }
+}
@@ -1,10 +1,11 @@
 use std::time::Duration;
 
 use dbus::{arg, blocking::{stdintf::org_freedesktop_dbus::Properties, Connection, Proxy}};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, util::is_flag_set_u32, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, util::is_flag_set_u32, ModuleError};
 
+#[derive(Serialize)]
 pub struct PlayerInfo {
     player: String,
     album: String,
@@ -15,13 +16,20 @@ pub struct PlayerInfo {
 }
 #[derive(Deserialize)]
 pub struct PlayerConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub ignore: Vec<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
+    pub require_playing: bool,
+    pub max_players: u32,
+    pub max_length: u32,
+    pub status_icon_playing: String,
+    pub status_icon_paused: String,
+    pub status_icon_stopped: String,
 }
 impl Module for PlayerInfo {
     fn new() -> PlayerInfo {
@@ -40,37 +48,58 @@ impl Module for PlayerInfo {
         let title_color: &CrabFetchColor = config.player.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.player.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.player.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.player.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.player.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.player.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.player.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.player.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.player.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.player.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.player.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.player.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.player.title
-            .replace("{track}", "Unknown")
-            .replace("{album}", "Unknown")
-            .replace("{album_artists}", "Unknown")
-            .replace("{track_artists}", "Unknown")
-            .replace("{player}", "Unknown")
-            .replace("{status}", "Unknown");
-
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        let title: String = config.player.title.render_values(&[
+            ("track", "Unknown"),
+            ("album", "Unknown"),
+            ("album_artists", "Unknown"),
+            ("track_artists", "Unknown"),
+            ("player", "Unknown"),
+            ("status", "Unknown"),
+            ("status_icon", ""),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{track}", &self.track)
-            .replace("{album}", &self.album)
-            .replace("{album_artists}", &self.album_artists.join(" "))
-            .replace("{track_artists}", &self.track_artists.join(" "))
-            .replace("{player}", &self.player)
-            .replace("{status}", &self.status)
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
+        // Long track names shouldn't be allowed to push everything else out of line
+        let limit = |x: &str| -> String {
+            if config.player.max_length == 0 {
+                return x.to_string();
+            }
+            formatter::truncate(x, config.player.max_length as usize)
+        };
+
+        text.render(|x| match x {
+            "track" => Some(limit(&self.track)),
+            "album" => Some(limit(&self.album)),
+            "album_artists" => Some(limit(&self.album_artists.join(" "))),
+            "track_artists" => Some(limit(&self.track_artists.join(" "))),
+            "player" => Some(self.player.to_string()),
+            "status" => Some(self.status.to_string()),
+            "status_icon" => Some(match self.status.as_str() {
+                "Playing" => config.player.status_icon_playing.to_string(),
+                "Paused" => config.player.status_icon_paused.to_string(),
+                "Stopped" => config.player.status_icon_stopped.to_string(),
+                _ => String::new(),
+            }),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -82,7 +111,7 @@ impl Module for PlayerInfo {
         if format.contains("{player}") {
             info_flags |= PLAYER_INFOFLAG_PLAYER;
         }
-        if format.contains("{status}") {
+        if format.contains("{status}") || format.contains("{status_icon}") {
             info_flags |= PLAYER_INFOFLAG_STATUS;
         }
 
@@ -97,7 +126,10 @@ const PLAYER_INFOFLAG_STATUS: u32 = 4;
 pub fn get_players(config: &Configuration) -> Result<Vec<PlayerInfo>, ModuleError> {
     let mut players: Vec<PlayerInfo> = Vec::new();
     // title is tagged onto the end here to account for the title placeholders
-    let info_flags: u32 = PlayerInfo::gen_info_flags(&format!("{}{}", config.player.format, config.player.title));
+    let mut info_flags: u32 = PlayerInfo::get_info_flags(&format!("{}{}", config.player.format, config.player.title), config);
+    if config.player.require_playing {
+        info_flags |= PLAYER_INFOFLAG_STATUS;
+    }
 
     let conn: Connection = match Connection::new_session() {
         Ok(r) => r,
@@ -110,12 +142,26 @@ pub fn get_players(config: &Configuration) -> Result<Vec<PlayerInfo>, ModuleErro
     };
 
     for player in found_players {
-        let name: String = player.split('.').last().unwrap().to_string();
+        if config.player.max_players != 0 && players.len() >= config.player.max_players as usize {
+            break
+        }
+        let name: String = player.split('.').next_back().unwrap().to_string();
         if config.player.ignore.contains(&name) {
             continue // ignored
         }
 
         let proxy: Proxy<'_, &Connection> = conn.with_proxy(&player, "/org/mpris/MediaPlayer2", Duration::from_secs(1));
+
+        // Done first so we don't bother with anything else if it's not playing
+        let status: String = if is_flag_set_u32(info_flags, PLAYER_INFOFLAG_STATUS) {
+            match req_player_property::<String>(&proxy, "PlaybackStatus") {
+                Ok(r) => r,
+                Err(_) => "Unknown".to_string(),
+            }
+        } else {"Unknown".to_string()};
+        if config.player.require_playing && status != "Playing" {
+            continue
+        }
     
         let player_metadata: Option<arg::PropMap> = if is_flag_set_u32(info_flags, PLAYER_INFOFLAG_METADATA) {
             match req_player_property(&proxy, "Metadata") {
@@ -158,12 +204,7 @@ pub fn get_players(config: &Configuration) -> Result<Vec<PlayerInfo>, ModuleErro
                     None => vec!["Unknown".to_string()],
                 }
             } else {vec!["Unknown".to_string()]},
-            status: if is_flag_set_u32(info_flags, PLAYER_INFOFLAG_STATUS) {
-                match req_player_property::<String>(&proxy, "PlaybackStatus") {
-                    Ok(r) => r,
-                    Err(_) => "Unknown".to_string(),
-                }
-            } else {"Unknown".to_string()},
+            status,
         };
         players.push(info);
     }
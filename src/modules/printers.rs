@@ -0,0 +1,243 @@
+use std::{io::{Read, Write}, os::unix::net::UnixStream, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, ModuleError};
+
+#[derive(Serialize)]
+pub struct PrinterInfo {
+    name: String,
+    description: String,
+    model: String,
+    state: String,
+    state_message: String,
+}
+#[derive(Deserialize)]
+pub struct PrintersConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+    pub ignore: Vec<String>,
+}
+impl Module for PrinterInfo {
+    fn new() -> PrinterInfo {
+        PrinterInfo {
+            name: "Unknown".to_string(),
+            description: String::new(),
+            model: String::new(),
+            state: "Unknown".to_string(),
+            state_message: String::new(),
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.printers.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.printers.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.printers.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.printers.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.printers.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.printers.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.printers.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.printers.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.printers.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.printers.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.printers.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.printers.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.printers.title.render_values(&[
+            ("name", "Unknown"),
+            ("description", ""),
+            ("model", ""),
+            ("state", "Unknown"),
+            ("state_message", ""),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "description" => Some(self.description.to_string()),
+            "model" => Some(self.model.to_string()),
+            "state" => Some(self.state.to_string()),
+            "state_message" => Some(self.state_message.to_string()),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on printers module. This should never happen, please make a bug report!")
+    }
+}
+
+pub fn get_printers(config: &Configuration) -> Result<Vec<PrinterInfo>, ModuleError> {
+    let mut stream: UnixStream = match ["/run/cups/cups.sock", "/var/run/cups/cups.sock"].iter().find_map(|x| UnixStream::connect(x).ok()) {
+        Some(r) => r,
+        None => return Err(ModuleError::new("Printers", "Can't connect to CUPS, is it running?".to_string())),
+    };
+    // Same as Tailscale in the local IP module, don't want to hang if cupsd is stuck
+    if stream.set_read_timeout(Some(Duration::from_millis(250))).is_err() || stream.set_write_timeout(Some(Duration::from_millis(250))).is_err() {
+        return Err(ModuleError::new("Printers", "Failed to set a timeout on the CUPS socket".to_string()));
+    }
+
+    // HTTP 1.0 so that CUPS doesn't send the response back chunked
+    let body: Vec<u8> = get_printers_request();
+    let mut request: Vec<u8> = format!("POST / HTTP/1.0\r\nHost: localhost\r\nContent-Type: application/ipp\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+    request.extend(body);
+    if let Err(e) = stream.write_all(&request) {
+        return Err(ModuleError::new("Printers", format!("Failed to send request to CUPS: {}", e)));
+    }
+    let mut response: Vec<u8> = Vec::new();
+    if let Err(e) = stream.read_to_end(&mut response) {
+        return Err(ModuleError::new("Printers", format!("Failed to read response from CUPS: {}", e)));
+    }
+
+    let body: &[u8] = match response.windows(4).position(|x| x == b"\r\n\r\n") {
+        Some(r) => &response[r + 4..],
+        None => return Err(ModuleError::new("Printers", "Invalid response from CUPS".to_string())),
+    };
+    let mut printers: Vec<PrinterInfo> = match parse_printers_response(body) {
+        Some(r) => r,
+        None => return Err(ModuleError::new("Printers", "Failed to parse response from CUPS".to_string())),
+    };
+    printers.retain(|x| !config.printers.ignore.iter().any(|y| x.name.starts_with(y)));
+
+    Ok(printers)
+}
+
+// The request is IPP, which is just a binary format sent over HTTP
+// https://datatracker.ietf.org/doc/html/rfc8010#section-3
+// https://www.cups.org/doc/spec-ipp.html#CUPS_GET_PRINTERS
+const IPP_CUPS_GET_PRINTERS: u16 = 0x4002;
+const IPP_TAG_OPERATION: u8 = 0x01;
+const IPP_TAG_END: u8 = 0x03;
+const IPP_TAG_PRINTER: u8 = 0x04;
+const IPP_TAG_ENUM: u8 = 0x23;
+const IPP_TAG_KEYWORD: u8 = 0x44;
+const IPP_TAG_CHARSET: u8 = 0x47;
+const IPP_TAG_LANGUAGE: u8 = 0x48;
+const IPP_STATUS_NOT_FOUND: u16 = 0x0406;
+
+fn get_printers_request() -> Vec<u8> {
+    let mut request: Vec<u8> = vec![2, 0];
+    request.extend(IPP_CUPS_GET_PRINTERS.to_be_bytes());
+    request.extend(1u32.to_be_bytes());
+
+    request.push(IPP_TAG_OPERATION);
+    push_attribute(&mut request, IPP_TAG_CHARSET, "attributes-charset", b"utf-8");
+    push_attribute(&mut request, IPP_TAG_LANGUAGE, "attributes-natural-language", b"en");
+    // Extra values of the same attribute have no name
+    for (i, x) in ["printer-name", "printer-info", "printer-make-and-model", "printer-state", "printer-state-message"].iter().enumerate() {
+        push_attribute(&mut request, IPP_TAG_KEYWORD, if i == 0 { "requested-attributes" } else { "" }, x.as_bytes());
+    }
+    request.push(IPP_TAG_END);
+
+    request
+}
+fn push_attribute(request: &mut Vec<u8>, tag: u8, name: &str, value: &[u8]) {
+    request.push(tag);
+    request.extend((name.len() as u16).to_be_bytes());
+    request.extend(name.as_bytes());
+    request.extend((value.len() as u16).to_be_bytes());
+    request.extend(value);
+}
+
+fn parse_printers_response(body: &[u8]) -> Option<Vec<PrinterInfo>> {
+    // Anything from 0x0400 up is an error, apart from not-found which is what CUPS says when there's
+    // no printers set up
+    // https://datatracker.ietf.org/doc/html/rfc8011#appendix-B
+    let status: u16 = u16::from_be_bytes([*body.get(2)?, *body.get(3)?]);
+    if status == IPP_STATUS_NOT_FOUND {
+        return Some(Vec::new());
+    }
+    if status >= 0x0400 {
+        return None;
+    }
+
+    let mut printers: Vec<PrinterInfo> = Vec::new();
+    let mut in_printer: bool = false;
+    let mut pos: usize = 8;
+    loop {
+        let tag: u8 = *body.get(pos)?;
+        pos += 1;
+        // Delimiter tags start a new group
+        if tag <= 0x0F {
+            if tag == IPP_TAG_END {
+                break;
+            }
+            in_printer = tag == IPP_TAG_PRINTER;
+            if in_printer {
+                printers.push(PrinterInfo::new());
+            }
+            continue;
+        }
+
+        let name_len: usize = u16::from_be_bytes([*body.get(pos)?, *body.get(pos + 1)?]) as usize;
+        let name: &[u8] = body.get(pos + 2..pos + 2 + name_len)?;
+        pos += 2 + name_len;
+        let value_len: usize = u16::from_be_bytes([*body.get(pos)?, *body.get(pos + 1)?]) as usize;
+        let value: &[u8] = body.get(pos + 2..pos + 2 + value_len)?;
+        pos += 2 + value_len;
+
+        let printer: &mut PrinterInfo = match (in_printer, printers.last_mut()) {
+            (true, Some(r)) => r,
+            _ => continue,
+        };
+        let text: String = String::from_utf8_lossy(value).to_string();
+        match name {
+            b"printer-name" => printer.name = text,
+            b"printer-info" => printer.description = text,
+            b"printer-make-and-model" => printer.model = text,
+            b"printer-state-message" => printer.state_message = text,
+            b"printer-state" if tag == IPP_TAG_ENUM && value_len == 4 => {
+                printer.state = match u32::from_be_bytes([value[0], value[1], value[2], value[3]]) {
+                    3 => "idle",
+                    4 => "printing",
+                    5 => "stopped",
+                    _ => "Unknown",
+                }.to_string();
+            },
+            _ => {},
+        }
+    }
+
+    Some(printers)
+}
+
+
+mod tests {
+    // Test printers and their states are read out of a CUPS-Get-Printers response
+    #[test]
+    fn printers_response() {
+        let mut body: Vec<u8> = vec![2, 0, 0, 0, 0, 0, 0, 1];
+        body.push(super::IPP_TAG_OPERATION);
+        super::push_attribute(&mut body, super::IPP_TAG_CHARSET, "attributes-charset", b"utf-8");
+        body.push(super::IPP_TAG_PRINTER);
+        super::push_attribute(&mut body, 0x42, "printer-name", b"Office");
+        super::push_attribute(&mut body, 0x41, "printer-make-and-model", b"HP LaserJet");
+        super::push_attribute(&mut body, super::IPP_TAG_ENUM, "printer-state", &4u32.to_be_bytes());
+        body.push(super::IPP_TAG_PRINTER);
+        super::push_attribute(&mut body, 0x42, "printer-name", b"Label");
+        super::push_attribute(&mut body, super::IPP_TAG_ENUM, "printer-state", &5u32.to_be_bytes());
+        body.push(super::IPP_TAG_END);
+
+        let printers: Vec<super::PrinterInfo> = super::parse_printers_response(&body).unwrap();
+        assert_eq!(printers.len(), 2);
+        assert_eq!((printers[0].name.as_str(), printers[0].model.as_str(), printers[0].state.as_str()), ("Office", "HP LaserJet", "printing"));
+        assert_eq!((printers[1].name.as_str(), printers[1].state.as_str()), ("Label", "stopped"));
+        // client-error-not-found, when there's no printers at all
+        assert_eq!(super::parse_printers_response(&[2, 0, 4, 6, 0, 0, 0, 1, 3]).map(|x| x.len()), Some(0));
+        // client-error-forbidden
+        assert!(super::parse_printers_response(&[2, 0, 4, 1, 0, 0, 0, 1, 3]).is_none());
+    }
+}
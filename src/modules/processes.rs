@@ -1,20 +1,22 @@
 use std::fs::{read_dir, ReadDir};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{formatter::CrabFetchColor, config_manager::Configuration, module::Module, ModuleError};
+use crate::{formatter::{CrabFetchColor, FormatTemplate}, config_manager::Configuration, module::Module, ModuleError};
 
+#[derive(Serialize)]
 pub struct ProcessesInfo {
     count: u32 // god forbid someone manages to hit this limit
 }
 #[derive(Deserialize)]
 pub struct ProcessesConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: Option<String>,
+    pub format: Option<FormatTemplate>,
 }
 impl Module for ProcessesInfo {
     fn new() -> ProcessesInfo {
@@ -27,27 +29,34 @@ impl Module for ProcessesInfo {
         let title_color: &CrabFetchColor = config.processes.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.processes.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.processes.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.processes.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.processes.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.processes.title, config);
-        let format: String = config.processes.format.clone().unwrap_or("{count}".to_string());
-        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&format, config), config);
+        let value: String = match &config.processes.format {
+            Some(format) => self.replace_color_placeholders(&self.replace_placeholders(format, config), config),
+            None => self.count.to_string(),
+        };
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.processes.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.processes.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.processes.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.processes.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.processes.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.processes.title.replace("{count}", "Unknown");
+        let title: String = config.processes.title.render_values(&[("count", "Unknown")]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{count}", &self.count.to_string())
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "count" => Some(self.count.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
@@ -0,0 +1,81 @@
+use std::{net::IpAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, network, ModuleError};
+
+#[derive(Serialize)]
+pub struct PublicIPInfo {
+    ip_addr: String,
+}
+#[derive(Deserialize)]
+pub struct PublicIPConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+    pub provider: String,
+    pub cache_ttl: u64,
+}
+impl Module for PublicIPInfo {
+    fn new() -> PublicIPInfo {
+        PublicIPInfo {
+            ip_addr: "Unknown".to_string(),
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.publicip.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.publicip.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.publicip.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.publicip.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.publicip.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.publicip.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.publicip.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.publicip.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.publicip.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.publicip.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.publicip.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.publicip.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.publicip.title.render_values(&[("addr", "Unknown")]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "addr" => Some(self.ip_addr.to_string()),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on public IP module. This should never happen, please make a bug report!")
+    }
+}
+
+pub fn get_public_ip(config: &Configuration) -> Result<PublicIPInfo, ModuleError> {
+    let provider: String = config.publicip.provider.to_string();
+    let timeout: Duration = Duration::from_millis(config.network_timeout);
+    let addr: String = network::fetch_cached("publicip", config.publicip.cache_ttl, config, move || {
+        let body: String = network::http_get(&provider, "/", timeout)?;
+        // Checked here so that an error page from the provider never ends up cached
+        match body.trim().parse::<IpAddr>() {
+            Ok(r) => Ok(r.to_string()),
+            Err(_) => Err(format!("{} didn't give back an IP address", provider)),
+        }
+    }).map_err(|e| ModuleError::new("PublicIP", e))?;
+
+    Ok(PublicIPInfo {
+        ip_addr: addr,
+    })
+}
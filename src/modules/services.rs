@@ -0,0 +1,167 @@
+use std::{fs::{self, ReadDir}, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, util, ModuleError};
+
+#[derive(Serialize)]
+pub struct ServicesInfo {
+    services: Vec<ServiceStatus>,
+}
+#[derive(Serialize)]
+pub struct ServiceStatus {
+    name: String,
+    up: bool,
+}
+#[derive(Deserialize)]
+pub struct ServicesConfiguration {
+    pub title: FormatTemplate,
+    pub title_color: Option<CrabFetchColor>,
+    pub title_bold: Option<bool>,
+    pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
+    pub separator: Option<String>,
+    pub format: FormatTemplate,
+    pub services: Vec<String>,
+    pub up_color: CrabFetchColor,
+    pub down_color: CrabFetchColor,
+    pub list_separator: String,
+}
+impl Module for ServicesInfo {
+    fn new() -> ServicesInfo {
+        ServicesInfo {
+            services: Vec::new(),
+        }
+    }
+
+    fn style(&self, config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.services.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.services.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.services.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.services.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.services.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = self.replace_placeholders(&config.services.title, config);
+        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.services.format, config), config);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
+    }
+    fn unknown_output(config: &Configuration) -> (String, String) {
+        let title_color: &CrabFetchColor = config.services.title_color.as_ref().unwrap_or(&config.title_color);
+        let title_bold: bool = config.services.title_bold.unwrap_or(config.title_bold);
+        let title_italic: bool = config.services.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.services.hide_title.unwrap_or(!config.titles);
+        let separator: &str = config.services.separator.as_ref().unwrap_or(&config.separator);
+
+        let title: String = config.services.title.render_values(&[
+            ("up", "Unknown"),
+            ("down", "Unknown"),
+            ("total", "Unknown"),
+            ("list", "Unknown"),
+            ("down_list", "Unknown"),
+        ]);
+
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
+    }
+
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
+        let up: usize = self.services.iter().filter(|x| x.up).count();
+        text.render(|x| match x {
+            "up" => Some(up.to_string()),
+            "down" => Some((self.services.len() - up).to_string()),
+            "total" => Some(self.services.len().to_string()),
+            "list" => Some(self.services.iter()
+                .map(|y| {
+                    let color: &CrabFetchColor = if y.up { &config.services.up_color } else { &config.services.down_color };
+                    color.color_string(&y.name).to_string()
+                })
+                .collect::<Vec<String>>()
+                .join(&config.services.list_separator)),
+            "down_list" => Some(self.services.iter()
+                .filter(|y| !y.up)
+                .map(|y| config.services.down_color.color_string(&y.name).to_string())
+                .collect::<Vec<String>>()
+                .join(&config.services.list_separator)),
+            _ => None,
+        })
+    }
+
+    fn gen_info_flags(_: &str) -> u32 {
+        panic!("gen_info_flags called on services module. This should never happen, please make a bug report!")
+    }
+}
+
+pub fn get_services(config: &Configuration) -> Result<ServicesInfo, ModuleError> {
+    if config.services.services.is_empty() {
+        return Err(ModuleError::new("Services", "No services to check, add some to services.services".to_string()));
+    }
+
+    // Only scanned if something isn't a systemd unit, and then only the once
+    let mut processes: Option<Vec<String>> = None;
+    let services: Vec<ServiceStatus> = config.services.services.iter().map(|name| {
+        let up: bool = match unit_running(name) {
+            Some(r) => r,
+            // Anything with a suffix (e.g nginx.service) is definitely a unit, so it's not running
+            None if name.contains('.') => false,
+            None => {
+                let processes: &Vec<String> = processes.get_or_insert_with(process_names);
+                processes.iter().any(|x| is_process_name(x, name))
+            },
+        };
+        ServiceStatus {
+            name: name.to_string(),
+            up,
+        }
+    }).collect();
+
+    Ok(ServicesInfo {
+        services,
+    })
+}
+
+// Goes by whether the unit's cgroup has any processes in it, rather than asking systemctl, which
+// is far slower
+// None if there's no cgroup for it at all, e.g it's not a unit or systemd isn't being used
+fn unit_running(name: &str) -> Option<bool> {
+    let unit: String = if name.contains('.') { name.to_string() } else { format!("{}.service", name) };
+
+    // cgroup v2, then v1
+    if let Ok(r) = util::file_read(Path::new(&format!("/sys/fs/cgroup/system.slice/{}/cgroup.events", unit))) {
+        return Some(is_populated(&r));
+    }
+    util::file_read(Path::new(&format!("/sys/fs/cgroup/systemd/system.slice/{}/cgroup.procs", unit))).ok()
+        .map(|x| !x.trim().is_empty())
+}
+fn is_populated(events: &str) -> bool {
+    events.lines().any(|x| x.trim() == "populated 1")
+}
+
+fn process_names() -> Vec<String> {
+    let dir: ReadDir = match fs::read_dir("/proc") {
+        Ok(r) => r,
+        Err(_) => return Vec::new(),
+    };
+
+    dir.filter_map(|x| x.ok())
+        .filter(|x| x.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter_map(|x| util::file_read(&x.path().join("comm")).ok())
+        .map(|x| x.trim().to_string())
+        .collect()
+}
+// The kernel cuts process names down to 15 characters
+fn is_process_name(process: &str, name: &str) -> bool {
+    process == name || (name.len() > 15 && process.len() == 15 && name.starts_with(process))
+}
+
+
+mod tests {
+    // Test units are up when their cgroup has processes, and process names match even when cut short
+    #[test]
+    fn service_status() {
+        assert!(super::is_populated("populated 1\nfrozen 0\n"));
+        assert!(!super::is_populated("populated 0\nfrozen 0\n"));
+        assert!(super::is_process_name("nginx", "nginx"));
+        assert!(!super::is_process_name("nginx", "ngin"));
+        assert!(super::is_process_name("syncthing-relay", "syncthing-relaysrv"));
+    }
+}
@@ -1,9 +1,10 @@
 use std::env;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::is_flag_set_u32, versions, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::is_flag_set_u32, versions, ModuleError};
 
+#[derive(Serialize)]
 pub struct ShellInfo {
     name: String,
     path: String,
@@ -11,12 +12,13 @@ pub struct ShellInfo {
 }
 #[derive(Deserialize)]
 pub struct ShellConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: String,
+    pub format: FormatTemplate,
     pub show_default_shell: bool
 }
 impl Module for ShellInfo {
@@ -32,32 +34,38 @@ impl Module for ShellInfo {
         let title_color: &CrabFetchColor = config.shell.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.shell.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.shell.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.shell.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.shell.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.shell.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.shell.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) {
         let title_color: &CrabFetchColor = config.shell.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.shell.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.shell.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.shell.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.shell.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.shell.title
-            .replace("{name}", "Unknown")
-            .replace("{path}", "Unknown")
-            .replace("{version}", "Unknown")
-            .replace("{percentage}", "Unknown");
+        let title: String = config.shell.title.render_values(&[
+            ("name", "Unknown"),
+            ("path", "Unknown"),
+            ("version", "Unknown"),
+            ("percentage", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{name}", &self.name)
-            .replace("{path}", &self.path)
-            .replace("{version}", &self.version)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "path" => Some(self.path.to_string()),
+            "version" => Some(self.version.to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -112,7 +120,7 @@ pub const KNOWN_SHELLS: &[&str] = &[
 
 pub fn get_shell(config: &Configuration, package_managers: &ManagerInfo) -> Result<ShellInfo, ModuleError> {
     let mut shell: ShellInfo = ShellInfo::new();
-    let info_flags: u32 = ShellInfo::gen_info_flags(&config.shell.format);
+    let info_flags: u32 = ShellInfo::get_info_flags(config.shell.format.source(), config);
 
     if config.shell.show_default_shell {
         return get_default_shell(info_flags, config.use_version_checksums, package_managers);
@@ -170,6 +178,10 @@ pub fn get_shell(config: &Configuration, package_managers: &ManagerInfo) -> Resu
         if is_flag_set_u32(info_flags, SHELL_INFOFLAG_PATH) {
             shell.path = match parent_process.get_exe(true) {
                 Ok(r) => r,
+                Err(_) if crate::util::is_permission_denied(std::path::Path::new(&format!("/proc/{}/exe", parent_process.pid))) => {
+                    crate::module::hint_permission("Shell", "{path}, {version}", "root, as it's running as another user");
+                    return Ok(shell);
+                },
                 Err(e) => return Err(ModuleError::new("Shell", format!("Failed to find exe path: {}", e)))
             };
         }
@@ -1,9 +1,10 @@
 use core::str;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor}, module::Module, syscalls::SyscallCache, ModuleError};
+use crate::{config_manager::Configuration, formatter::{self, CrabFetchColor, FormatTemplate}, module::Module, syscalls::SyscallCache, ModuleError};
 
+#[derive(Serialize)]
 pub struct SwapInfo {
     used_kb: u64,
     total_kb: u64,
@@ -11,10 +12,11 @@ pub struct SwapInfo {
 }
 #[derive(Deserialize)]
 pub struct SwapConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
     pub progress_left_border: Option<String>,
     pub progress_right_border: Option<String>,
@@ -23,7 +25,7 @@ pub struct SwapConfiguration {
     pub progress_target_length: Option<u8>,
     pub decimal_places: Option<u32>,
     pub use_ibis: Option<bool>,
-    pub format: String
+    pub format: FormatTemplate
 }
 impl Module for SwapInfo {
     fn new() -> SwapInfo {
@@ -38,45 +40,55 @@ impl Module for SwapInfo {
         let title_color: &CrabFetchColor = config.swap.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.swap.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.swap.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.swap.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.swap.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.swap.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.swap.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) { 
         let title_color: &CrabFetchColor = config.swap.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.swap.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.swap.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.swap.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.swap.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.uptime.title
-            .replace("{used}", "Unknown")
-            .replace("{total}", "Unknown")
-            .replace("{bar}", "Unknown");
+        let title: String = config.swap.title.render_values(&[
+            ("used", "Unknown"),
+            ("total", "Unknown"),
+            ("bar", "Unknown"),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, config: &Configuration) -> String {
+    fn replace_placeholders(&self, text: &FormatTemplate, config: &Configuration) -> String {
         let dec_places: u32 = config.swap.decimal_places.unwrap_or(config.decimal_places);
         let use_ibis: bool = config.swap.use_ibis.unwrap_or(config.use_ibis);
 
-        let mut bar: String = String::new();
-        if text.contains("{bar}") {
-            let left_border: &str = config.swap.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
-            let right_border: &str = config.swap.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
-            let progress: &str = config.swap.progress_progress.as_ref().unwrap_or(&config.progress_progress);
-            let empty: &str = config.swap.progress_empty.as_ref().unwrap_or(&config.progress_empty);
-            let length: u8 = config.swap.progress_target_length.unwrap_or(config.progress_target_length);
-            formatter::make_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length);
-        }
-
-        formatter::process_percentage_placeholder(text, formatter::round(self.percent as f64, dec_places) as f32, config)
-            .replace("{used}", &formatter::auto_format_bytes(self.used_kb, use_ibis, dec_places))
-            .replace("{total}", &formatter::auto_format_bytes(self.total_kb, use_ibis, dec_places))
-            .replace("{bar}", &bar)
+        text.render_with_places(dec_places, |x, dec_places| match x {
+            "percent" => Some(formatter::format_percentage(formatter::round(self.percent as f64, dec_places) as f32, config)),
+            "used" => Some(formatter::auto_format_bytes(self.used_kb, use_ibis, dec_places, config.unit_space)),
+            "total" => Some(formatter::auto_format_bytes(self.total_kb, use_ibis, dec_places, config.unit_space)),
+            "bar" | "bar_with_percent" => {
+                let left_border: &str = config.swap.progress_left_border.as_ref().unwrap_or(&config.progress_left_border);
+                let right_border: &str = config.swap.progress_right_border.as_ref().unwrap_or(&config.progress_right_border);
+                let progress: &str = config.swap.progress_progress.as_ref().unwrap_or(&config.progress_progress);
+                let empty: &str = config.swap.progress_empty.as_ref().unwrap_or(&config.progress_empty);
+                let length: u8 = config.swap.progress_target_length.unwrap_or(config.progress_target_length);
+
+                let mut bar: String = String::new();
+                if x == "bar_with_percent" || config.progress_show_percent {
+                    formatter::make_percent_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length, dec_places, config);
+                } else {
+                    formatter::make_styled_bar(&mut bar, left_border, right_border, progress, empty, self.percent, length, config);
+                }
+                Some(bar)
+            },
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
@@ -1,24 +1,24 @@
-use std::env;
+use std::{env, path::Path};
 
-#[cfg(feature = "android")]
-use std::path::Path;
+use serde::{Deserialize, Serialize};
 
-use serde::Deserialize;
-
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::{self, is_flag_set_u32}, versions, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::{self, Module}, package_managers::ManagerInfo, proccess_info::ProcessInfo, util::{self, is_flag_set_u32}, versions, ModuleError};
 
+#[derive(Serialize)]
 pub struct TerminalInfo {
     name: String,
     path: String,
-    version: String
+    version: String,
+    via: Vec<String>,
 }
 #[derive(Deserialize)]
 pub struct TerminalConfiguration {
-    pub title: String,
-    pub format: String,
+    pub title: FormatTemplate,
+    pub format: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
 }
 impl Module for TerminalInfo {
@@ -27,6 +27,7 @@ impl Module for TerminalInfo {
             name: "Unknown".to_string(),
             path: "Unknown".to_string(),
             version: "Unknown".to_string(),
+            via: Vec::new(),
         }
     }
 
@@ -34,31 +35,39 @@ impl Module for TerminalInfo {
         let title_color: &CrabFetchColor = config.terminal.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.terminal.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.terminal.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.terminal.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.terminal.separator.as_ref().unwrap_or(&config.separator);
 
         let title: String = self.replace_placeholders(&config.terminal.title, config);
         let value: String = self.replace_color_placeholders(&self.replace_placeholders(&config.terminal.format, config), config);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) { 
         let title_color: &CrabFetchColor = config.terminal.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.terminal.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.terminal.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.terminal.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.terminal.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.terminal.title
-            .replace("{name}", "Unknown")
-            .replace("{path}", "Unknown")
-            .replace("{version}", "Unknown");
+        let title: String = config.terminal.title.render_values(&[
+            ("name", "Unknown"),
+            ("path", "Unknown"),
+            ("version", "Unknown"),
+            ("via", ""),
+        ]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{name}", &self.name)
-            .replace("{path}", &self.path)
-            .replace("{version}", &self.version)
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "name" => Some(self.name.to_string()),
+            "path" => Some(self.path.to_string()),
+            "version" => Some(self.version.to_string()),
+            "via" => Some(self.via.iter().map(|x| format!(" via {}", x)).collect()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(format: &str) -> u32 {
@@ -83,7 +92,7 @@ impl Module for TerminalInfo {
 }
 
 // A list of known terminals, similar to shell we keep going up until we encouter one
-const KNOWN_TERMS: &[&str] = &[
+pub const KNOWN_TERMS: &[&str] = &[
     "alacritty", 
     "fbpad",
     "fbterm",
@@ -105,7 +114,21 @@ const KNOWN_TERMS: &[&str] = &[
     "qterminal",
     "terminology",
     "weston-terminal",
-    "tmux"
+];
+// Things that sit between the terminal and the shell
+// These are shown in {via}, but we keep going to find the real terminal behind them
+const MULTIPLEXERS: &[&str] = &[
+    "tmux",
+    "screen",
+    "zellij",
+];
+// What runs processes inside of a container, if we hit these the terminal's on the other side
+const CONTAINER_RUNTIMES: &[&str] = &[
+    "conmon",
+    "crun",
+    "runc",
+    "containerd-shim",
+    "containerd-shim-runc-v2",
 ];
 
 const TERM_INFOFLAG_NAME: u32 = 1;
@@ -114,7 +137,7 @@ const TERM_INFOFLAG_VERSION: u32 = 4;
 
 pub fn get_terminal(config: &Configuration, package_managers: &ManagerInfo) -> Result<TerminalInfo, ModuleError> {
     let mut terminal: TerminalInfo = TerminalInfo::new();
-    let info_flags: u32 = TerminalInfo::gen_info_flags(&config.terminal.format);
+    let info_flags: u32 = TerminalInfo::get_info_flags(config.terminal.format.source(), config);
 
     #[cfg(feature = "android")]
     if env::consts::OS == "android" && Path::new("/data/data/com.termux/files/").exists() { // TODO: Does this still work in other emulators?
@@ -146,38 +169,77 @@ pub fn get_terminal(config: &Configuration, package_managers: &ManagerInfo) -> R
 
     // This is just a rust-ified & slightly more robust solution from https://askubuntu.com/a/508047
     // Find the terminal's PID by going up through every shell level
+    // Anything like tmux or a container along the way is noted down for {via}
     let mut terminal_process: Option<ProcessInfo> = None;
 
     let mut loops = 0; // always use protection against infinite loops kids
     let mut parent_process: ProcessInfo = ProcessInfo::new_from_parent();
-    let mut found: bool = false;
-    while !found {
-        if loops > 10 {
-            return Err(ModuleError::new("Terminal", "Terminal PID loop ran for more than 10 iterations! Either I'm in a infinite loop, or you're >10 shells deep, in which case you're a moron.".to_string()));
+    loop {
+        if loops > 15 {
+            return Err(ModuleError::new("Terminal", "Terminal PID loop ran for more than 15 iterations! Either I'm in a infinite loop, or you're >15 shells deep, in which case you're a moron.".to_string()));
         }
         loops += 1;
 
+        // Hit init, so there's no terminal above us we can see
+        if parent_process.pid <= 1 {
+            break;
+        }
+
         terminal.name = match parent_process.get_process_name() {
-            Ok(r) => r.to_string(),
+            Ok(r) => r,
             Err(e) => return Err(ModuleError::new("Terminal", format!("Can't get process name: {}", e))),
         };
-        if !KNOWN_TERMS.contains(&terminal.name.as_str()) {
-            // go up a level
-            parent_process = match parent_process.get_parent_process() {
-                Ok(r) => r,
-                Err(e) => return Err(ModuleError::new("Terminal", format!("Can't get parent process: {}", e))),
-            };
-
-            continue;
+        if KNOWN_TERMS.contains(&terminal.name.as_str()) {
+            terminal_process = Some(parent_process.clone());
+            break;
         }
 
-        terminal_process = Some(parent_process.clone());
-        found = true;
+        if MULTIPLEXERS.contains(&terminal.name.as_str()) {
+            if !terminal.via.contains(&terminal.name) {
+                terminal.via.insert(0, terminal.name.to_string());
+            }
+            // The tmux server is detached from everything, so jump over to the client that's
+            // actually attached to our terminal
+            if terminal.name == "tmux" {
+                if let Some(client) = get_tmux_client() {
+                    parent_process = client;
+                    continue;
+                }
+            }
+        }
+
+        if CONTAINER_RUNTIMES.contains(&terminal.name.as_str()) {
+            // Past here is the runtime's own processes, not whatever started the container
+            break;
+        }
+
+        // go up a level
+        parent_process = match parent_process.get_parent_process() {
+            Ok(r) => r,
+            Err(_) => break,
+        };
     }
 
+    // Containers cut off the process tree before we get to tmux, but it'll still have left it's
+    // env behind
+    if env::var("TMUX").is_ok() && !terminal.via.iter().any(|x| x == "tmux") {
+        terminal.via.insert(0, "tmux".to_string());
+    }
+    if let Some(container) = util::detect_container() {
+        terminal.via.push(container.to_string());
+    }
 
+    // Couldn't get it from the process tree, e.g we're inside a container or the multiplexer's
+    // server is detached, so see if the terminal left anything behind in our environment
     if terminal_process.is_none() {
-        return Err(ModuleError::new("Terminal", "Was unsuccessfull in finding Terminal process.".to_string()));
+        match terminal_from_env() {
+            Some(r) => {
+                terminal.name = r;
+                terminal.path = "Unknown".to_string();
+                return Ok(terminal);
+            },
+            None => return Err(ModuleError::new("Terminal", "Was unsuccessfull in finding Terminal process.".to_string())),
+        }
     }
     let mut terminal_process: ProcessInfo = terminal_process.unwrap();
     if !terminal_process.is_valid() {
@@ -204,6 +266,11 @@ pub fn get_terminal(config: &Configuration, package_managers: &ManagerInfo) -> R
     if is_flag_set_u32(info_flags, TERM_INFOFLAG_PATH) {
         terminal.path = match terminal_process.get_exe(true) {
             Ok(r) => r,
+            // e.g the terminal's running as another user, we can still show the name
+            Err(_) if util::is_permission_denied(Path::new(&format!("/proc/{}/exe", terminal_process.pid))) => {
+                module::hint_permission("Terminal", "{path}, {version}", "root, as it's running as another user");
+                return Ok(terminal);
+            },
             Err(e) => return Err(ModuleError::new("Terminal", format!("Can't get process exe: {}", e))),
         };
     }
@@ -214,3 +281,41 @@ pub fn get_terminal(config: &Configuration, package_managers: &ManagerInfo) -> R
 
     Ok(terminal)
 }
+
+fn get_tmux_client() -> Option<ProcessInfo> {
+    env::var("TMUX").ok()?;
+    let output: String = util::run_command("tmux", &["display-message", "-p", "#{client_pid}"], 500).ok()?;
+    let pid: u32 = output.trim().parse().ok()?;
+
+    let mut client: ProcessInfo = ProcessInfo::new(pid);
+    if !client.is_valid() {
+        return None;
+    }
+    // Start from the client's parent, otherwise we'd just find tmux again
+    client.get_parent_process().ok()
+}
+
+// Most terminals set at least one of these, and they tend to survive being passed through into
+// containers and multiplexers
+fn terminal_from_env() -> Option<String> {
+    const ENV_HINTS: &[(&str, &str)] = &[
+        ("KITTY_WINDOW_ID", "kitty"),
+        ("ALACRITTY_WINDOW_ID", "alacritty"),
+        ("WEZTERM_PANE", "wezterm"),
+        ("KONSOLE_VERSION", "konsole"),
+        ("TERMINATOR_UUID", "terminator"),
+        ("TILIX_ID", "tilix"),
+        ("GNOME_TERMINAL_SCREEN", "GNOME Terminal"),
+    ];
+    for (var, name) in ENV_HINTS {
+        if env::var(var).is_ok() {
+            return Some(name.to_string());
+        }
+    }
+
+    match env::var("TERM_PROGRAM") {
+        // The multiplexers set this to themselves, which isn't what we're after
+        Ok(r) if !r.is_empty() && !MULTIPLEXERS.contains(&r.as_str()) => Some(r),
+        _ => None,
+    }
+}
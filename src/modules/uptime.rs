@@ -1,21 +1,30 @@
 use std::{path::Path, time::Duration};
 
 use humantime::format_duration;
-use serde::Deserialize;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
-use crate::{config_manager::Configuration, formatter::CrabFetchColor, module::Module, syscalls::SyscallCache, util, ModuleError};
+use crate::{config_manager::Configuration, formatter::{CrabFetchColor, FormatTemplate}, module::Module, syscalls::SyscallCache, util, ModuleError};
 
 pub struct UptimeInfo {
     uptime: Duration,
 }
+// Done by hand to output it as seconds, Duration's default is a bit odd for anyone reading it
+impl Serialize for UptimeInfo {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("UptimeInfo", 1)?;
+        state.serialize_field("uptime_seconds", &self.uptime.as_secs())?;
+        state.end()
+    }
+}
 #[derive(Deserialize)]
 pub struct UptimeConfiguration {
-    pub title: String,
+    pub title: FormatTemplate,
     pub title_color: Option<CrabFetchColor>,
     pub title_bold: Option<bool>,
     pub title_italic: Option<bool>,
+    pub hide_title: Option<bool>,
     pub separator: Option<String>,
-    pub format: Option<String>,
+    pub format: Option<FormatTemplate>,
 }
 impl Module for UptimeInfo {
     fn new() -> UptimeInfo {
@@ -28,27 +37,34 @@ impl Module for UptimeInfo {
         let title_color: &CrabFetchColor = config.uptime.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.uptime.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.uptime.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.uptime.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.uptime.separator.as_ref().unwrap_or(&config.separator);
 
-        let format: String = config.uptime.format.clone().unwrap_or("{time}".to_string());
         let title: String = self.replace_placeholders(&config.uptime.title, config);
-        let value: String = self.replace_color_placeholders(&self.replace_placeholders(&format, config), config);
+        let value: String = match &config.uptime.format {
+            Some(format) => self.replace_color_placeholders(&self.replace_placeholders(format, config), config),
+            None => format_duration(self.uptime).to_string(),
+        };
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, &value)
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, &value)
     }
     fn unknown_output(config: &Configuration) -> (String, String) { 
         let title_color: &CrabFetchColor = config.uptime.title_color.as_ref().unwrap_or(&config.title_color);
         let title_bold: bool = config.uptime.title_bold.unwrap_or(config.title_bold);
         let title_italic: bool = config.uptime.title_italic.unwrap_or(config.title_italic);
+        let hide_title: bool = config.uptime.hide_title.unwrap_or(!config.titles);
         let separator: &str = config.uptime.separator.as_ref().unwrap_or(&config.separator);
 
-        let title: String = config.uptime.title.replace("{time}", "Unknown");
+        let title: String = config.uptime.title.render_values(&[("time", "Unknown")]);
 
-        Self::default_style(config, &title, title_color, title_bold, title_italic, separator, "Unknown")
+        Self::default_style(config, &title, title_color, title_bold, title_italic, hide_title, separator, "Unknown")
     }
 
-    fn replace_placeholders(&self, text: &str, _: &Configuration) -> String {
-        text.replace("{time}", &format_duration(self.uptime).to_string())
+    fn replace_placeholders(&self, text: &FormatTemplate, _: &Configuration) -> String {
+        text.render(|x| match x {
+            "time" => Some(format_duration(self.uptime).to_string()),
+            _ => None,
+        })
     }
 
     fn gen_info_flags(_: &str) -> u32 {
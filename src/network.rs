@@ -0,0 +1,170 @@
+// Shared bits for modules that need to go out to the internet, e.g publicip
+// Everything they get is cached, and nothing's allowed to take longer than network_timeout, so a
+// slow or missing connection can never hold up the rest of the fetch
+
+use std::{env, fs, io::{Read, Write}, net::{SocketAddr, TcpStream, ToSocketAddrs}, path::{Path, PathBuf}, sync::mpsc, thread, time::Duration};
+
+use crate::{config_manager::Configuration, util};
+
+// Where the network modules keep their results between runs
+pub fn cache_dir() -> Option<PathBuf> {
+    match env::var("XDG_CACHE_HOME") {
+        Ok(r) => Some(PathBuf::from(r).join("CrabFetch")),
+        Err(_) => env::var("HOME").ok().map(|x| PathBuf::from(x).join(".cache/CrabFetch")),
+    }
+}
+
+// Whether there's a default route out of the machine
+// The routing tables are read instead of trying a connection, as waiting on one is what we're trying
+// to avoid in the first place
+pub fn is_online() -> bool {
+    if util::file_read(Path::new("/proc/net/route")).is_ok_and(|x| has_default_route_v4(&x)) {
+        return true;
+    }
+    util::file_read(Path::new("/proc/net/ipv6_route")).is_ok_and(|x| has_default_route_v6(&x))
+}
+// Each line is; interface, destination, gateway, flags, ...
+fn has_default_route_v4(contents: &str) -> bool {
+    // RTF_UP, from linux/route.h
+    const UP_FLAG: u32 = 0x1;
+
+    contents.lines().skip(1).any(|x| {
+        let values: Vec<&str> = x.split_whitespace().collect();
+        values.len() > 3 && values[1] == "00000000" && u32::from_str_radix(values[3], 16).is_ok_and(|x| x & UP_FLAG != 0)
+    })
+}
+// Each line is; destination, prefix length, source, source prefix length, next hop, metric, refs, use, flags, interface
+// The kernel puts unreachable defaults on lo, so those don't count
+fn has_default_route_v6(contents: &str) -> bool {
+    contents.lines().any(|x| {
+        let values: Vec<&str> = x.split_whitespace().collect();
+        values.len() > 9 && values[0].chars().all(|x| x == '0') && values[1] == "00" && values[9] != "lo"
+    })
+}
+
+// Gets a network module's value, straight from it's cache if that's newer than `ttl` seconds
+// Otherwise `fetch` is ran on it's own thread and given up on after network_timeout, so that it can't
+// stall us even if DNS hangs. If that fails, or there's no connection, the old cached value is used
+pub fn fetch_cached<F>(name: &str, ttl: u64, config: &Configuration, fetch: F) -> Result<String, String>
+where F: FnOnce() -> Result<String, String> + Send + 'static {
+    if !config.network_modules {
+        return Err("Network modules are turned off by network_modules".to_string());
+    }
+
+    let cache_path: Option<PathBuf> = cache_dir().map(|x| x.join(name));
+    let cached: Option<(String, Duration)> = cache_path.as_ref().and_then(|x| read_cache(x));
+    if let Some((value, age)) = &cached {
+        if age.as_secs() < ttl {
+            return Ok(value.to_string());
+        }
+    }
+
+    let fresh: Result<String, String> = match is_online() {
+        true => run_with_timeout(fetch, Duration::from_millis(config.network_timeout)),
+        false => Err("No network connection".to_string()),
+    };
+    match fresh {
+        Ok(r) => {
+            if let Some(path) = &cache_path {
+                // Not being able to cache it isn't worth failing over
+                let _ = write_cache(path, &r);
+            }
+            Ok(r)
+        },
+        Err(e) => match cached {
+            Some((value, _)) => Ok(value),
+            None => Err(e),
+        },
+    }
+}
+
+fn read_cache(path: &Path) -> Option<(String, Duration)> {
+    let age: Duration = fs::metadata(path).ok()?.modified().ok()?.elapsed().unwrap_or_default();
+    let value: String = util::file_read(path).ok()?;
+
+    Some((value, age))
+}
+fn write_cache(path: &Path, value: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, value).map_err(|e| e.to_string())
+}
+
+// If it runs over, the thread's left to finish by itself, it'll get taken down when we exit anyway
+fn run_with_timeout<F>(fetch: F, timeout: Duration) -> Result<String, String>
+where F: FnOnce() -> Result<String, String> + Send + 'static {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(fetch());
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(r) => r,
+        Err(_) => Err(format!("Timed out after {}ms", timeout.as_millis())),
+    }
+}
+
+// A bare bones plain HTTP GET, which is all the simple text APIs the network modules use need
+pub fn http_get(host: &str, path: &str, timeout: Duration) -> Result<String, String> {
+    let addr: SocketAddr = match (host, 80).to_socket_addrs() {
+        Ok(mut r) => match r.next() {
+            Some(r) => r,
+            None => return Err(format!("Can't resolve {}", host)),
+        },
+        Err(e) => return Err(format!("Can't resolve {}: {}", host, e)),
+    };
+    let mut stream: TcpStream = match TcpStream::connect_timeout(&addr, timeout) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("Can't connect to {}: {}", host, e)),
+    };
+    stream.set_read_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(timeout)).map_err(|e| e.to_string())?;
+
+    // HTTP 1.0 so that it closes the connection once it's done, rather than us having to parse
+    // the length out
+    let request: String = format!("GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: CrabFetch\r\n\r\n", path, host);
+    stream.write_all(request.as_bytes()).map_err(|e| format!("Failed to send request to {}: {}", host, e))?;
+    let mut response: String = String::new();
+    stream.read_to_string(&mut response).map_err(|e| format!("Failed to read response from {}: {}", host, e))?;
+
+    parse_http_response(&response)
+}
+fn parse_http_response(response: &str) -> Result<String, String> {
+    let (head, body) = match response.split_once("\r\n\r\n") {
+        Some(r) => r,
+        None => return Err("Malformed HTTP response".to_string()),
+    };
+    let status: &str = head.split_whitespace().nth(1).unwrap_or("Unknown");
+    if status != "200" {
+        return Err(format!("Got HTTP status {}", status));
+    }
+
+    Ok(body.to_string())
+}
+
+
+mod tests {
+    // Test default routes are found in both routing tables, ignoring the ones on lo
+    #[test]
+    fn default_routes() {
+        let v4: &str = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0002A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0";
+        assert!(super::has_default_route_v4(v4));
+        assert!(!super::has_default_route_v4(v4.lines().take(1).chain(v4.lines().skip(2)).collect::<Vec<&str>>().join("\n").as_str()));
+
+        let v6: &str = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000001 00200200       lo
+fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0";
+        assert!(!super::has_default_route_v6(v6));
+        assert!(super::has_default_route_v6("00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003     eth0"));
+    }
+
+    // Test the body's pulled out of a response, and anything but a 200 is an error
+    #[test]
+    fn http_response() {
+        assert_eq!(super::parse_http_response("HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\n203.0.113.5\n"), Ok("203.0.113.5\n".to_string()));
+        assert!(super::parse_http_response("HTTP/1.0 429 Too Many Requests\r\n\r\nslow down").is_err());
+        assert!(super::parse_http_response("garbage").is_err());
+    }
+}
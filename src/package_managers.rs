@@ -1,7 +1,7 @@
 // Queries and caches package manager entries to prevent duplicate work between Packages module and
 // Version detection 
 
-use std::{collections::HashMap, ffi::OsStr, fs::{read_dir, DirEntry, File, ReadDir}, io::{BufRead, BufReader}, path::PathBuf};
+use std::{collections::HashMap, ffi::OsStr, fs::{read_dir, DirEntry, File, ReadDir}, io::{BufRead, BufReader}, path::{Path, PathBuf}};
 
 pub struct PackageInfo {
     pub name: String,
@@ -31,22 +31,27 @@ impl ManagerInfo {
     }
 
     pub fn probe_and_cache(&mut self) {
-        if let Some(pacman) = Self::process_pacman_packages() {
+        self.probe_and_cache_system(Path::new("/"));
+        if let Some(brew) = Self::process_homebrew_packages() {
+            self.available_managers += MANAGER_HOMEBREW;
+            self.packages.extend(brew);
+        }
+    }
+    // Only the system package managers, with `root` being where the system is mounted
+    // Used to look at the host from inside of a container
+    pub fn probe_and_cache_system(&mut self, root: &Path) {
+        if let Some(pacman) = Self::process_pacman_packages(root) {
             self.available_managers += MANAGER_PACMAN;
             self.packages.extend(pacman);
         }
-        if let Some(dpkg) = Self::process_dpkg_packages() {
+        if let Some(dpkg) = Self::process_dpkg_packages(root) {
             self.available_managers += MANAGER_DPKG;
             self.packages.extend(dpkg);
         }
-        if let Some(xbps) = Self::process_xbps_packages() {
+        if let Some(xbps) = Self::process_xbps_packages(root) {
             self.available_managers += MANAGER_XBPS;
             self.packages.extend(xbps);
         }
-        if let Some(brew) = Self::process_homebrew_packages() {
-            self.available_managers += MANAGER_HOMEBREW;
-            self.packages.extend(brew);
-        }
     }
 
     pub fn find_all_packages_from(&self, manager: u8) -> HashMap<&String, &PackageInfo> {
@@ -56,8 +61,8 @@ impl ManagerInfo {
     }
 
     // Credit for Pacman, Flatpak and DPKG detection goes to FastFetch, they were big brain while I was running pacman -Q like a dummy
-    fn process_pacman_packages() -> Option<HashMap<String, PackageInfo>> {
-        let dir: ReadDir = match read_dir("/var/lib/pacman/local") {
+    fn process_pacman_packages(root: &Path) -> Option<HashMap<String, PackageInfo>> {
+        let dir: ReadDir = match read_dir(root.join("var/lib/pacman/local")) {
             Ok(r) => r,
             Err(_) => return None,
         };
@@ -96,13 +101,13 @@ impl ManagerInfo {
 
         Some(packages)
     }
-    fn process_dpkg_packages() -> Option<HashMap<String, PackageInfo>> {
+    fn process_dpkg_packages(root: &Path) -> Option<HashMap<String, PackageInfo>> {
         let file_path: &str = if cfg!(not(feature = "android")) { 
             "/var/lib/dpkg/status"
         } else {
             "/data/data/com.termux/files/usr/var/lib/dpkg/status"
         };
-        let file: File = match File::open(file_path) {
+        let file: File = match File::open(root.join(file_path.trim_start_matches('/'))) {
             Ok(r) => r,
             Err(_) => return None,
         };
@@ -147,8 +152,8 @@ impl ManagerInfo {
 
         Some(packages)
     }
-    fn process_xbps_packages() -> Option<HashMap<String, PackageInfo>> {
-        let file: File = match File::open("/var/db/xbps/pkgdb-0.38.plist") {
+    fn process_xbps_packages(root: &Path) -> Option<HashMap<String, PackageInfo>> {
+        let file: File = match File::open(root.join("var/db/xbps/pkgdb-0.38.plist")) {
             Ok(r) => r,
             Err(_) => return None,
         };
@@ -223,7 +228,7 @@ impl ManagerInfo {
                     let string: String = line[8..line.len() - 9].to_string();
                     // {package-name}-{ver}_{rev} 
                     let no_rev: &str = string.split('_').next().unwrap();
-                    let version: &str = no_rev.split('-').last().unwrap();
+                    let version: &str = no_rev.split('-').next_back().unwrap();
 
                     cur_package_info.version = version.to_string();
                     next_line_is_version_str = false;
@@ -234,19 +239,19 @@ impl ManagerInfo {
         Some(packages)
     }
 
-    pub fn process_flatpak_packages_count(&self) -> Option<u64> {
+    pub fn process_flatpak_packages_count(&self, root: &Path) -> Option<u64> {
         // This counts everything in /app and /runtime
         // This does NOT get full information, as I don't care enough about flatpak to figure out
         // how to process it. It's simply used in the packages module and nowhere else for now
         let mut result: usize = 0;
 
-        let flatpak_apps_dir: ReadDir = match read_dir("/var/lib/flatpak/app") {
+        let flatpak_apps_dir: ReadDir = match read_dir(root.join("var/lib/flatpak/app")) {
             Ok(r) => r,
             Err(_) => return None,
         };
         result += flatpak_apps_dir.count();
 
-        let flatpak_runtime_dir: ReadDir = match read_dir("/var/lib/flatpak/runtime") {
+        let flatpak_runtime_dir: ReadDir = match read_dir(root.join("var/lib/flatpak/runtime")) {
             Ok(r) => r,
             Err(_) => return None,
         };
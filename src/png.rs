@@ -0,0 +1,439 @@
+// A small PNG decoder, for the image logo backends that need the actual pixels (sixel etc)
+// Pulling in a whole image crate for this felt like overkill, as we only ever need to read one
+// small logo. Doesn't support interlaced images, but just about nothing uses them these days.
+//
+// References;
+// - https://www.w3.org/TR/png/
+// - https://www.rfc-editor.org/rfc/rfc1950 (zlib)
+// - https://www.rfc-editor.org/rfc/rfc1951 (deflate)
+// - https://github.com/madler/zlib/blob/develop/contrib/puff/puff.c
+
+// Always RGBA, 8 bits per channel
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i: usize = ((y * self.width + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    // Nearest neighbour, it's a logo so nothing fancier is needed
+    pub fn resize(&self, width: u32, height: u32) -> Image {
+        let mut pixels: Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let src_y: u32 = (y * self.height / height).min(self.height - 1);
+            for x in 0..width {
+                let src_x: u32 = (x * self.width / width).min(self.width - 1);
+                pixels.extend_from_slice(&self.pixel(src_x, src_y));
+            }
+        }
+
+        Image {
+            width,
+            height,
+            pixels
+        }
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Err("Not a PNG".to_string());
+    }
+
+    let mut width: u32 = 0;
+    let mut height: u32 = 0;
+    let mut bit_depth: u8 = 0;
+    let mut color_type: u8 = 0;
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut compressed: Vec<u8> = Vec::new();
+
+    let mut pos: usize = 8;
+    while pos + 8 <= data.len() {
+        let length: usize = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let chunk_type: &[u8] = &data[pos + 4..pos + 8];
+        let chunk: &[u8] = match data.get(pos + 8..pos + 8 + length) {
+            Some(r) => r,
+            None => return Err("Truncated chunk".to_string()),
+        };
+        pos += 12 + length; // + length, type and CRC
+
+        match chunk_type {
+            b"IHDR" => {
+                if chunk.len() < 13 {
+                    return Err("Invalid header".to_string());
+                }
+                width = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                height = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+                bit_depth = chunk[8];
+                color_type = chunk[9];
+                if chunk[12] != 0 {
+                    return Err("Interlaced PNG's aren't supported".to_string());
+                }
+            },
+            b"PLTE" => {
+                palette = chunk.chunks_exact(3)
+                    .map(|x| [x[0], x[1], x[2], 255])
+                    .collect();
+            },
+            b"tRNS" => {
+                // Only bother with it for paletted images
+                for (i, alpha) in chunk.iter().enumerate() {
+                    if let Some(r) = palette.get_mut(i) {
+                        r[3] = *alpha;
+                    }
+                }
+            },
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {},
+        }
+    }
+    if width == 0 || height == 0 {
+        return Err("Image has no size".to_string());
+    }
+
+    let channels: usize = match color_type {
+        0 | 3 => 1,
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => return Err(format!("Unknown color type {}", color_type)),
+    };
+    if !matches!(bit_depth, 1 | 2 | 4 | 8 | 16) {
+        return Err(format!("Unknown bit depth {}", bit_depth));
+    }
+
+    // zlib header is 2 bytes, adler32 checksum on the end is ignored
+    if compressed.len() < 2 {
+        return Err("No image data".to_string());
+    }
+    let raw: Vec<u8> = inflate(&compressed[2..])?;
+
+    // Undo the filtering on each scanline
+    let bits_per_pixel: usize = channels * bit_depth as usize;
+    let bytes_per_pixel: usize = bits_per_pixel.div_ceil(8);
+    let stride: usize = (width as usize * bits_per_pixel).div_ceil(8);
+    if raw.len() < (stride + 1) * height as usize {
+        return Err("Image data is too short".to_string());
+    }
+    let mut lines: Vec<u8> = vec![0; stride * height as usize];
+    for y in 0..height as usize {
+        let filter: u8 = raw[y * (stride + 1)];
+        let src: &[u8] = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let a: u8 = if x >= bytes_per_pixel {lines[y * stride + x - bytes_per_pixel]} else {0};
+            let b: u8 = if y > 0 {lines[(y - 1) * stride + x]} else {0};
+            let c: u8 = if x >= bytes_per_pixel && y > 0 {lines[(y - 1) * stride + x - bytes_per_pixel]} else {0};
+            lines[y * stride + x] = match filter {
+                0 => src[x],
+                1 => src[x].wrapping_add(a),
+                2 => src[x].wrapping_add(b),
+                3 => src[x].wrapping_add(((a as u16 + b as u16) / 2) as u8),
+                4 => src[x].wrapping_add(paeth(a, b, c)),
+                _ => return Err(format!("Unknown filter type {}", filter)),
+            };
+        }
+    }
+
+    // And finally, convert it all to RGBA
+    let mut pixels: Vec<u8> = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let line: &[u8] = &lines[y * stride..(y + 1) * stride];
+        for x in 0..width as usize {
+            // Each sample scaled down to 8 bits
+            let sample = |i: usize| -> u8 {
+                match bit_depth {
+                    8 => line[x * channels + i],
+                    16 => line[(x * channels + i) * 2],
+                    _ => {
+                        // Less than a byte per sample, only happens with grayscale/paletted
+                        let bit: usize = x * bit_depth as usize;
+                        let value: u8 = (line[bit / 8] >> (8 - bit_depth as usize - bit % 8)) & ((1 << bit_depth) - 1);
+                        if color_type == 3 {value} else {value * (255 / ((1 << bit_depth) - 1))}
+                    },
+                }
+            };
+            let rgba: [u8; 4] = match color_type {
+                0 => [sample(0), sample(0), sample(0), 255],
+                2 => [sample(0), sample(1), sample(2), 255],
+                3 => *palette.get(sample(0) as usize).unwrap_or(&[0, 0, 0, 0]),
+                4 => [sample(0), sample(0), sample(0), sample(1)],
+                _ => [sample(0), sample(1), sample(2), sample(3)],
+            };
+            pixels.extend_from_slice(&rgba);
+        }
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels
+    })
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p: i16 = a as i16 + b as i16 - c as i16;
+    let pa: i16 = (p - a as i16).abs();
+    let pb: i16 = (p - b as i16).abs();
+    let pc: i16 = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+
+//
+// Deflate
+//
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+// The order the code length code lengths come in, for dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u8,
+}
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32, String> {
+        let mut value: u32 = 0;
+        for i in 0..count {
+            let byte: u8 = match self.data.get(self.pos) {
+                Some(r) => *r,
+                None => return Err("Ran out of image data".to_string()),
+            };
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+
+        Ok(value)
+    }
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+struct Huffman {
+    counts: [u16; 16], // Amount of codes of each length
+    symbols: Vec<u16>, // Symbols, ordered by their code
+}
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts: [u16; 16] = [0; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets: [u16; 16] = [0; 16];
+        for i in 1..15 {
+            offsets[i + 1] = offsets[i] + counts[i];
+        }
+        let mut symbols: Vec<u16> = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Huffman {
+            counts,
+            symbols
+        }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count: i32 = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err("Invalid huffman code".to_string())
+    }
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader: BitReader = BitReader {
+        data,
+        pos: 0,
+        bit: 0
+    };
+    let mut out: Vec<u8> = Vec::new();
+
+    loop {
+        let last: bool = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                // Stored
+                reader.align();
+                let header: &[u8] = match data.get(reader.pos..reader.pos + 4) {
+                    Some(r) => r,
+                    None => return Err("Ran out of image data".to_string()),
+                };
+                let length: usize = u16::from_le_bytes([header[0], header[1]]) as usize;
+                reader.pos += 4;
+                match data.get(reader.pos..reader.pos + length) {
+                    Some(r) => out.extend_from_slice(r),
+                    None => return Err("Ran out of image data".to_string()),
+                }
+                reader.pos += length;
+            },
+            1 => {
+                // Fixed huffman codes
+                let mut lengths: [u8; 288] = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                inflate_block(&mut reader, &mut out, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            },
+            2 => {
+                // Dynamic huffman codes
+                let lit_count: usize = reader.bits(5)? as usize + 257;
+                let dist_count: usize = reader.bits(5)? as usize + 1;
+                let code_count: usize = reader.bits(4)? as usize + 4;
+
+                let mut code_lengths: [u8; 19] = [0; 19];
+                for i in CODE_LENGTH_ORDER.iter().take(code_count) {
+                    code_lengths[*i] = reader.bits(3)? as u8;
+                }
+                let code_huffman: Huffman = Huffman::new(&code_lengths);
+
+                let mut lengths: Vec<u8> = Vec::with_capacity(lit_count + dist_count);
+                while lengths.len() < lit_count + dist_count {
+                    let symbol: u16 = code_huffman.decode(&mut reader)?;
+                    let (value, repeat): (u8, u32) = match symbol {
+                        0..=15 => (symbol as u8, 1),
+                        16 => match lengths.last() {
+                            Some(r) => (*r, 3 + reader.bits(2)?),
+                            None => return Err("Repeat with no previous length".to_string()),
+                        },
+                        17 => (0, 3 + reader.bits(3)?),
+                        _ => (0, 11 + reader.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() > lit_count + dist_count {
+                    return Err("Too many code lengths".to_string());
+                }
+
+                inflate_block(&mut reader, &mut out, &Huffman::new(&lengths[..lit_count]), &Huffman::new(&lengths[lit_count..]))?;
+            },
+            _ => return Err("Invalid block type".to_string()),
+        }
+
+        if last {
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+fn inflate_block(reader: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman) -> Result<(), String> {
+    loop {
+        let symbol: u16 = lit.decode(reader)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index: usize = symbol as usize - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err("Invalid length symbol".to_string());
+                }
+                let length: usize = LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index])? as usize;
+
+                let index: usize = dist.decode(reader)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err("Invalid distance symbol".to_string());
+                }
+                let distance: usize = DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index])? as usize;
+                if distance > out.len() {
+                    return Err("Distance goes back too far".to_string());
+                }
+
+                // Has to be done byte by byte, as it can overlap with what we're writing
+                let start: usize = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            },
+        }
+    }
+}
+
+
+mod tests {
+    // Test all 3 deflate block types decompress right
+    #[test]
+    fn inflate_blocks() {
+        // Stored
+        let stored: Vec<u8> = [&[1, 5, 0, 250, 255][..], b"Crabs"].concat();
+        assert_eq!(super::inflate(&stored).unwrap(), b"Crabs");
+
+        // Fixed huffman codes, zlib's output for "CrabFetch" * 3 with the header taken off
+        let fixed: [u8; 18] = [115, 46, 74, 76, 114, 75, 45, 73, 206, 112, 198, 96, 0, 0, 140, 127, 10, 39];
+        assert_eq!(super::inflate(&fixed).unwrap(), b"CrabFetchCrabFetchCrabFetch");
+
+        // Dynamic huffman codes
+        let dynamic: [u8; 137] = [197, 206, 139, 21, 4, 17, 12, 64, 209, 90, 17, 100, 136, 79, 144, 33, 170, 223, 221, 42, 246, 22, 240, 206, 51, 198, 88, 7, 1, 19, 181, 33, 234, 98, 238, 114, 3, 141, 227, 105, 154, 135, 21, 249, 166, 5, 85, 147, 196, 233, 25, 24, 70, 88, 184, 201, 112, 220, 5, 132, 224, 173, 65, 71, 246, 58, 11, 186, 179, 58, 161, 183, 186, 101, 114, 111, 245, 167, 117, 158, 178, 213, 122, 164, 190, 142, 195, 50, 213, 231, 161, 161, 190, 64, 2, 101, 71, 54, 180, 113, 133, 241, 45, 179, 159, 81, 146, 86, 88, 233, 50, 42, 63, 102, 146, 63, 131, 194, 149, 158, 163, 83, 25, 141, 18, 6, 112, 214, 252, 252, 225, 250, 3, 48, 202, 128, 32];
+        let expected: Vec<u8> = (0..300_u32).map(|i| ((i * i / 7) % 26 + 97) as u8).collect();
+        assert_eq!(super::inflate(&dynamic).unwrap(), expected);
+
+        assert!(super::inflate(&[]).is_err());
+    }
+
+    // Test PNG's decode to the right RGBA pixels
+    #[test]
+    fn decode_png() {
+        // 2x2 RGBA, using the sub and up filters
+        let rgba: [u8; 83] = [137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 2, 0, 0, 0, 2, 8, 6, 0, 0, 0, 114, 182, 13, 36, 0, 0, 0, 26, 73, 68, 65, 84, 120, 218, 99, 252, 207, 192, 240, 159, 241, 63, 67, 35, 19, 35, 195, 127, 6, 46, 81, 185, 21, 0, 55, 111, 5, 104, 105, 99, 193, 236, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130];
+        let image: super::Image = super::decode(&rgba).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(1, 0), [0, 255, 0, 128]);
+        assert_eq!(image.pixel(0, 1), [0, 0, 255, 255]);
+        assert_eq!(image.pixel(1, 1), [10, 20, 30, 40]);
+
+        // 3x1 1-bit paletted, with the first color transparent
+        let paletted: [u8; 98] = [137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 3, 0, 0, 0, 1, 1, 3, 0, 0, 0, 33, 46, 134, 247, 0, 0, 0, 6, 80, 76, 84, 69, 1, 2, 3, 200, 100, 50, 80, 180, 174, 63, 0, 0, 0, 1, 116, 82, 78, 83, 0, 64, 230, 216, 102, 0, 0, 0, 10, 73, 68, 65, 84, 120, 218, 99, 88, 0, 0, 0, 162, 0, 161, 113, 5, 203, 65, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130];
+        let image: super::Image = super::decode(&paletted).unwrap();
+        assert_eq!(image.pixels, vec![200, 100, 50, 255, 1, 2, 3, 0, 200, 100, 50, 255]);
+
+        let resized: super::Image = image.resize(6, 2);
+        assert_eq!((resized.width, resized.height), (6, 2));
+        assert_eq!(resized.pixel(5, 1), [200, 100, 50, 255]);
+        assert_eq!(resized.pixel(2, 0), [1, 2, 3, 0]);
+
+        assert!(super::decode(b"Not a PNG").is_err());
+    }
+}
@@ -88,10 +88,18 @@ impl ProcessInfo {
             None => {
                 match self.get_exe(true) {
                     Ok(r) => {
-                        self.process_name = Some(r.split('/').last().unwrap().to_string());
+                        self.process_name = Some(r.split('/').next_back().unwrap().to_string());
                         Ok(self.process_name.as_ref().unwrap().to_string())
                     },
-                    Err(e) => Err(format!("Unable to get exe path: {}", e))
+                    // Processes run as other users (e.g through sudo) won't let us read their exe,
+                    // but the name's still in their stat, albeit cut down to 15 characters
+                    Err(e) => match self.get_stat() {
+                        Ok(r) => {
+                            self.process_name = Some(r.comm.trim_start_matches('(').trim_end_matches(')').to_string());
+                            Ok(self.process_name.as_ref().unwrap().to_string())
+                        },
+                        Err(_) => Err(format!("Unable to get exe path: {}", e)),
+                    }
                 }
             }
         }
@@ -143,10 +151,7 @@ impl ProcessInfo {
     }
 
     pub fn get_parent_process(&mut self) -> Result<ProcessInfo, String> {
-        let pid: u32 = match self.get_parent_pid() {
-            Ok(r) => r,
-            Err(e) => return Err(e),
-        };
+        let pid: u32 = self.get_parent_pid()?;
 
         let process: ProcessInfo = ProcessInfo::new(pid);
         if !process.is_valid() {
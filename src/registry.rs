@@ -0,0 +1,293 @@
+// The table of every module we can run, built once at startup
+// Each module registers how it's detected and how it's displayed, so the main loop, JSON output
+// and --strict only need to look modules up by name rather than knowing about each one
+//
+// Results are kept once detected, so anything else needing them (e.g the ASCII wanting the
+// distro) doesn't do the work twice
+
+use std::any::Any;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{config_manager::Configuration, module::{Module, ModuleError, ModuleWarning}, package_managers::ManagerInfo, syscalls::SyscallCache};
+use crate::modules::{audiodevices::{self, AudioDeviceInfo}, battery::{self, BatteryInfo}, camera::{self, CameraInfo}, cpu::{self, CPUInfo}, datetime::{self, DateTimeInfo}, desktop::{self, DesktopInfo}, displays::{self, DisplayInfo}, editor::{self, EditorInfo}, envvar::{self, EnvVarInfo}, gpu::{self, GPUInfo}, host::{self, HostInfo}, hostname::{self, HostnameInfo}, initsys::{self, InitSystemInfo}, keys::{self, KeysInfo}, locale::{self, LocaleInfo}, localip::{self, LocalIPInfo}, memory::{self, MemoryInfo}, mounts::{self, MountInfo}, os::{self, OSInfo}, packages::{self, PackagesInfo}, printers::{self, PrinterInfo}, processes::{self, ProcessesInfo}, publicip::{self, PublicIPInfo}, services::{self, ServicesInfo}, shell::{self, ShellInfo}, swap::{self, SwapInfo}, terminal::{self, TerminalInfo}, uptime::{self, UptimeInfo}};
+#[cfg(feature = "player")]
+use crate::modules::player::{self, PlayerInfo};
+
+// Everything a module might need while detecting
+pub struct ModuleContext<'a> {
+    pub config: &'a Configuration,
+    pub package_managers: &'a ManagerInfo,
+    pub syscall_cache: &'a mut SyscallCache,
+    pub warnings: &'a mut Vec<ModuleWarning>,
+    // Whatever came after the : in the module's name, e.g the variable in env:HOME
+    pub argument: &'a str,
+}
+
+type Detect<T> = fn(&mut ModuleContext) -> Result<T, ModuleError>;
+type Render<T> = fn(&T, &Configuration) -> Vec<(String, String)>;
+
+pub trait RegisteredModule {
+    // Detects the module if it hasn't been already, and gives back the lines to display
+    fn run(&mut self, context: &mut ModuleContext, log_errors: bool) -> Vec<(String, String)>;
+    // Null if it's not been ran with that argument, or failed
+    fn to_json(&self, argument: &str) -> Value;
+    fn failed(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+struct CachedModule<T> {
+    detect: Detect<T>,
+    render: Render<T>,
+    unknown: fn(&Configuration) -> (String, String),
+    // Modules that depend on their argument keep a result for each one, e.g each env:<VAR>
+    per_argument: bool,
+    results: Vec<(String, Result<T, ModuleError>)>,
+}
+impl<T> CachedModule<T> {
+    fn result(&self, argument: &str) -> Option<&Result<T, ModuleError>> {
+        let argument: &str = if self.per_argument { argument } else { "" };
+        self.results.iter().find(|x| x.0 == argument).map(|x| &x.1)
+    }
+}
+impl<T: Serialize + 'static> RegisteredModule for CachedModule<T> {
+    fn run(&mut self, context: &mut ModuleContext, log_errors: bool) -> Vec<(String, String)> {
+        if self.result(context.argument).is_none() {
+            let argument: String = if self.per_argument { context.argument.to_string() } else { String::new() };
+            let result: Result<T, ModuleError> = (self.detect)(context);
+            self.results.push((argument, result));
+        }
+        match self.result(context.argument).unwrap() {
+            Ok(r) => (self.render)(r, context.config),
+            Err(e) => {
+                if log_errors {
+                    vec![(String::new(), e.to_string())]
+                } else {
+                    vec![(self.unknown)(context.config)]
+                }
+            },
+        }
+    }
+
+    fn to_json(&self, argument: &str) -> Value {
+        match self.result(argument) {
+            Some(Ok(r)) => serde_json::to_value(r).unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.results.iter().any(|x| x.1.is_err())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct RegistryEntry {
+    name: &'static str,
+    // What it shows up as in --benchmark
+    bench_name: &'static str,
+    module: Box<dyn RegisteredModule>,
+}
+
+pub struct ModuleRegistry {
+    entries: Vec<RegistryEntry>,
+}
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    // Every module that's built in to CrabFetch
+    pub fn builtin() -> Self {
+        let mut registry: ModuleRegistry = Self::new();
+        registry.register::<HostnameInfo>("hostname", "Hostname Module", |c| hostname::get_hostname(c.config, c.syscall_cache), |x, c| vec![x.style(c)]);
+        registry.register::<CPUInfo>("cpu", "CPU Module", |c| cpu::get_cpu(c.config), |x, c| vec![x.style(c)]);
+        registry.register_multiline::<GPUInfo>("gpu", "GPU Module", |c| gpu::get_gpus(c.config), |x, c| {
+            x.iter().enumerate().map(|(index, gpu)| {
+                let mut gpu: GPUInfo = gpu.clone();
+                gpu.set_index(index as u8 + 1);
+                gpu.style(c)
+            }).collect()
+        });
+        registry.register::<MemoryInfo>("memory", "Memory Module", |c| memory::get_memory(c.config), |x, c| {
+            if c.memory.per_node && !x.nodes().is_empty() {
+                x.nodes().iter().map(|node| node.style(c)).collect()
+            } else {
+                vec![x.style(c)]
+            }
+        });
+        registry.register::<SwapInfo>("swap", "Swap Module", |c| swap::get_swap(c.syscall_cache), |x, c| vec![x.style(c)]);
+        registry.register_multiline::<MountInfo>("mounts", "Mounts Module", |c| mounts::get_mounted_drives(c.config, c.warnings), |x, c| {
+            x.iter().filter(|mount| !mount.is_ignored(c)).map(|mount| mount.style(c)).collect()
+        });
+        registry.register::<HostInfo>("host", "Host Module", |c| host::get_host(c.config), |x, c| {
+            let mut lines: Vec<(String, String)> = vec![x.style(c)];
+            if c.host.newline_chassis {
+                lines.push(x.style_chassis(c));
+            }
+            lines
+        });
+        registry.register_multiline::<DisplayInfo>("displays", "Displays Module", |c| displays::get_displays(c.config, c.warnings).map_err(ModuleError::from), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register::<OSInfo>("os", "OS Module", |c| os::get_os(c.config, c.syscall_cache), |x, c| {
+            let mut lines: Vec<(String, String)> = vec![x.style(c)];
+            if c.os.newline_kernel {
+                lines.push(x.style_kernel(c));
+            }
+            lines
+        });
+        registry.register::<PackagesInfo>("packages", "Packages Module", |c| Ok(packages::get_packages(c.package_managers, c.config)), |x, c| vec![x.style(c)]);
+        registry.register::<DesktopInfo>("desktop", "Desktop Module", |c| desktop::get_desktop(c.config), |x, c| vec![x.style(c)]);
+        registry.register::<TerminalInfo>("terminal", "Terminal Module", |c| terminal::get_terminal(c.config, c.package_managers), |x, c| vec![x.style(c)]);
+        registry.register::<ShellInfo>("shell", "Shell Module", |c| shell::get_shell(c.config, c.package_managers), |x, c| vec![x.style(c)]);
+        registry.register_multiline::<BatteryInfo>("battery", "Battery Module", |_| battery::get_batteries(), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register::<UptimeInfo>("uptime", "Uptime Module", |c| uptime::get_uptime(c.syscall_cache), |x, c| vec![x.style(c)]);
+        registry.register::<LocaleInfo>("locale", "Locale Module", |_| locale::get_locale(), |x, c| vec![x.style(c)]);
+        #[cfg(feature = "player")]
+        registry.register_multiline::<PlayerInfo>("player", "Player Module", |c| player::get_players(c.config), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register::<EditorInfo>("editor", "Editor Module", |c| editor::get_editor(c.config, c.package_managers), |x, c| vec![x.style(c)]);
+        registry.register::<InitSystemInfo>("initsys", "InitSys Module", |c| initsys::get_init_system(c.config, c.package_managers), |x, c| vec![x.style(c)]);
+        registry.register::<ProcessesInfo>("processes", "Processes Module", |_| processes::get_process_count(), |x, c| vec![x.style(c)]);
+        registry.register::<DateTimeInfo>("datetime", "Datetime Module", |_| Ok(datetime::get_date_time()), |x, c| vec![x.style(c)]);
+        registry.register_multiline::<LocalIPInfo>("localip", "Local IP Module", |c| localip::get_local_ips(c.config), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register::<PublicIPInfo>("publicip", "Public IP Module", |c| publicip::get_public_ip(c.config), |x, c| vec![x.style(c)]);
+        registry.register::<KeysInfo>("keys", "Keys Module", |c| keys::get_keys(c.config), |x, c| vec![x.style(c)]);
+        registry.register::<ServicesInfo>("services", "Services Module", |c| services::get_services(c.config), |x, c| vec![x.style(c)]);
+        registry.register_multiline::<AudioDeviceInfo>("audiodevices", "Audio Devices Module", |c| audiodevices::get_audio_devices(c.config), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register_multiline::<CameraInfo>("camera", "Camera Module", |c| camera::get_cameras(c.config), |x, c| x.iter().map(|y| y.style(c)).collect());
+        registry.register_multiline::<PrinterInfo>("printers", "Printers Module", |c| printers::get_printers(c.config), |x, c| x.iter().map(|y| y.style(c)).collect());
+        // Each env:<VAR> is it's own variable, so each one's detected by itself
+        registry.add(RegistryEntry {
+            name: "env",
+            bench_name: "Env Module",
+            module: Box::new(CachedModule::<EnvVarInfo> {
+                detect: |c| envvar::get_env_var(c.argument),
+                render: |x, c| vec![x.style(c)],
+                unknown: EnvVarInfo::unknown_output,
+                per_argument: true,
+                results: Vec::new(),
+            }),
+        });
+
+        registry
+    }
+
+    pub fn register<T: Module + Serialize + 'static>(&mut self, name: &'static str, bench_name: &'static str, detect: Detect<T>, render: Render<T>) {
+        self.add(RegistryEntry {
+            name,
+            bench_name,
+            module: Box::new(CachedModule {
+                detect,
+                render,
+                unknown: T::unknown_output,
+                per_argument: false,
+                results: Vec::new(),
+            }),
+        });
+    }
+    // For modules that can find more than one of something, e.g GPUs
+    // They still only have the one unknown output if they fail
+    pub fn register_multiline<T: Module + Serialize + 'static>(&mut self, name: &'static str, bench_name: &'static str, detect: Detect<Vec<T>>, render: Render<Vec<T>>) {
+        self.add(RegistryEntry {
+            name,
+            bench_name,
+            module: Box::new(CachedModule {
+                detect,
+                render,
+                unknown: T::unknown_output,
+                per_argument: false,
+                results: Vec::new(),
+            }),
+        });
+    }
+    // Later registrations replace earlier ones of the same name
+    fn add(&mut self, entry: RegistryEntry) {
+        self.entries.retain(|x| x.name != entry.name);
+        self.entries.push(entry);
+    }
+
+    pub fn bench_name(&self, name: &str) -> Option<&'static str> {
+        self.entries.iter().find(|x| x.name == name).map(|x| x.bench_name)
+    }
+
+    // None if there's no module by that name
+    pub fn run(&mut self, name: &str, context: &mut ModuleContext, log_errors: bool) -> Option<Vec<(String, String)>> {
+        let entry: &mut RegistryEntry = self.entries.iter_mut().find(|x| x.name == name)?;
+        Some(entry.module.run(context, log_errors))
+    }
+
+    // Gets a module's result for use elsewhere, detecting it first if it's not been ran yet
+    pub fn detect<T: 'static>(&mut self, name: &str, context: &mut ModuleContext) -> Option<&Result<T, ModuleError>> {
+        let entry: &mut RegistryEntry = self.entries.iter_mut().find(|x| x.name == name)?;
+        let cached: &CachedModule<T> = entry.module.as_any().downcast_ref::<CachedModule<T>>()?;
+        if cached.result(context.argument).is_none() {
+            // Only need it to run, the lines themselves aren't shown
+            entry.module.run(context, false);
+        }
+        entry.module.as_any().downcast_ref::<CachedModule<T>>()?.result(context.argument)
+    }
+
+    // Builds the JSON output from whatever modules have ran
+    // Anything that isn't a module (e.g space, segments) is ignored and failed modules are null
+    // Modules with an argument are keyed by all of it, e.g env:HOME, so each one gets it's own entry
+    pub fn to_json(&self, modules: &[String]) -> Value {
+        let mut map: Map<String, Value> = Map::new();
+        for module in modules {
+            let (module_name, argument): (&str, &str) = module.split_once(':').unwrap_or((module, ""));
+            if let Some(entry) = self.entries.iter().find(|x| x.name == module_name) {
+                map.insert(module.to_string(), entry.module.to_json(argument));
+            }
+        }
+
+        Value::Object(map)
+    }
+
+    // Whether any module we ran failed, used for --strict
+    pub fn has_failures(&self) -> bool {
+        self.failure_count() != 0
+    }
+    pub fn failure_count(&self) -> usize {
+        self.entries.iter().filter(|x| x.module.failed()).count()
+    }
+}
+
+
+mod tests {
+    // Test modules are found by name, and anything else is left for the caller to handle
+    #[test]
+    fn builtin_registry() {
+        let registry: super::ModuleRegistry = super::ModuleRegistry::builtin();
+        assert_eq!(registry.bench_name("cpu"), Some("CPU Module"));
+        assert_eq!(registry.bench_name("env"), Some("Env Module"));
+        assert_eq!(registry.bench_name("space"), None);
+        assert!(!registry.has_failures());
+    }
+    // Test each env:<VAR> keeps it's own result, rather than the last one replacing the rest
+    #[test]
+    fn json_per_argument() {
+        let config: crate::config_manager::Configuration = crate::config_manager::parse(&None, &None, &true).unwrap();
+        let package_managers: crate::package_managers::ManagerInfo = crate::package_managers::ManagerInfo::new();
+        let mut syscall_cache: crate::syscalls::SyscallCache = crate::syscalls::SyscallCache::new();
+        let mut warnings: Vec<crate::module::ModuleWarning> = Vec::new();
+        let mut registry: super::ModuleRegistry = super::ModuleRegistry::builtin();
+        for argument in ["PATH", "CRABFETCH_TEST_UNSET_VAR"] {
+            let mut context: super::ModuleContext = super::ModuleContext {
+                config: &config,
+                package_managers: &package_managers,
+                syscall_cache: &mut syscall_cache,
+                warnings: &mut warnings,
+                argument,
+            };
+            registry.run("env", &mut context, false);
+        }
+
+        let json: serde_json::Value = registry.to_json(&["env:PATH".to_string(), "env:CRABFETCH_TEST_UNSET_VAR".to_string()]);
+        assert_eq!(json["env:PATH"]["name"], "PATH");
+        assert_eq!(json["env:CRABFETCH_TEST_UNSET_VAR"]["name"], "CRABFETCH_TEST_UNSET_VAR");
+    }
+}
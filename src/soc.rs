@@ -0,0 +1,108 @@
+// SoC's, where the CPU and GPU are in the same package, and so are really known by the one name
+// Shared between the CPU and GPU modules
+
+use std::path::Path;
+
+#[cfg(feature = "android")]
+use {android_system_properties::AndroidSystemProperties, std::env};
+
+use crate::util;
+
+// Devicetree lists what the board's compatible with from most to least specific, and one of those is the SoC
+// e.g "raspberrypi,5-model-b\0brcm,bcm2712\0"
+pub fn soc_name() -> Option<String> {
+    #[cfg(feature = "android")]
+    if env::consts::OS == "android" {
+        let props = AndroidSystemProperties::new();
+        if let Some(r) = props.get("ro.soc.model").and_then(|x| lookup_soc(&x)) {
+            return Some(r.to_string());
+        }
+    }
+
+    let compatible: String = util::file_read(Path::new("/proc/device-tree/compatible")).ok()?;
+    compatible.split('\0').find_map(lookup_soc).map(|x| x.to_string())
+}
+// Takes either a full compatible string, or just the model on it's own as Android gives it, e.g "SM8550"
+fn lookup_soc(id: &str) -> Option<&'static str> {
+    SOC_LOOKUP.iter()
+        .find(|x| x.0 == id || (!id.contains(',') && x.0.split_once(',').is_some_and(|y| y.1.eq_ignore_ascii_case(id))))
+        .map(|x| x.1)
+}
+
+// Devicetree compatible strings, from the kernel's dts files
+const SOC_LOOKUP: [(&str, &str); 50] = [
+    // Apple, under Asahi
+    ("apple,t8103", "Apple M1"),
+    ("apple,t6000", "Apple M1 Pro"),
+    ("apple,t6001", "Apple M1 Max"),
+    ("apple,t6002", "Apple M1 Ultra"),
+    ("apple,t8112", "Apple M2"),
+    ("apple,t6020", "Apple M2 Pro"),
+    ("apple,t6021", "Apple M2 Max"),
+    ("apple,t6022", "Apple M2 Ultra"),
+    // Broadcom, mostly Raspberry Pi's
+    ("brcm,bcm2835", "BCM2835"),
+    ("brcm,bcm2836", "BCM2836"),
+    ("brcm,bcm2837", "BCM2837"),
+    ("brcm,bcm2711", "BCM2711"),
+    ("brcm,bcm2712", "BCM2712"),
+    // Qualcomm
+    ("qcom,sdm845", "Snapdragon 845"),
+    ("qcom,sm8150", "Snapdragon 855"),
+    ("qcom,sm8250", "Snapdragon 865"),
+    ("qcom,sm8350", "Snapdragon 888"),
+    ("qcom,sm8450", "Snapdragon 8 Gen 1"),
+    ("qcom,sm8475", "Snapdragon 8+ Gen 1"),
+    ("qcom,sm8550", "Snapdragon 8 Gen 2"),
+    ("qcom,sm8650", "Snapdragon 8 Gen 3"),
+    ("qcom,sm8750", "Snapdragon 8 Elite"),
+    ("qcom,sm7325", "Snapdragon 778G"),
+    ("qcom,sm6375", "Snapdragon 695"),
+    ("qcom,sc7180", "Snapdragon 7c"),
+    ("qcom,sc7280", "Snapdragon 7c+ Gen 3"),
+    ("qcom,sc8180x", "Snapdragon 8cx"),
+    ("qcom,sc8280xp", "Snapdragon 8cx Gen 3"),
+    ("qcom,x1e80100", "Snapdragon X Elite"),
+    // Rockchip
+    ("rockchip,rk3328", "RK3328"),
+    ("rockchip,rk3399", "RK3399"),
+    ("rockchip,rk3566", "RK3566"),
+    ("rockchip,rk3568", "RK3568"),
+    ("rockchip,rk3588", "RK3588"),
+    ("rockchip,rk3588s", "RK3588S"),
+    // Allwinner
+    ("allwinner,sun8i-h3", "Allwinner H3"),
+    ("allwinner,sun50i-a64", "Allwinner A64"),
+    ("allwinner,sun50i-h6", "Allwinner H6"),
+    ("allwinner,sun50i-h616", "Allwinner H616"),
+    // Amlogic
+    ("amlogic,meson-gxl", "Amlogic S905X"),
+    ("amlogic,meson-sm1", "Amlogic S905X3"),
+    ("amlogic,meson-g12b", "Amlogic S922X"),
+    // MediaTek
+    ("mediatek,mt8183", "MediaTek Kompanio 500"),
+    ("mediatek,mt8195", "MediaTek Kompanio 1380"),
+    // NVIDIA
+    ("nvidia,tegra210", "NVIDIA Tegra X1"),
+    ("nvidia,tegra194", "NVIDIA Jetson Xavier"),
+    ("nvidia,tegra234", "NVIDIA Jetson Orin"),
+    // Google
+    ("google,gs101", "Google Tensor"),
+    ("google,gs201", "Google Tensor G2"),
+    // Samsung
+    ("samsung,exynos850", "Exynos 850"),
+];
+
+
+mod tests {
+    // Test SoCs are found from devicetree compatible strings or just their model
+    #[test]
+    fn soc_lookup() {
+        let compatible: &str = "raspberrypi,5-model-b\0brcm,bcm2712\0";
+        assert_eq!(compatible.split('\0').find_map(super::lookup_soc), Some("BCM2712"));
+        assert_eq!("apple,j274\0apple,t8103\0apple,arm-platform\0".split('\0').find_map(super::lookup_soc), Some("Apple M1"));
+        assert_eq!(super::lookup_soc("SM8550"), Some("Snapdragon 8 Gen 2"));
+        assert_eq!(super::lookup_soc("vendor,unknown-board"), None);
+        assert_eq!(super::lookup_soc(""), None);
+    }
+}
@@ -1,6 +1,6 @@
 // Some utility functions
 
-use std::{ffi::{c_char, CStr}, fs::File, io::Read, path::{Path, PathBuf}};
+use std::{collections::HashMap, env, ffi::{c_char, CStr}, fs::{File, OpenOptions}, io::{Read, Write}, os::fd::AsRawFd, path::{Path, PathBuf}, process::{Child, Command, Stdio}, thread, time::{Duration, Instant}};
 
 /// Quickly reads the full contents of a specified file using [File::open] and [File::read_to_string]
 /// Don't use this for medium to large sized files, for performance reasons please use a buffer instead.
@@ -76,3 +76,213 @@ pub fn in_wsl() -> bool {
     // Using the first method
     Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
 }
+
+/// Checks if `path` failed to read because we don't have permission to, rather than it not
+/// existing or anything else
+pub fn is_permission_denied(path: &Path) -> bool {
+    match File::open(path) {
+        Ok(_) => false,
+        Err(e) => e.kind() == std::io::ErrorKind::PermissionDenied,
+    }
+}
+
+/// Works out if we're running inside a container, and if so what made it; `distrobox`, `toolbox`,
+/// `podman` or `docker`.
+/// Returns [None] if we're not in one, or can't tell.
+pub fn detect_container() -> Option<&'static str> {
+    if Path::new("/run/.toolboxenv").exists() {
+        return Some("toolbox");
+    }
+    // Podman makes .containerenv for us, distrobox then sets this on top of it
+    if Path::new("/run/.containerenv").exists() {
+        if env::var("CONTAINER_ID").is_ok() || env::var("DISTROBOX_ENTER_PATH").is_ok() {
+            return Some("distrobox");
+        }
+        return Some("podman");
+    }
+    if Path::new("/.dockerenv").exists() {
+        return Some("docker");
+    }
+
+    None
+}
+
+/// The name of the container we're running in, as given to distrobox/toolbox/podman.
+/// Returns [None] if we're not in a container, or it doesn't tell us it's name.
+pub fn container_name() -> Option<String> {
+    // Distrobox sets this one for us
+    if let Ok(r) = env::var("CONTAINER_ID") {
+        return Some(r);
+    }
+    let contents: String = file_read(Path::new("/run/.containerenv")).ok()?;
+    let name: &str = contents.lines().find_map(|x| x.strip_prefix("name="))?;
+
+    Some(name.trim_matches('"').to_string())
+}
+
+/// Where the host's filesystem is mounted, if we're in a container that mounts it.
+/// Both distrobox and toolbox put it at `/run/host`
+pub fn host_root() -> Option<PathBuf> {
+    detect_container()?;
+    let root: PathBuf = PathBuf::from("/run/host");
+    if !root.join("etc").exists() {
+        return None;
+    }
+
+    Some(root)
+}
+
+/// The width of the terminal in columns, or [None] if stdout isn't one.
+pub fn terminal_columns() -> Option<u16> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let success: bool = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if !success || size.ws_col == 0 {
+        return None;
+    }
+
+    Some(size.ws_col)
+}
+
+/// Sends `query` to the terminal over `/dev/tty` and returns it's reply.
+/// Reading stops once `is_done` is happy with what's come back, or after `timeout` ms in total, as
+/// terminals that don't understand the query just won't reply.
+/// Returns [None] if there's no terminal or it didn't reply at all.
+pub fn query_terminal(query: &str, timeout: u64, is_done: impl Fn(&[u8]) -> bool) -> Option<String> {
+    let mut tty: File = OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    let fd: i32 = tty.as_raw_fd();
+
+    // Raw mode, otherwise the reply gets echoed and we can't read it until a newline
+    let original: libc::termios = unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return None;
+        }
+        termios
+    };
+    let mut raw: libc::termios = original;
+    raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+        return None;
+    }
+
+    let mut reply: Vec<u8> = Vec::new();
+    if tty.write_all(query.as_bytes()).is_ok() && tty.flush().is_ok() {
+        let timeout: Duration = Duration::from_millis(timeout);
+        let start: Instant = Instant::now();
+        let mut buffer: [u8; 64] = [0; 64];
+        while let Some(remaining) = timeout.checked_sub(start.elapsed()) {
+            let mut poll: libc::pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+            if unsafe { libc::poll(&mut poll, 1, remaining.as_millis() as i32) } <= 0 {
+                break;
+            }
+            let read: usize = match tty.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(r) => r,
+            };
+            reply.extend_from_slice(&buffer[..read]);
+            if is_done(&reply) {
+                break;
+            }
+        }
+    }
+
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    if reply.is_empty() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&reply).to_string())
+}
+
+/// Pipes `value` through `sh -c command` and returns whatever it prints, minus the trailing newline.
+/// `envs` are set for the command on top of our own environment.
+/// The command gets killed if it takes longer than `timeout` ms.
+/// Errors if it can't be ran, times out or exits non-zero, so the caller can fall back to the original value.
+pub fn run_post_command(command: &str, value: &str, envs: &[(&str, String)], timeout: u64) -> Result<String, String> {
+    let mut child: Child = match Command::new("sh")
+        .args(["-c", command])
+        .envs(envs.iter().map(|x| (x.0, x.1.as_str())))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn() {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Failed to run '{}': {}", command, e)),
+        };
+
+    // Dropped straight after so the command sees EOF
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(value.as_bytes());
+    }
+
+    wait_with_timeout(child, command, timeout)
+}
+
+/// Runs `program` with `args` and returns whatever it prints, minus the trailing newline.
+/// The same as run_post_command, but without going through the shell or giving it any input.
+pub fn run_command(program: &str, args: &[&str], timeout: u64) -> Result<String, String> {
+    let child: Child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn() {
+            Ok(r) => r,
+            Err(e) => return Err(format!("Failed to run '{}': {}", program, e)),
+        };
+
+    wait_with_timeout(child, program, timeout)
+}
+
+// Kills the child if it's still going after `timeout` ms, otherwise hands back it's output
+fn wait_with_timeout(mut child: Child, command: &str, timeout: u64) -> Result<String, String> {
+    let start: Instant = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    return Err(format!("'{}' exited with {}", command, status));
+                }
+                break;
+            },
+            Ok(None) => {
+                if start.elapsed() > Duration::from_millis(timeout) {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("'{}' timed out after {}ms", command, timeout));
+                }
+                thread::sleep(Duration::from_millis(2));
+            },
+            Err(e) => return Err(format!("Failed to wait on '{}': {}", command, e)),
+        }
+    }
+
+    let mut output: String = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        if let Err(e) = stdout.read_to_string(&mut output) {
+            return Err(format!("Failed to read output of '{}': {}", command, e));
+        }
+    }
+
+    Ok(output.trim_end_matches('\n').to_string())
+}
+
+/// Reads the current logind session's info from `/run/systemd/sessions/{id}`, e.g it's `TYPE`,
+/// `DESKTOP` or `SEAT`.
+/// The session is found from `$XDG_SESSION_ID`, falling back to `/proc/self/sessionid`.
+/// Returns [None] if we're not in a session or logind isn't running.
+pub fn logind_session() -> Option<HashMap<String, String>> {
+    let session_id: String = match env::var("XDG_SESSION_ID") {
+        Ok(r) => r,
+        Err(_) => file_read(Path::new("/proc/self/sessionid")).ok()?.trim().to_string(),
+    };
+    // The kernel reports this if we're not in a session at all
+    if session_id.is_empty() || session_id == "4294967295" {
+        return None;
+    }
+
+    let contents: String = file_read(&Path::new("/run/systemd/sessions").join(session_id)).ok()?;
+    Some(contents.lines()
+        .filter_map(|x| x.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect())
+}
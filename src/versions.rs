@@ -1,9 +1,9 @@
 // Purely handles version detection
-use std::{env, fs, process::Command};
+use std::{collections::BTreeMap, env, fs::{self, File}, io::Write, path::{Path, PathBuf}, process::Command};
 
 use sha2::{Sha256, Digest};
 
-use crate::{modules::shell::KNOWN_SHELLS, package_managers::ManagerInfo, proccess_info::ProcessInfo};
+use crate::{config_manager, modules::{shell::KNOWN_SHELLS, terminal::KNOWN_TERMS}, package_managers::ManagerInfo, proccess_info::ProcessInfo, util};
 
 pub fn find_version(exe_path: &str, name: Option<&str>, use_checksums: bool, package_managers: &ManagerInfo) -> Option<String> {
     // Steps;
@@ -11,7 +11,7 @@ pub fn find_version(exe_path: &str, name: Option<&str>, use_checksums: bool, pac
     // If not (or not found), check the known checksums 
     // If not found either, ONLY THEN go to {command} --version parsing 
 
-    let name: &str = name.unwrap_or(exe_path.split('/').last().unwrap());
+    let name: &str = name.unwrap_or(exe_path.split('/').next_back().unwrap());
 
     // We'll try app specific stuff first 
     let app_specific: Option<String> = match name {
@@ -55,6 +55,15 @@ fn use_package_manager(name: &str, package_managers: &ManagerInfo) -> Option<Str
     None
 }
 fn match_checksum(path: &str) -> Option<String> {
+    let hash: String = hash_file(path)?;
+
+    // The user's own ones take priority, as they're more likely to be up to date
+    if let Some((version, _)) = read_user_checksums().get(&hash) {
+        return Some(version.to_string());
+    }
+    compare_hash(&hash)
+}
+fn hash_file(path: &str) -> Option<String> {
     // Read all the byte of that file
     let file_bytes: Vec<u8> = match fs::read(path) {
         Ok(r) => r,
@@ -63,8 +72,8 @@ fn match_checksum(path: &str) -> Option<String> {
 
     let mut hasher = Sha256::new();
     hasher.update(file_bytes);
-    
-    compare_hash(&hex::encode(hasher.finalize()))
+
+    Some(hex::encode(hasher.finalize()))
 }
 fn parse_command(path: &str, name: &str) -> Option<String> {
     // uhoh, expect shitty performance
@@ -111,14 +120,11 @@ fn parse_command(path: &str, name: &str) -> Option<String> {
         "vim" => Some(raw.split(' ').collect::<Vec<&str>>()[4].to_string()),
         "nvim" => Some(raw.split(' ').collect::<Vec<&str>>()[1].split('\n').next().unwrap()[1..].to_string()),
         // Init Systems
-        "systemd" => Some(raw.split(' ').collect::<Vec<&str>>()[2].split('\n').next().unwrap().trim_matches(&['(', ')']).to_string()),
+        "systemd" => Some(raw.split(' ').collect::<Vec<&str>>()[2].split('\n').next().unwrap().trim_matches(['(', ')']).to_string()),
 
         _ => {
             let raw_split: Vec<&str> = raw.split(' ').collect();
-            let attempted_ver: &str = match raw_split.get(1) {
-                Some(r) => r,
-                None => return None,
-            };
+            let attempted_ver: &str = raw_split.get(1)?;
             Some(attempted_ver.to_string())
         },
     }
@@ -137,6 +143,134 @@ fn substitite_package_name(name: &str) -> &str {
 }
 
 
+// User Hashes
+// These live in {config dir}/checksums, one per line as "{hash} {version} {name}"
+// The name's only there to make it readable, it's not used for matching
+fn read_user_checksums() -> BTreeMap<String, (String, String)> {
+    let mut checksums: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let contents: String = match config_manager::checksums_path() {
+        Some(r) => util::file_read(&r).unwrap_or_default(),
+        None => return checksums,
+    };
+    parse_user_checksums(&contents, &mut checksums);
+
+    checksums
+}
+fn parse_user_checksums(contents: &str, checksums: &mut BTreeMap<String, (String, String)>) {
+    for line in contents.lines() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let values: Vec<&str> = line.split_whitespace().collect();
+        if values.len() < 2 {
+            continue;
+        }
+        let name: &str = values.get(2).unwrap_or(&"");
+        checksums.insert(values[0].to_string(), (values[1].to_string(), name.to_string()));
+    }
+}
+
+// Hashes everything we know about that's installed, and adds it to the user's checksums
+// Anything in `extra` is also added, in the form of "{path}" or "{path}={version}"
+pub fn update_checksums(extra: &[String], package_managers: &ManagerInfo) {
+    let path: PathBuf = match config_manager::checksums_path() {
+        Some(r) => r,
+        None => panic!("Unable to find config directory, is $HOME set?"),
+    };
+    let mut checksums: BTreeMap<String, (String, String)> = read_user_checksums();
+    let existing: usize = checksums.len();
+
+    // Known stuff first
+    // This only goes off the package manager, as running --version on a terminal we're not in
+    // could just open a new window
+    let mut names: Vec<String> = KNOWN_TERMS.iter().chain(KNOWN_SHELLS.iter()).map(|x| x.to_string()).collect();
+    if let Ok(editor) = env::var("EDITOR") {
+        names.push(editor.split('/').next_back().unwrap_or(&editor).to_string());
+    }
+    for name in names {
+        let exe_path: String = match find_in_path(&name) {
+            Some(r) => r,
+            None => continue,
+        };
+        let version: String = match use_package_manager(substitite_package_name(&name), package_managers) {
+            Some(r) => r,
+            None => continue,
+        };
+        if let Some(hash) = hash_file(&exe_path) {
+            println!("{} {} ({})", name, version, exe_path);
+            checksums.insert(hash, (version, name));
+        }
+    }
+
+    // Then anything the user's asked for themselves
+    for entry in extra {
+        let (exe_path, version): (&str, Option<&str>) = match entry.split_once('=') {
+            Some((p, v)) => (p, Some(v)),
+            None => (entry, None),
+        };
+        let exe_path: String = match fs::canonicalize(shellexpand::tilde(exe_path).to_string()) {
+            Ok(r) => r.display().to_string(),
+            Err(e) => {
+                eprintln!("Unable to find {}: {}", exe_path, e);
+                continue;
+            },
+        };
+        let name: String = exe_path.split('/').next_back().unwrap().to_string();
+        let version: String = match version {
+            Some(r) => r.to_string(),
+            None => match find_version(&exe_path, Some(&name), false, package_managers) {
+                Some(r) => r,
+                None => {
+                    eprintln!("Unable to find the version of {}, pass it in with {}=<version>", exe_path, exe_path);
+                    continue;
+                },
+            },
+        };
+        match hash_file(&exe_path) {
+            Some(hash) => {
+                println!("{} {} ({})", name, version, exe_path);
+                checksums.insert(hash, (version, name));
+            },
+            None => eprintln!("Unable to read {}", exe_path),
+        }
+    }
+
+    write_user_checksums(&path, &checksums);
+    println!("Saved {} checksums ({} new) to {}", checksums.len(), checksums.len() - existing, path.display());
+}
+fn write_user_checksums(path: &Path, checksums: &BTreeMap<String, (String, String)>) {
+    let mut contents: String = String::from("# CrabFetch version checksums, used when use_version_checksums is on\n# Each line is \"{sha256 hash} {version} {name}\". Update these with crabfetch --update-checksums\n");
+    // Sorted by name to keep it readable, rather than by hash
+    let mut entries: Vec<(&String, &(String, String))> = checksums.iter().collect();
+    entries.sort_by(|a, b| a.1.1.cmp(&b.1.1).then(a.1.0.cmp(&b.1.0)));
+    for (hash, (version, name)) in entries {
+        contents.push_str(&format!("{} {} {}\n", hash, version, name));
+    }
+
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            panic!("Unable to create directory: {}", e);
+        }
+    }
+    let mut file: File = match File::create(path) {
+        Ok(r) => r,
+        Err(e) => panic!("Unable to create file; {}", e),
+    };
+    if let Err(e) = file.write_all(contents.as_bytes()) {
+        panic!("Unable to write to file; {}", e);
+    }
+}
+fn find_in_path(name: &str) -> Option<String> {
+    let path: String = env::var("PATH").ok()?;
+    path.split(':')
+        .map(|x| Path::new(x).join(name))
+        .find(|x| x.is_file())
+        .and_then(|x| fs::canonicalize(x).ok())
+        .map(|x| x.display().to_string())
+}
+
+
 // Known Hashes
 // Please contribute these so I'm not mind-numbingly doing these
 // 
@@ -197,14 +331,26 @@ fn bash_version() -> Option<String> {
     }
 }
 fn zsh_version() -> Option<String> {
-    match env::var("ZSH_VERSION") {
-        Ok(r) => Some(r),
-        Err(_) => None,
-    }
+    env::var("ZSH_VERSION").ok()
 }
 fn fish_version() -> Option<String> {
-    match env::var("FISH_VERSION") {
-        Ok(r) => Some(r),
-        Err(_) => None,
+    env::var("FISH_VERSION").ok()
+}
+
+
+mod tests {
+    // Test the user's checksum file is read correctly, comments and all
+    #[test]
+    fn user_checksums() {
+        let contents: &str = "# A comment
+bfc1a826895089928bd40eb09a340c6f3b6eb22d51589ca32c032761ff44843b 0.35.2 kitty
+
+7ac8cc89b75b595955ec56d8e4b6047c2fc233a6a10c81a137c8417d17a9a970 5.9
+broken";
+        let mut checksums: std::collections::BTreeMap<String, (String, String)> = std::collections::BTreeMap::new();
+        super::parse_user_checksums(contents, &mut checksums);
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(checksums["bfc1a826895089928bd40eb09a340c6f3b6eb22d51589ca32c032761ff44843b"], ("0.35.2".to_string(), "kitty".to_string()));
+        assert_eq!(checksums["7ac8cc89b75b595955ec56d8e4b6047c2fc233a6a10c81a137c8417d17a9a970"], ("5.9".to_string(), String::new()));
     }
 }